@@ -0,0 +1,6 @@
+ALTER TABLE qr_code
+ADD foreground_color VARCHAR(7) NOT NULL DEFAULT '#000000',
+ADD background_color VARCHAR(7) NOT NULL DEFAULT '#ffffff',
+ADD module_shape TEXT NOT NULL DEFAULT 'square',
+ADD quiet_zone INTEGER NOT NULL DEFAULT 4,
+ADD show_logo BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,6 @@
+CREATE TABLE account_logo (
+    account_id UUID PRIMARY KEY REFERENCES account (id),
+    content_type TEXT NOT NULL,
+    data BYTEA NOT NULL,
+    uploaded_at TIMESTAMP NOT NULL
+);
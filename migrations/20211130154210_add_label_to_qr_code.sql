@@ -0,0 +1,2 @@
+ALTER TABLE qr_code
+ADD label VARCHAR(320);
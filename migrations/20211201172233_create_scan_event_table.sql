@@ -0,0 +1,10 @@
+CREATE TABLE scan_event (
+    id UUID PRIMARY KEY,
+    qr_code_id UUID NOT NULL REFERENCES qr_code (id) ON DELETE CASCADE,
+    scanned_at TIMESTAMP NOT NULL,
+    user_agent TEXT,
+    referrer TEXT,
+    client_hash TEXT
+);
+
+CREATE INDEX scan_event_qr_code_id_scanned_at_idx ON scan_event (qr_code_id, scanned_at);
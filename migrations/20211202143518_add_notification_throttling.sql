@@ -0,0 +1,7 @@
+ALTER TABLE qr_code
+ADD code_cooldown_seconds INTEGER NOT NULL DEFAULT 300,
+ADD client_cooldown_seconds INTEGER NOT NULL DEFAULT 900;
+
+ALTER TABLE scan_event
+ADD notified BOOLEAN NOT NULL DEFAULT false,
+ADD throttled BOOLEAN NOT NULL DEFAULT false;
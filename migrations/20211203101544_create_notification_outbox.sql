@@ -0,0 +1,27 @@
+CREATE TABLE notification (
+    id UUID PRIMARY KEY,
+    qr_code_id UUID NOT NULL REFERENCES qr_code (id) ON DELETE CASCADE,
+    scan_event_id UUID REFERENCES scan_event (id) ON DELETE SET NULL,
+    channel TEXT NOT NULL,
+    recipient TEXT NOT NULL,
+    message TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP NOT NULL,
+    last_error TEXT,
+    created_at TIMESTAMP NOT NULL,
+    delivered_at TIMESTAMP
+);
+
+CREATE INDEX notification_pending_idx ON notification (next_attempt_at) WHERE status = 'pending';
+CREATE INDEX notification_qr_code_id_idx ON notification (qr_code_id, created_at);
+
+CREATE TABLE notification_attempt (
+    id UUID PRIMARY KEY,
+    notification_id UUID NOT NULL REFERENCES notification (id) ON DELETE CASCADE,
+    attempted_at TIMESTAMP NOT NULL,
+    succeeded BOOLEAN NOT NULL,
+    error TEXT
+);
+
+CREATE INDEX notification_attempt_notification_id_idx ON notification_attempt (notification_id);
@@ -0,0 +1,2 @@
+ALTER TABLE qr_code
+ADD notification_channel TEXT NOT NULL DEFAULT 'call';
@@ -0,0 +1,14 @@
+CREATE TABLE qr_code_recipient (
+    id UUID PRIMARY KEY,
+    qr_code_id UUID NOT NULL REFERENCES qr_code (id) ON DELETE CASCADE,
+    name TEXT,
+    channel TEXT NOT NULL,
+    address VARCHAR(320) NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT true,
+    quiet_hours_start TIME,
+    quiet_hours_end TIME,
+    utc_offset_minutes INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX qr_code_recipient_qr_code_id_idx ON qr_code_recipient (qr_code_id);
@@ -0,0 +1,5 @@
+ALTER TABLE qr_code
+ADD utc_offset_minutes INTEGER NOT NULL DEFAULT 0;
+
+ALTER TABLE notification
+ADD html_message TEXT;
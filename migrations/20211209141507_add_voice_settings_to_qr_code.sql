@@ -0,0 +1,4 @@
+ALTER TABLE qr_code
+ADD voice TEXT,
+ADD voice_language TEXT,
+ADD voice_loop INTEGER NOT NULL DEFAULT 1;
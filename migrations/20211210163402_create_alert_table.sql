@@ -0,0 +1,26 @@
+CREATE TABLE alert (
+    id UUID PRIMARY KEY,
+    qr_code_id UUID NOT NULL REFERENCES qr_code (id) ON DELETE CASCADE,
+    scan_event_id UUID REFERENCES scan_event (id) ON DELETE SET NULL,
+    message TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'open',
+    created_at TIMESTAMP NOT NULL,
+    escalate_at TIMESTAMP,
+    escalated_at TIMESTAMP,
+    acknowledged_at TIMESTAMP,
+    acknowledged_by TEXT
+);
+
+CREATE INDEX alert_qr_code_id_idx ON alert (qr_code_id, created_at);
+CREATE INDEX alert_escalate_at_idx ON alert (escalate_at) WHERE status = 'open';
+
+ALTER TABLE notification
+ADD alert_id UUID REFERENCES alert (id) ON DELETE CASCADE;
+
+CREATE INDEX notification_alert_id_idx ON notification (alert_id);
+
+ALTER TABLE qr_code
+ADD escalation_timeout_seconds INTEGER;
+
+ALTER TABLE qr_code_recipient
+ADD escalation BOOLEAN NOT NULL DEFAULT false;
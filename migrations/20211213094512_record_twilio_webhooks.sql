@@ -0,0 +1,17 @@
+ALTER TABLE notification
+ADD provider_sid TEXT,
+ADD provider_status TEXT,
+ADD provider_status_at TIMESTAMP;
+
+CREATE INDEX notification_recipient_idx ON notification (recipient, created_at);
+
+CREATE TABLE notification_reply (
+    id UUID PRIMARY KEY,
+    notification_id UUID NOT NULL REFERENCES notification (id) ON DELETE CASCADE,
+    sender TEXT NOT NULL,
+    body TEXT NOT NULL,
+    provider_sid TEXT,
+    received_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX notification_reply_notification_id_idx ON notification_reply (notification_id);
@@ -0,0 +1,22 @@
+CREATE TABLE email_event (
+    id UUID PRIMARY KEY,
+    notification_id UUID REFERENCES notification (id) ON DELETE CASCADE,
+    message_id TEXT,
+    record_type TEXT NOT NULL,
+    recipient TEXT,
+    detail TEXT,
+    received_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX email_event_notification_id_idx ON email_event (notification_id);
+CREATE INDEX notification_provider_sid_idx ON notification (provider_sid);
+
+CREATE TABLE email_suppression (
+    id UUID PRIMARY KEY,
+    account_id UUID NOT NULL REFERENCES account (id) ON DELETE CASCADE,
+    email TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    message_id TEXT,
+    created_at TIMESTAMP NOT NULL,
+    UNIQUE (account_id, email)
+);
@@ -0,0 +1,3 @@
+-- Subject of email notifications
+ALTER TABLE notification
+ADD subject TEXT;
@@ -0,0 +1,7 @@
+CREATE TABLE form_notification_settings (
+    form_id UUID PRIMARY KEY REFERENCES form (id) ON DELETE CASCADE,
+    recipients TEXT[] NOT NULL DEFAULT '{}',
+    include_attachment BOOLEAN NOT NULL DEFAULT FALSE,
+    attachment_format TEXT NOT NULL DEFAULT 'csv',
+    updated_at TIMESTAMP NOT NULL
+);
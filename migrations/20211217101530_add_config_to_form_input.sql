@@ -0,0 +1,17 @@
+ALTER TABLE form_input
+ADD config JSONB NOT NULL DEFAULT '{}';
+
+-- Map the free text types of existing fields onto the supported field types
+UPDATE form_input
+SET type = CASE lower(type)
+    WHEN 'numfield' THEN 'number'
+    WHEN 'numslider' THEN 'slider'
+    ELSE lower(type)
+END;
+
+UPDATE form_input
+SET type = 'text'
+WHERE type NOT IN (
+    'text', 'email', 'phone', 'number', 'slider', 'datetime',
+    'checkbox', 'radio', 'select', 'rating', 'image'
+);
@@ -0,0 +1,23 @@
+CREATE TABLE form_section (
+    id UUID PRIMARY KEY,
+    form_id UUID NOT NULL REFERENCES form (id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    title TEXT,
+    description TEXT,
+    page_break BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX form_section_form_id_idx ON form_section (form_id);
+
+ALTER TABLE form_input
+ADD position INTEGER NOT NULL DEFAULT 0,
+ADD section_id UUID REFERENCES form_section (id) ON DELETE SET NULL;
+
+-- Existing fields have no recorded order, so keep the order their rows were stored in
+UPDATE form_input
+SET position = numbered.position
+FROM (
+    SELECT id, ROW_NUMBER() OVER (PARTITION BY form_id ORDER BY ctid) - 1 AS position
+    FROM form_input
+) numbered
+WHERE form_input.id = numbered.id;
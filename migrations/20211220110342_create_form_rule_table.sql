@@ -0,0 +1,14 @@
+CREATE TABLE form_rule (
+    id UUID PRIMARY KEY,
+    form_id UUID NOT NULL REFERENCES form (id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    -- Field that `show` and `hide` rules apply to
+    target_field_id UUID REFERENCES form_input (id) ON DELETE CASCADE,
+    -- Section that `skip` rules skip ahead to
+    target_section_id UUID REFERENCES form_section (id) ON DELETE CASCADE,
+    match_any BOOLEAN NOT NULL DEFAULT FALSE,
+    conditions JSONB NOT NULL DEFAULT '[]'
+);
+
+CREATE INDEX form_rule_form_id_idx ON form_rule (form_id);
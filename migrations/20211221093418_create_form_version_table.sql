@@ -0,0 +1,38 @@
+CREATE TABLE form_version (
+    id UUID PRIMARY KEY,
+    form_id UUID NOT NULL REFERENCES form (id) ON DELETE CASCADE,
+    version INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    -- Fields of the form as they were when the version was created, in order
+    fields JSONB NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    UNIQUE (form_id, version)
+);
+
+-- Removed fields are archived so that historical answers to them are kept
+ALTER TABLE form_input
+ADD archived_at TIMESTAMP;
+
+ALTER TABLE response
+ADD form_version_id UUID REFERENCES form_version (id);
+
+-- Existing responses were most likely submitted against the current fields, so record those as each form's first version
+INSERT INTO form_version (id, form_id, version, title, fields, created_at)
+SELECT uuid_generate_v4(), form.id, 1, COALESCE(form.title, ''), COALESCE((
+    SELECT jsonb_agg(jsonb_build_object(
+        'field_id', form_input.id,
+        'caption', COALESCE(form_input.caption, ''),
+        'type', form_input.type,
+        'config', form_input.config,
+        'position', form_input.position,
+        'section_id', form_input.section_id
+    ) ORDER BY form_input.position)
+    FROM form_input
+    WHERE form_input.form_id = form.id
+), '[]'), now() AT TIME ZONE 'utc'
+FROM form;
+
+UPDATE response
+SET form_version_id = form_version.id
+FROM form_version
+WHERE form_version.form_id = response.form_id;
@@ -0,0 +1,6 @@
+-- Forms already shared are left accepting responses
+ALTER TABLE form
+ADD status TEXT NOT NULL DEFAULT 'published',
+ADD opens_at TIMESTAMP,
+ADD closes_at TIMESTAMP,
+ADD max_responses INTEGER;
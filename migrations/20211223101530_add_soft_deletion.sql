@@ -0,0 +1,6 @@
+-- Deleted forms and QR codes are kept in the trash until they are purged
+ALTER TABLE form
+ADD deleted_at TIMESTAMP;
+
+ALTER TABLE qr_code
+ADD deleted_at TIMESTAMP;
@@ -0,0 +1,11 @@
+CREATE TABLE form_template (
+    id UUID PRIMARY KEY,
+    account_id UUID NOT NULL REFERENCES account (id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    description TEXT,
+    -- Title, fields, sections and rules new forms are created with
+    definition JSONB NOT NULL,
+    created_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX form_template_account_id_idx ON form_template (account_id);
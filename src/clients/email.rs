@@ -0,0 +1,181 @@
+//! Contains the provider-independent description of an email and the trait of the backends that send them
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::HashMap;
+
+/// Metadata key under which notification emails carry the id of their notification.
+pub const NOTIFICATION_ID_METADATA: &str = "notification_id";
+
+/// Templates of backends that cannot render any.
+static NO_TEMPLATES: EmailTemplates = EmailTemplates {
+    alert: None,
+    password_reset: None,
+};
+
+/// Sends emails through a provider such as Postmark or an SMTP server.
+#[async_trait]
+pub trait EmailSender: Send + Sync {
+    /// Sends an email, returning the id the provider assigned to it when it reports one.
+    async fn send(&self, email: &Email) -> Result<Option<String>, anyhow::Error>;
+
+    /// Branded templates emails should be sent with, when the provider renders templates.
+    fn templates(&self) -> &EmailTemplates {
+        &NO_TEMPLATES
+    }
+}
+
+/// Aliases of the branded templates configured on the Postmark server.
+/// Emails are sent with plain bodies when their template is not configured.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct EmailTemplates {
+    /// Template of scan alerts, rendered with `subject`, `text_body` and `html_body`.
+    pub alert: Option<String>,
+    /// Template of password reset emails, rendered with `subject`, `username` and `reset_url`.
+    pub password_reset: Option<String>,
+}
+
+/// Postmark template, referred to by its numeric id or its alias.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateRef {
+    Id(i64),
+    Alias(String),
+}
+
+/// Content of an email.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailBody {
+    Text(String),
+    /// HTML body with a plain text alternative for clients that do not render HTML.
+    Html {
+        text: String,
+        html: String,
+    },
+    /// Body rendered by Postmark from one of its templates.
+    Template {
+        template: TemplateRef,
+        model: serde_json::Value,
+    },
+}
+
+/// Postmark message stream an email is sent through.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum MessageStream {
+    /// Emails triggered by the recipient's actions, such as alerts and password resets.
+    #[serde(rename = "outbound")]
+    Transactional,
+    /// Emails sent to many recipients at once, such as announcements.
+    #[serde(rename = "broadcast")]
+    Broadcast,
+}
+
+/// File attached to an email.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Attachment {
+    /// File name shown to the recipient.
+    pub name: String,
+    /// MIME type of the file, e.g. `text/csv`.
+    pub content_type: String,
+    #[serde(serialize_with = "serialize_base64")]
+    pub content: Vec<u8>,
+}
+
+impl Attachment {
+    pub fn new(name: impl Into<String>, content_type: impl Into<String>, content: Vec<u8>) -> Self {
+        Self {
+            name: name.into(),
+            content_type: content_type.into(),
+            content,
+        }
+    }
+}
+
+/// Serializes binary content as base64, the encoding email providers expect it in.
+fn serialize_base64<S: Serializer>(content: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&base64::encode(content))
+}
+
+/// An email to send through an `EmailSender`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Email {
+    pub(crate) to: String,
+    pub(crate) subject: Option<String>,
+    pub(crate) body: EmailBody,
+    pub(crate) tag: Option<String>,
+    pub(crate) reply_to: Option<String>,
+    pub(crate) metadata: HashMap<String, String>,
+    pub(crate) message_stream: MessageStream,
+    pub(crate) attachments: Vec<Attachment>,
+}
+
+impl Email {
+    pub fn new(to: impl Into<String>, body: EmailBody) -> Self {
+        Self {
+            to: to.into(),
+            subject: None,
+            body,
+            tag: None,
+            reply_to: None,
+            metadata: HashMap::new(),
+            message_stream: MessageStream::Transactional,
+            attachments: Vec::new(),
+        }
+    }
+
+    pub fn text(to: impl Into<String>, text: impl Into<String>) -> Self {
+        Self::new(to, EmailBody::Text(text.into()))
+    }
+
+    pub fn html(to: impl Into<String>, text: impl Into<String>, html: impl Into<String>) -> Self {
+        Self::new(
+            to,
+            EmailBody::Html {
+                text: text.into(),
+                html: html.into(),
+            },
+        )
+    }
+
+    pub fn template(
+        to: impl Into<String>,
+        template: TemplateRef,
+        model: serde_json::Value,
+    ) -> Self {
+        Self::new(to, EmailBody::Template { template, model })
+    }
+
+    /// Templates define their own subject, so the subject of a templated email is added to
+    /// its model as `subject` instead.
+    pub fn subject(mut self, subject: impl Into<String>) -> Self {
+        self.subject = Some(subject.into());
+        self
+    }
+
+    /// Category Postmark groups the email's statistics under.
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tag = Some(tag.into());
+        self
+    }
+
+    pub fn reply_to(mut self, reply_to: impl Into<String>) -> Self {
+        self.reply_to = Some(reply_to.into());
+        self
+    }
+
+    /// Attaches a value Postmark includes in the webhook events of the email.
+    pub fn metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn message_stream(mut self, message_stream: MessageStream) -> Self {
+        self.message_stream = message_stream;
+        self
+    }
+
+    pub fn attachment(mut self, attachment: Attachment) -> Self {
+        self.attachments.push(attachment);
+        self
+    }
+}
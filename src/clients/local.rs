@@ -0,0 +1,128 @@
+//! Contains a backend that writes emails, SMS messages and calls to a local directory or to
+//! standard output instead of sending them, for development and tests.
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use uuid::Uuid;
+
+use super::email::{Email, EmailSender};
+use super::phone::{SmsSender, VoiceCaller};
+
+/// Records every message as a JSON document, in its own file when a directory is configured.
+#[derive(Debug, Clone)]
+pub struct LocalOutbox {
+    directory: Option<PathBuf>,
+}
+
+impl LocalOutbox {
+    /// Creates an outbox writing to `directory`, or to standard output when it is `None`.
+    pub fn new(directory: Option<PathBuf>) -> Self {
+        Self { directory }
+    }
+
+    /// Writes a message of the given `kind` and returns the id it was recorded under.
+    async fn write(&self, kind: &str, message: serde_json::Value) -> Result<String, anyhow::Error> {
+        let id = Uuid::new_v4().to_string();
+        let document = serde_json::to_string_pretty(&serde_json::json!({
+            "id": id,
+            "kind": kind,
+            "sent_at": Utc::now().naive_utc(),
+            "message": message,
+        }))?;
+        match &self.directory {
+            Some(directory) => {
+                tokio::fs::create_dir_all(directory).await?;
+                // Timestamped names keep the files in the order the messages were sent
+                let name = format!(
+                    "{}-{}-{}.json",
+                    Utc::now().format("%Y%m%dT%H%M%S%.6f"),
+                    kind,
+                    id
+                );
+                tokio::fs::write(directory.join(name), document).await?;
+            }
+            None => println!("{}", document),
+        }
+        Ok(id)
+    }
+}
+
+#[async_trait]
+impl EmailSender for LocalOutbox {
+    async fn send(&self, email: &Email) -> Result<Option<String>, anyhow::Error> {
+        let id = self.write("email", serde_json::to_value(email)?).await?;
+        Ok(Some(id))
+    }
+}
+
+#[async_trait]
+impl SmsSender for LocalOutbox {
+    async fn send_sms(&self, to: &str, message: &str) -> Result<(), anyhow::Error> {
+        self.write("sms", serde_json::json!({ "to": to, "body": message }))
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl VoiceCaller for LocalOutbox {
+    async fn call(
+        &self,
+        to: &str,
+        twiml: &str,
+        status_callback: Option<String>,
+    ) -> Result<(), anyhow::Error> {
+        self.write(
+            "call",
+            serde_json::json!({ "to": to, "twiml": twiml, "status_callback": status_callback }),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::LocalOutbox;
+    use crate::clients::email::{Email, EmailSender};
+    use crate::clients::phone::SmsSender;
+
+    #[tokio::test]
+    async fn messages_are_written_to_their_own_files_in_order() {
+        let directory = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        let outbox = LocalOutbox::new(Some(directory.clone()));
+
+        let id = outbox
+            .send(&Email::text("staff@hermodapp.com", "Table 4 needs help").subject("Alert"))
+            .await
+            .unwrap()
+            .unwrap();
+        outbox
+            .send_sms("+15555550123", "Table 4 needs help")
+            .await
+            .unwrap();
+
+        let mut files: Vec<_> = std::fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        files.sort();
+        assert_eq!(2, files.len());
+        let email: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&files[0]).unwrap()).unwrap();
+        assert_eq!(id, email["id"]);
+        assert_eq!("email", email["kind"]);
+        assert_eq!("staff@hermodapp.com", email["message"]["to"]);
+        assert_eq!("Alert", email["message"]["subject"]);
+        assert_eq!("Table 4 needs help", email["message"]["body"]["text"]);
+        let sms: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&files[1]).unwrap()).unwrap();
+        assert_eq!("sms", sms["kind"]);
+        assert_eq!("+15555550123", sms["message"]["to"]);
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
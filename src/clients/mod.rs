@@ -1,3 +1,20 @@
 //! Contains clients for interacting with third-party APIs
+pub mod email;
+pub mod local;
+pub mod phone;
 pub mod postmark;
+pub mod smtp;
 pub mod twilio;
+
+use std::sync::Arc;
+
+use email::EmailSender;
+use phone::{SmsSender, VoiceCaller};
+
+/// Backends emails, SMS messages and calls are sent through, selected by `Settings`.
+#[derive(Clone)]
+pub struct Providers {
+    pub email: Arc<dyn EmailSender>,
+    pub sms: Arc<dyn SmsSender>,
+    pub voice: Arc<dyn VoiceCaller>,
+}
@@ -0,0 +1,21 @@
+//! Contains the traits of the backends that send SMS messages and place phone calls
+use async_trait::async_trait;
+
+/// Sends SMS messages through a provider such as Twilio.
+#[async_trait]
+pub trait SmsSender: Send + Sync {
+    async fn send_sms(&self, to: &str, message: &str) -> Result<(), anyhow::Error>;
+}
+
+/// Places phone calls that follow TwiML instructions through a provider such as Twilio.
+#[async_trait]
+pub trait VoiceCaller: Send + Sync {
+    /// Calls `to` and follows `twiml`. The provider posts the outcome of the call to
+    /// `status_callback` when one is given.
+    async fn call(
+        &self,
+        to: &str,
+        twiml: &str,
+        status_callback: Option<String>,
+    ) -> Result<(), anyhow::Error>;
+}
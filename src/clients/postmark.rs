@@ -1,58 +1,130 @@
+//! Contains everything required for sending emails through Postmark's API
+use async_trait::async_trait;
 use reqwest::Client;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Serialize)]
-pub enum EmailBody<'request> {
-    HtmlBody(&'request str),
-    TextBody(&'request str),
+use super::email::{
+    Attachment, Email, EmailBody, EmailSender, EmailTemplates, MessageStream, TemplateRef,
+};
+
+/// Path of the API endpoint that sends `email`.
+fn endpoint(email: &Email) -> &'static str {
+    match email.body {
+        EmailBody::Template { .. } => "email/withTemplate",
+        _ => "email",
+    }
+}
+
+/// JSON body of a request to Postmark's email endpoints.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct EmailRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    subject: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text_body: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    html_body: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    template_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    template_alias: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    template_model: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tag: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_to: Option<&'a str>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    metadata: &'a HashMap<String, String>,
+    message_stream: MessageStream,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<AttachmentRequest<'a>>,
 }
 
-#[derive(Serialize)]
+/// Attachment of a request to Postmark's email endpoints, with its content in base64.
+#[derive(Serialize, Debug)]
 #[serde(rename_all = "PascalCase")]
-pub struct EmailJson<'client, 'request> {
-    from: &'client str,
-    to: &'request str,
-    cc: Option<String>,
-    bcc: Option<String>,
-    subject: Option<String>,
-    tag: Option<String>,
-    text_body: &'request str,
-    reply_to: Option<String>,
-    headers: Option<Vec<String>>,
-    track_opens: Option<bool>,
-    track_links: Option<String>,
-    metadata: Option<HashMap<String, String>>,
-    attachments: Option<Vec<String>>,
-    message_stream: Option<String>,
+struct AttachmentRequest<'a> {
+    name: &'a str,
+    content: String,
+    content_type: &'a str,
 }
 
-impl<'client, 'request> EmailJson<'client, 'request> {
-    pub fn new(to: &'request str, from: &'client str, text_body: &'request str) -> Self {
+impl<'a> AttachmentRequest<'a> {
+    fn new(attachment: &'a Attachment) -> Self {
         Self {
+            name: &attachment.name,
+            content: base64::encode(&attachment.content),
+            content_type: &attachment.content_type,
+        }
+    }
+}
+
+impl<'a> EmailRequest<'a> {
+    fn new(from: &'a str, email: &'a Email) -> Self {
+        let mut request = Self {
             from,
-            to,
-            cc: None,
-            bcc: None,
-            subject: None,
-            tag: None,
-            text_body,
-            reply_to: None,
-            headers: None,
-            track_opens: None,
-            track_links: None,
-            metadata: None,
-            attachments: None,
-            message_stream: None,
+            to: &email.to,
+            subject: email.subject.as_deref(),
+            text_body: None,
+            html_body: None,
+            template_id: None,
+            template_alias: None,
+            template_model: None,
+            tag: email.tag.as_deref(),
+            reply_to: email.reply_to.as_deref(),
+            metadata: &email.metadata,
+            message_stream: email.message_stream,
+            attachments: email
+                .attachments
+                .iter()
+                .map(AttachmentRequest::new)
+                .collect(),
+        };
+        match &email.body {
+            EmailBody::Text(text) => request.text_body = Some(text),
+            EmailBody::Html { text, html } => {
+                request.text_body = Some(text);
+                request.html_body = Some(html);
+            }
+            EmailBody::Template { template, model } => {
+                match template {
+                    TemplateRef::Id(id) => request.template_id = Some(*id),
+                    TemplateRef::Alias(alias) => request.template_alias = Some(alias),
+                }
+                let mut model = model.clone();
+                if let (Some(subject), Some(fields)) =
+                    (request.subject.take(), model.as_object_mut())
+                {
+                    fields.entry("subject").or_insert_with(|| subject.into());
+                }
+                request.template_model = Some(model);
+            }
         }
+        request
     }
 }
 
+/// Postmark's response to a sent email.
+#[derive(Deserialize, Debug)]
+pub struct SendEmailResponse {
+    /// Id Postmark refers to the email by in its webhooks.
+    #[serde(rename = "MessageID")]
+    pub message_id: String,
+}
+
+/// Client for sending emails
+#[derive(Clone)]
 pub struct PostmarkClient {
     http_client: Client,
     base_url: String,
     server_auth_token: String,
     from: String,
+    templates: EmailTemplates,
 }
 
 impl PostmarkClient {
@@ -61,6 +133,7 @@ impl PostmarkClient {
         timeout: std::time::Duration,
         server_auth_token: String,
         from: String,
+        templates: EmailTemplates,
     ) -> Self {
         let http_client = Client::builder().timeout(timeout).build().unwrap();
         Self {
@@ -68,46 +141,44 @@ impl PostmarkClient {
             base_url,
             server_auth_token,
             from,
+            templates,
         }
     }
+}
 
-    #[tracing::instrument(name = "clients::postmark::send_email", skip(self))]
-    pub async fn send_email(&self, to: &str, message: &str) -> Result<(), reqwest::Error> {
-        let url = format!("{}email", self.base_url);
-        let json = EmailJson::new(to, &self.from, message);
-
-        let response = self
+#[async_trait]
+impl EmailSender for PostmarkClient {
+    /// Sends an email, returning the id Postmark assigned to it. An accepted email whose response
+    /// cannot be parsed is still reported as sent, without an id, so that it is not sent again.
+    #[tracing::instrument(name = "clients::postmark::send", skip(self, email), fields(to=%email.to))]
+    async fn send(&self, email: &Email) -> Result<Option<String>, anyhow::Error> {
+        let url = format!("{}{}", self.base_url, endpoint(email));
+        let body = self
             .http_client
             .post(&url)
             .header("Accept", "application/json")
             .header("Content-Type", "application/json")
             .header("X-Postmark-Server-Token", &self.server_auth_token)
-            .body(serde_json::to_string(&json).unwrap())
+            .json(&EmailRequest::new(&self.from, email))
             .send()
             .await?
-            .error_for_status()?;
-        dbg!(response);
-        Ok(())
+            .error_for_status()?
+            .text()
+            .await?;
+        match serde_json::from_str::<SendEmailResponse>(&body) {
+            Ok(response) => Ok(Some(response.message_id)),
+            Err(e) => {
+                tracing::warn!(
+                    "Postmark accepted an email without a readable MessageID: {}",
+                    e
+                );
+                Ok(None)
+            }
+        }
     }
-}
-
-/*
-    #[tracing::instrument(name = "clients::postmark::send_templated_email", skip(self))]
-    pub async fn send_templated_email(&self, to: String, template: String) -> Result<(), reqwest::Error> {
-        let url = format!("{}Accounts/{}/Calls.json", self.base_url, &self.account_sid);
-        let message = urlencoding::encode(&message);
-        let twiml = format!("<Response><Say>{}</Say></Response>", message);
-        let body = format!("Twiml={}&To={}&From={}", twiml, to, self.from);
 
-        self.http_client
-            .post(&url)
-            .header("Content-Type", "application/x-www-form-urlencoded")
-            .basic_auth(&self.account_sid, Some(&self.auth_token))
-            .body(body)
-            .send()
-            .await?
-            .error_for_status()?;
-        Ok(())
+    fn templates(&self) -> &EmailTemplates {
+        &self.templates
     }
 }
 
@@ -116,98 +187,129 @@ mod tests {
     use std::time::Duration;
 
     use wiremock::{
-        matchers::{header, method, path},
-        Mock, MockServer, Request, ResponseTemplate,
+        matchers::{body_json, header, method, path},
+        Mock, MockServer, ResponseTemplate,
     };
 
-    use super::TwilioClient;
-
-    struct SendSmsBodyMatcher;
+    use super::PostmarkClient;
+    use crate::clients::email::{
+        Attachment, Email, EmailSender, EmailTemplates, MessageStream, TemplateRef,
+    };
 
-    impl wiremock::Match for SendSmsBodyMatcher {
-        fn matches(&self, request: &Request) -> bool {
-            let result = String::from_utf8(request.body.clone());
-            if let Ok(body) = result {
-                body.contains(&format!("Body={}", urlencoding::encode("Hello, World!")))
-                    && body.contains(&format!("To={}", "+12321231234"))
-                    && body.contains(&format!("From={}", "+12321231234"))
-            } else {
-                false
-            }
-        }
+    fn postmark_client(base_url: String) -> PostmarkClient {
+        PostmarkClient::new(
+            base_url + "/",
+            Duration::from_secs(1),
+            "server_token".to_string(),
+            "alerts@hermodapp.com".to_string(),
+            EmailTemplates::default(),
+        )
     }
 
-    struct SendCallBodyMatcher;
-
-    impl wiremock::Match for SendCallBodyMatcher {
-        fn matches(&self, request: &Request) -> bool {
-            let result = String::from_utf8(request.body.clone());
-            if let Ok(body) = result {
-                let message = urlencoding::encode("Hello, World!");
-                let twiml = format!("<Response><Say>{}</Say></Response>", message);
-                body.contains(&format!("Twiml={}", twiml))
-                    && body.contains(&format!("To={}", "+12321231234"))
-                    && body.contains(&format!("From={}", "+12321231234"))
-            } else {
-                false
-            }
-        }
+    fn accepted() -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "To": "staff@hermodapp.com",
+            "MessageID": "b7bc2f4a-e38e-4336-af7d-e6c392c2f817",
+            "ErrorCode": 0,
+            "Message": "OK"
+        }))
     }
 
-    fn twilio_client(base_url: String) -> TwilioClient {
-        TwilioClient::new(
-            base_url + "/",
-            Duration::from_secs(1),
-            "account_sid".to_string(),
-            "auth_token".to_string(),
-            "+12321231234".to_string(),
+    #[tokio::test]
+    async fn send_posts_html_emails_with_their_options() {
+        let mock_server = MockServer::start().await;
+        let client = postmark_client(mock_server.uri());
+
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .and(header("X-Postmark-Server-Token", "server_token"))
+            .and(body_json(serde_json::json!({
+                "From": "alerts@hermodapp.com",
+                "To": "staff@hermodapp.com",
+                "Subject": "Alert: Table 4",
+                "TextBody": "Table 4 needs help",
+                "HtmlBody": "<p>Table 4 needs help</p>",
+                "Tag": "alert",
+                "ReplyTo": "owner@hermodapp.com",
+                "Metadata": { "notification_id": "1" },
+                "MessageStream": "outbound",
+                "Attachments": [{
+                    "Name": "response.csv",
+                    "Content": "UXVlc3Rpb24sQW5zd2Vy",
+                    "ContentType": "text/csv"
+                }]
+            })))
+            .respond_with(accepted())
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let email = Email::html(
+            "staff@hermodapp.com",
+            "Table 4 needs help",
+            "<p>Table 4 needs help</p>",
         )
+        .subject("Alert: Table 4")
+        .tag("alert")
+        .reply_to("owner@hermodapp.com")
+        .metadata("notification_id", "1")
+        .attachment(Attachment::new(
+            "response.csv",
+            "text/csv",
+            b"Question,Answer".to_vec(),
+        ));
+        assert_eq!(
+            Some("b7bc2f4a-e38e-4336-af7d-e6c392c2f817".to_string()),
+            client.send(&email).await.unwrap()
+        );
     }
 
     #[tokio::test]
-    async fn send_sms_sends_the_correct_http_request() {
+    async fn send_posts_templated_emails_with_the_subject_in_their_model() {
         let mock_server = MockServer::start().await;
-        let message = "Hello, World!";
-        let to = "+12321231234";
-        let client = twilio_client(mock_server.uri());
-        let url = format!("Accounts/{}/Messages", client.account_sid,);
+        let client = postmark_client(mock_server.uri());
 
-        Mock::given(header("Content-Type", "application/x-www-form-urlencoded"))
-            .and(path(url))
+        Mock::given(path("/email/withTemplate"))
             .and(method("POST"))
-            .and(SendSmsBodyMatcher)
-            .respond_with(ResponseTemplate::new(200))
+            .and(body_json(serde_json::json!({
+                "From": "alerts@hermodapp.com",
+                "To": "staff@hermodapp.com",
+                "TemplateAlias": "password-reset",
+                "TemplateModel": { "subject": "Reset your password", "username": "staff" },
+                "MessageStream": "broadcast"
+            })))
+            .respond_with(accepted())
             .expect(1)
             .mount(&mock_server)
             .await;
 
-        let _ = client
-            .send_sms(to.to_string(), message.to_string())
-            .await
-            .unwrap();
+        let email = Email::template(
+            "staff@hermodapp.com",
+            TemplateRef::Alias("password-reset".to_string()),
+            serde_json::json!({ "username": "staff" }),
+        )
+        .subject("Reset your password")
+        .message_stream(MessageStream::Broadcast);
+        client.send(&email).await.unwrap();
     }
 
     #[tokio::test]
-    async fn send_call_sends_the_correct_http_request() {
+    async fn send_reports_accepted_emails_without_a_readable_id_as_sent() {
         let mock_server = MockServer::start().await;
-        let message = "Hello, World!";
-        let to = "+12321231234";
-        let client = twilio_client(mock_server.uri());
-        let url = format!("Accounts/{}/Calls.json", client.account_sid,);
+        let client = postmark_client(mock_server.uri());
 
-        Mock::given(header("Content-Type", "application/x-www-form-urlencoded"))
-            .and(path(url))
-            .and(method("POST"))
-            .and(SendCallBodyMatcher)
+        Mock::given(method("POST"))
             .respond_with(ResponseTemplate::new(200))
             .expect(1)
             .mount(&mock_server)
             .await;
 
-        let _ = client
-            .send_call(to.to_string(), message.to_string())
-            .await
-            .unwrap();
+        assert_eq!(
+            None,
+            client
+                .send(&Email::text("staff@hermodapp.com", "Hello, Postmark!"))
+                .await
+                .unwrap()
+        );
     }
 }
-*/
@@ -0,0 +1,520 @@
+//! Contains an email backend that sends emails through an SMTP server, for deployments that cannot use Postmark
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::Deserialize;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio_rustls::{client::TlsStream, rustls::ClientConfig, webpki::DNSNameRef, TlsConnector};
+use uuid::Uuid;
+
+use super::email::{Attachment, Email, EmailBody, EmailSender};
+
+/// Name the client introduces itself with in `EHLO`.
+const EHLO_NAME: &str = "hermodapp.com";
+
+/// How the connection to the SMTP server is encrypted.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    /// Plain text, only suitable for servers on the local network.
+    None,
+    /// Upgrades a plain text connection with `STARTTLS`, usually on port 587.
+    StartTls,
+    /// Encrypts the connection from the start, usually on port 465.
+    Tls,
+}
+
+/// Client for sending emails through an SMTP server. Postmark templates are not supported.
+#[derive(Clone)]
+pub struct SmtpClient {
+    host: String,
+    port: u16,
+    tls: SmtpTls,
+    /// Username and password used with `AUTH PLAIN`, if the server requires authentication.
+    credentials: Option<(String, String)>,
+    from: String,
+    timeout: Duration,
+}
+
+impl SmtpClient {
+    pub fn new(
+        host: String,
+        port: u16,
+        tls: SmtpTls,
+        credentials: Option<(String, String)>,
+        from: String,
+        timeout: Duration,
+    ) -> Self {
+        Self {
+            host,
+            port,
+            tls,
+            credentials,
+            from,
+            timeout,
+        }
+    }
+
+    /// Opens a session with the server and submits `message` for delivery to `to`.
+    async fn deliver(&self, to: &str, message: &str) -> Result<(), anyhow::Error> {
+        let stream = TcpStream::connect((self.host.as_str(), self.port)).await?;
+        match self.tls {
+            SmtpTls::None => {
+                let mut connection = Connection::new(stream);
+                connection.greet().await?;
+                self.transaction(&mut connection, to, message).await
+            }
+            SmtpTls::Tls => {
+                let mut connection = Connection::new(self.tls_connect(stream).await?);
+                connection.greet().await?;
+                self.transaction(&mut connection, to, message).await
+            }
+            SmtpTls::StartTls => {
+                let mut connection = Connection::new(stream);
+                connection.greet().await?;
+                connection.command("STARTTLS", 220).await?;
+                let mut connection =
+                    Connection::new(self.tls_connect(connection.into_inner()).await?);
+                connection
+                    .command(&format!("EHLO {}", EHLO_NAME), 250)
+                    .await?;
+                self.transaction(&mut connection, to, message).await
+            }
+        }
+    }
+
+    async fn transaction<S: AsyncRead + AsyncWrite + Unpin>(
+        &self,
+        connection: &mut Connection<S>,
+        to: &str,
+        message: &str,
+    ) -> Result<(), anyhow::Error> {
+        if let Some((username, password)) = &self.credentials {
+            let token = base64::encode(format!("\0{}\0{}", username, password));
+            connection
+                .command(&format!("AUTH PLAIN {}", token), 235)
+                .await
+                .context("The SMTP server rejected the credentials")?;
+        }
+        connection
+            .command(
+                &format!("MAIL FROM:<{}>", envelope_address(&self.from)?),
+                250,
+            )
+            .await?;
+        connection
+            .command(&format!("RCPT TO:<{}>", envelope_address(to)?), 250)
+            .await?;
+        connection.command("DATA", 354).await?;
+        connection
+            .command(&format!("{}\r\n.", dot_stuff(message)), 250)
+            .await?;
+        // The message has been accepted, so failing to end the session politely is not an error
+        if let Err(e) = connection.command("QUIT", 221).await {
+            tracing::warn!("Failed to end the SMTP session: {:?}", e);
+        }
+        Ok(())
+    }
+
+    async fn tls_connect(&self, stream: TcpStream) -> Result<TlsStream<TcpStream>, anyhow::Error> {
+        let mut config = ClientConfig::new();
+        config
+            .root_store
+            .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+        let domain = DNSNameRef::try_from_ascii_str(&self.host)
+            .map_err(|_| anyhow::anyhow!("{} is not a valid SMTP host name", self.host))?;
+        let stream = TlsConnector::from(Arc::new(config))
+            .connect(domain, stream)
+            .await?;
+        Ok(stream)
+    }
+}
+
+#[async_trait]
+impl EmailSender for SmtpClient {
+    /// Sends an email, returning the `Message-ID` it was sent with.
+    #[tracing::instrument(name = "clients::smtp::send", skip(self, email), fields(to=%email.to))]
+    async fn send(&self, email: &Email) -> Result<Option<String>, anyhow::Error> {
+        let message_id = message_id(&self.from);
+        let message = format_message(&self.from, email, &message_id)?;
+        tokio::time::timeout(self.timeout, self.deliver(&email.to, &message))
+            .await
+            .context("Timed out sending an email to the SMTP server")??;
+        Ok(Some(message_id))
+    }
+}
+
+/// Line-based connection to an SMTP server.
+struct Connection<S> {
+    stream: BufReader<S>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Connection<S> {
+    fn new(stream: S) -> Self {
+        Self {
+            stream: BufReader::new(stream),
+        }
+    }
+
+    fn into_inner(self) -> S {
+        self.stream.into_inner()
+    }
+
+    /// Waits for the server's greeting and introduces the client.
+    async fn greet(&mut self) -> Result<(), anyhow::Error> {
+        self.reply(220).await?;
+        self.command(&format!("EHLO {}", EHLO_NAME), 250).await?;
+        Ok(())
+    }
+
+    /// Sends a command and waits for a reply of the same class as `expected`, e.g. any 2xx for 250.
+    async fn command(&mut self, command: &str, expected: u16) -> Result<String, anyhow::Error> {
+        let writer = self.stream.get_mut();
+        writer.write_all(command.as_bytes()).await?;
+        writer.write_all(b"\r\n").await?;
+        writer.flush().await?;
+        self.reply(expected).await
+    }
+
+    /// Reads a possibly multiline reply, failing unless its code is of the same class as `expected`.
+    async fn reply(&mut self, expected: u16) -> Result<String, anyhow::Error> {
+        let mut lines = Vec::new();
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line).await? == 0 {
+                anyhow::bail!("The SMTP server closed the connection");
+            }
+            let line = line.trim_end().to_string();
+            let code: u16 = line
+                .get(..3)
+                .and_then(|code| code.parse().ok())
+                .ok_or_else(|| anyhow::anyhow!("Invalid SMTP reply: {}", line))?;
+            let last = line.as_bytes().get(3) != Some(&b'-');
+            lines.push(line);
+            if last {
+                let reply = lines.join("\n");
+                if code / 100 != expected / 100 {
+                    anyhow::bail!("The SMTP server replied: {}", reply);
+                }
+                return Ok(reply);
+            }
+        }
+    }
+}
+
+/// Formats `email` as a MIME message whose lines are separated by CRLF.
+fn format_message(from: &str, email: &Email, message_id: &str) -> Result<String, anyhow::Error> {
+    let mut headers = vec![
+        format!("From: {}", header_value(from)),
+        format!("To: {}", header_value(&email.to)),
+        format!("Date: {}", Utc::now().to_rfc2822()),
+        format!("Message-ID: {}", message_id),
+    ];
+    if let Some(subject) = &email.subject {
+        headers.push(format!("Subject: {}", encode_header(subject)));
+    }
+    if let Some(reply_to) = &email.reply_to {
+        headers.push(format!("Reply-To: {}", header_value(reply_to)));
+    }
+    headers.push("MIME-Version: 1.0".to_string());
+    let body = match &email.body {
+        EmailBody::Text(text) => mime_part("text/plain", text),
+        EmailBody::Html { text, html } => {
+            let boundary = format!("hermod-{}", Uuid::new_v4().to_simple());
+            format!(
+                "Content-Type: multipart/alternative; boundary=\"{b}\"\r\n\r\n--{b}\r\n{}\r\n--{b}\r\n{}\r\n--{b}--",
+                mime_part("text/plain", text),
+                mime_part("text/html", html),
+                b = boundary
+            )
+        }
+        EmailBody::Template { .. } => {
+            anyhow::bail!("Emails sent through SMTP cannot use Postmark templates")
+        }
+    };
+    if email.attachments.is_empty() {
+        return Ok(format!("{}\r\n{}", headers.join("\r\n"), body));
+    }
+    let boundary = format!("hermod-{}", Uuid::new_v4().to_simple());
+    let mut parts = vec![body];
+    parts.extend(email.attachments.iter().map(attachment_part));
+    Ok(format!(
+        "{}\r\nContent-Type: multipart/mixed; boundary=\"{b}\"\r\n\r\n--{b}\r\n{}\r\n--{b}--",
+        headers.join("\r\n"),
+        parts.join(&format!("\r\n--{}\r\n", boundary)),
+        b = boundary
+    ))
+}
+
+/// Formats a UTF-8 body part, base64 encoded so that its lines are short and never start with a dot.
+fn mime_part(content_type: &str, content: &str) -> String {
+    format!(
+        "Content-Type: {}; charset=utf-8\r\nContent-Transfer-Encoding: base64\r\n\r\n{}",
+        content_type,
+        base64_lines(content.as_bytes())
+    )
+}
+
+/// Formats an attachment as a base64 encoded part.
+fn attachment_part(attachment: &Attachment) -> String {
+    let name = encode_header(&attachment.name).replace('"', "'");
+    format!(
+        "Content-Type: {}; name=\"{n}\"\r\nContent-Disposition: attachment; filename=\"{n}\"\r\nContent-Transfer-Encoding: base64\r\n\r\n{}",
+        header_value(&attachment.content_type),
+        base64_lines(&attachment.content),
+        n = name
+    )
+}
+
+/// Encodes `content` in base64 lines of at most 76 characters, separated by CRLF.
+fn base64_lines(content: &[u8]) -> String {
+    let encoded = base64::encode(content);
+    let lines: Vec<&str> = encoded
+        .as_bytes()
+        .chunks(76)
+        .map(|line| std::str::from_utf8(line).expect("base64 is ASCII"))
+        .collect();
+    lines.join("\r\n")
+}
+
+/// Removes line breaks, which would let a value inject headers.
+fn header_value(value: &str) -> String {
+    value
+        .split(|c| c == '\r' || c == '\n')
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Encodes a header value as an RFC 2047 encoded word unless it is printable ASCII.
+fn encode_header(value: &str) -> String {
+    let value = header_value(value);
+    if value.chars().all(|c| c.is_ascii() && !c.is_ascii_control()) {
+        value
+    } else {
+        format!("=?UTF-8?B?{}?=", base64::encode(value))
+    }
+}
+
+/// Escapes lines starting with a dot, which would otherwise end the message early.
+fn dot_stuff(message: &str) -> String {
+    let message = message.replace("\r\n.", "\r\n..");
+    if message.starts_with('.') {
+        format!(".{}", message)
+    } else {
+        message
+    }
+}
+
+/// Returns the bare address of a mailbox such as `Hermod <alerts@hermodapp.com>`.
+fn address(mailbox: &str) -> &str {
+    match (mailbox.find('<'), mailbox.rfind('>')) {
+        (Some(start), Some(end)) if start < end => &mailbox[start + 1..end],
+        _ => mailbox.trim(),
+    }
+}
+
+/// Returns the bare address of a mailbox for use in an SMTP command, failing if it contains
+/// characters that would end the command early and let the rest be read as further commands.
+fn envelope_address(mailbox: &str) -> Result<&str, anyhow::Error> {
+    let address = address(mailbox);
+    if address.contains(|c| matches!(c, '\r' | '\n' | '<' | '>')) {
+        anyhow::bail!("{:?} is not a valid email address", mailbox);
+    }
+    Ok(address)
+}
+
+/// Generates a unique `Message-ID` in the sender's domain.
+fn message_id(from: &str) -> String {
+    let domain = address(from)
+        .rsplit('@')
+        .next()
+        .filter(|domain| !domain.is_empty() && !domain.contains(' '))
+        .unwrap_or(EHLO_NAME);
+    format!("<{}@{}>", Uuid::new_v4(), domain)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    use super::{address, dot_stuff, encode_header, format_message, SmtpClient, SmtpTls};
+    use crate::clients::email::{Attachment, Email, EmailSender, TemplateRef};
+
+    /// Starts a server accepting a single session that answers `RCPT TO` with `rcpt_reply`,
+    /// and returns its port and the lines it received.
+    async fn fake_server(rcpt_reply: &'static str) -> (u16, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = BufReader::new(stream);
+            let mut received = Vec::new();
+            let mut in_data = false;
+            stream
+                .get_mut()
+                .write_all(b"220 localhost\r\n")
+                .await
+                .unwrap();
+            loop {
+                let mut line = String::new();
+                if stream.read_line(&mut line).await.unwrap() == 0 {
+                    break;
+                }
+                let line = line.trim_end_matches("\r\n").to_string();
+                received.push(line.clone());
+                let reply = if in_data {
+                    if line != "." {
+                        continue;
+                    }
+                    in_data = false;
+                    "250 Queued"
+                } else if line.starts_with("EHLO") {
+                    "250-localhost\r\n250 AUTH PLAIN"
+                } else if line.starts_with("AUTH") {
+                    "235 Authenticated"
+                } else if line.starts_with("RCPT") {
+                    rcpt_reply
+                } else if line == "DATA" {
+                    in_data = true;
+                    "354 End data with <CR><LF>.<CR><LF>"
+                } else if line == "QUIT" {
+                    "221 Bye"
+                } else {
+                    "250 OK"
+                };
+                stream
+                    .get_mut()
+                    .write_all(format!("{}\r\n", reply).as_bytes())
+                    .await
+                    .unwrap();
+            }
+            received
+        });
+        (port, handle)
+    }
+
+    fn smtp_client(port: u16) -> SmtpClient {
+        SmtpClient::new(
+            "127.0.0.1".to_string(),
+            port,
+            SmtpTls::None,
+            Some(("user".to_string(), "secret".to_string())),
+            "Hermod <alerts@hermodapp.com>".to_string(),
+            Duration::from_secs(1),
+        )
+    }
+
+    #[tokio::test]
+    async fn send_submits_the_message_to_the_server() {
+        let (port, server) = fake_server("250 OK").await;
+
+        let message_id = smtp_client(port)
+            .send(&Email::text("staff@hermodapp.com", "Table 4 needs help").subject("Alert"))
+            .await
+            .unwrap()
+            .unwrap();
+
+        let received = server.await.unwrap();
+        assert_eq!("EHLO hermodapp.com", received[0]);
+        assert_eq!(
+            format!("AUTH PLAIN {}", base64::encode("\0user\0secret")),
+            received[1]
+        );
+        assert_eq!("MAIL FROM:<alerts@hermodapp.com>", received[2]);
+        assert_eq!("RCPT TO:<staff@hermodapp.com>", received[3]);
+        assert_eq!("DATA", received[4]);
+        assert!(received.contains(&"Subject: Alert".to_string()));
+        assert!(received.contains(&format!("Message-ID: {}", message_id)));
+        assert!(received.contains(&base64::encode("Table 4 needs help")));
+        assert_eq!(Some(&"QUIT".to_string()), received.last());
+        assert!(message_id.ends_with("@hermodapp.com>"));
+    }
+
+    #[tokio::test]
+    async fn send_refuses_addresses_that_would_inject_commands() {
+        let (port, _server) = fake_server("250 OK").await;
+
+        let error = smtp_client(port)
+            .send(&Email::text(
+                "staff@hermodapp.com>\r\nRCPT TO:<attacker@example.com",
+                "Table 4 needs help",
+            ))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("is not a valid email address"));
+    }
+
+    #[tokio::test]
+    async fn send_fails_when_the_server_rejects_the_recipient() {
+        let (port, _server) = fake_server("550 No such user").await;
+
+        let error = smtp_client(port)
+            .send(&Email::text("nobody@hermodapp.com", "Table 4 needs help"))
+            .await
+            .unwrap_err();
+        assert!(format!("{:?}", error).contains("550 No such user"));
+    }
+
+    #[test]
+    fn html_emails_are_multipart_alternatives() {
+        let email = Email::html("staff@hermodapp.com", "Table 4", "<p>Table 4</p>");
+        let message = format_message("alerts@hermodapp.com", &email, "<1@hermodapp.com>").unwrap();
+        assert!(message.contains("Content-Type: multipart/alternative; boundary=\"hermod-"));
+        assert!(message.contains(&base64::encode("Table 4")));
+        assert!(message.contains(&base64::encode("<p>Table 4</p>")));
+        assert!(message.contains("Content-Type: text/html; charset=utf-8"));
+    }
+
+    #[test]
+    fn attachments_are_sent_as_mixed_parts() {
+        let email = Email::text("staff@hermodapp.com", "New response").attachment(Attachment::new(
+            "response.csv",
+            "text/csv",
+            b"Question,Answer".to_vec(),
+        ));
+        let message = format_message("alerts@hermodapp.com", &email, "<1@hermodapp.com>").unwrap();
+        assert!(message.contains("Content-Type: multipart/mixed; boundary=\"hermod-"));
+        assert!(message.contains(&base64::encode("New response")));
+        assert!(message.contains("Content-Disposition: attachment; filename=\"response.csv\""));
+        assert!(message.contains(&base64::encode("Question,Answer")));
+    }
+
+    #[test]
+    fn templated_emails_cannot_be_formatted() {
+        let email = Email::template(
+            "staff@hermodapp.com",
+            TemplateRef::Alias("scan-alert".to_string()),
+            serde_json::json!({}),
+        );
+        assert!(format_message("alerts@hermodapp.com", &email, "<1@hermodapp.com>").is_err());
+    }
+
+    #[test]
+    fn headers_cannot_be_injected_and_are_encoded_when_needed() {
+        assert_eq!("Alert Bcc: x", encode_header("Alert\r\nBcc: x"));
+        assert_eq!("=?UTF-8?B?Q2Fmw6k=?=", encode_header("Café"));
+    }
+
+    #[test]
+    fn lines_starting_with_a_dot_are_escaped() {
+        assert_eq!("..a\r\n..b\r\nc", dot_stuff(".a\r\n.b\r\nc"));
+    }
+
+    #[test]
+    fn addresses_are_extracted_from_mailboxes() {
+        assert_eq!(
+            "alerts@hermodapp.com",
+            address("Hermod <alerts@hermodapp.com>")
+        );
+        assert_eq!("alerts@hermodapp.com", address(" alerts@hermodapp.com "));
+    }
+}
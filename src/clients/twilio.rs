@@ -1,7 +1,15 @@
 //! Contains everything required for interacting with Twilio's API
+pub mod signature;
+pub mod twiml;
+
+use async_trait::async_trait;
 use reqwest::Client;
 
+use super::phone::{SmsSender, VoiceCaller};
+use twiml::{Say, Twiml};
+
 /// Client for sending SMS messages and phone calls
+#[derive(Clone)]
 pub struct TwilioClient {
     http_client: Client,
     base_url: String,
@@ -40,8 +48,12 @@ impl TwilioClient {
         }
 
         let url = format!("{}Accounts/{}/Messages", self.base_url, &self.account_sid);
-        let message = urlencoding::encode(&message);
-        let body = format!("Body={}&To={}&From={}", message, to, self.from);
+        let body = format!(
+            "Body={}&To={}&From={}",
+            urlencoding::encode(&message),
+            urlencoding::encode(&to),
+            urlencoding::encode(&self.from)
+        );
 
         self.http_client
             .post(&url)
@@ -54,20 +66,40 @@ impl TwilioClient {
         Ok(())
     }
 
-    /// Send a phone call using Twilio's API
+    /// Send a phone call reading `message` out loud using Twilio's API
     #[tracing::instrument(name = "clients::twilio::send_call", skip(self))]
     pub async fn send_call(&self, to: String, message: String) -> Result<(), anyhow::Error> {
+        let twiml = Twiml::new().say(Say::new(message)).to_xml();
+        self.send_twiml_call(to, twiml, None).await
+    }
+
+    /// Send a phone call that follows the given TwiML instructions using Twilio's API.
+    /// Twilio posts the outcome of the call to `status_callback` when one is given.
+    #[tracing::instrument(name = "clients::twilio::send_twiml_call", skip(self))]
+    pub async fn send_twiml_call(
+        &self,
+        to: String,
+        twiml: String,
+        status_callback: Option<String>,
+    ) -> Result<(), anyhow::Error> {
         if to.len() != 12 {
             return Err(anyhow::anyhow!("Phone number must be 12 characters long"));
         }
-        if !to.starts_with("+".chars().next().unwrap()) {
+        if !to.starts_with('+') {
             return Err(anyhow::anyhow!("Phone number must begin with +"));
         }
 
         let url = format!("{}Accounts/{}/Calls.json", self.base_url, &self.account_sid);
-        let message = urlencoding::encode(&message);
-        let twiml = format!("<Response><Say>{}</Say></Response>", message);
-        let body = format!("Twiml={}&To={}&From={}", twiml, to, self.from);
+        let mut body = format!(
+            "Twiml={}&To={}&From={}",
+            urlencoding::encode(&twiml),
+            urlencoding::encode(&to),
+            urlencoding::encode(&self.from)
+        );
+        if let Some(status_callback) = status_callback {
+            body.push_str("&StatusCallback=");
+            body.push_str(&urlencoding::encode(&status_callback));
+        }
 
         self.http_client
             .post(&url)
@@ -79,6 +111,37 @@ impl TwilioClient {
             .error_for_status()?;
         Ok(())
     }
+
+    /// Whether `signature`, the `X-Twilio-Signature` header of a webhook request, proves that
+    /// Twilio posted `params` to `url` on behalf of this account.
+    pub fn is_valid_signature(
+        &self,
+        url: &str,
+        params: &[(String, String)],
+        signature: &str,
+    ) -> bool {
+        signature::is_valid(&self.auth_token, url, params, signature)
+    }
+}
+
+#[async_trait]
+impl SmsSender for TwilioClient {
+    async fn send_sms(&self, to: &str, message: &str) -> Result<(), anyhow::Error> {
+        TwilioClient::send_sms(self, to.to_string(), message.to_string()).await
+    }
+}
+
+#[async_trait]
+impl VoiceCaller for TwilioClient {
+    async fn call(
+        &self,
+        to: &str,
+        twiml: &str,
+        status_callback: Option<String>,
+    ) -> Result<(), anyhow::Error> {
+        self.send_twiml_call(to.to_string(), twiml.to_string(), status_callback)
+            .await
+    }
 }
 
 #[cfg(test)]
@@ -86,7 +149,7 @@ mod tests {
     use std::time::Duration;
 
     use wiremock::{
-        matchers::{header, method, path},
+        matchers::{body_string, header, method, path},
         Mock, MockServer, Request, ResponseTemplate,
     };
 
@@ -113,11 +176,10 @@ mod tests {
         fn matches(&self, request: &Request) -> bool {
             let result = String::from_utf8(request.body.clone());
             if let Ok(body) = result {
-                let message = urlencoding::encode("Hello, World!");
-                let twiml = format!("<Response><Say>{}</Say></Response>", message);
-                body.contains(&format!("Twiml={}", twiml))
-                    && body.contains(&format!("To={}", "+12321231234"))
-                    && body.contains(&format!("From={}", "+12321231234"))
+                let twiml = "<Response><Say>Hello, World!</Say></Response>";
+                body.contains(&format!("Twiml={}", urlencoding::encode(twiml)))
+                    && body.contains(&format!("To={}", urlencoding::encode("+12321231234")))
+                    && body.contains(&format!("From={}", urlencoding::encode("+12321231234")))
             } else {
                 false
             }
@@ -179,4 +241,36 @@ mod tests {
             .await
             .unwrap();
     }
+
+    #[tokio::test]
+    async fn send_twiml_call_sends_the_encoded_twiml_and_status_callback() {
+        let mock_server = MockServer::start().await;
+        let twiml = "<Response><Say>Table &amp; bar</Say></Response>";
+        let status_callback = "https://hermodapp.com/webhooks/twilio/voice-status?id=1";
+        let client = twilio_client(mock_server.uri());
+        let url = format!("Accounts/{}/Calls.json", client.account_sid,);
+
+        Mock::given(path(url))
+            .and(method("POST"))
+            .and(body_string(format!(
+                "Twiml={}&To={}&From={}&StatusCallback={}",
+                urlencoding::encode(twiml),
+                urlencoding::encode("+12321231234"),
+                urlencoding::encode("+12321231234"),
+                urlencoding::encode(status_callback)
+            )))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        client
+            .send_twiml_call(
+                "+12321231234".to_string(),
+                twiml.to_string(),
+                Some(status_callback.to_string()),
+            )
+            .await
+            .unwrap();
+    }
 }
@@ -0,0 +1,90 @@
+//! Contains the validation of the `X-Twilio-Signature` header sent with Twilio's webhooks.
+use hmac::{Hmac, Mac, NewMac};
+use sha1::Sha1;
+
+type HmacSha1 = Hmac<Sha1>;
+
+/// Computes the signature Twilio sends when it posts `params` to `url`: the base64 encoded
+/// HMAC-SHA1, keyed with the account's auth token, of the URL followed by every parameter
+/// name and value, sorted by name.
+pub fn sign(auth_token: &str, url: &str, params: &[(String, String)]) -> String {
+    base64::encode(mac(auth_token, url, params).finalize().into_bytes())
+}
+
+/// Whether `signature` is the one Twilio would have sent when posting `params` to `url`.
+/// The comparison runs in constant time.
+pub fn is_valid(auth_token: &str, url: &str, params: &[(String, String)], signature: &str) -> bool {
+    match base64::decode(signature) {
+        Ok(signature) => mac(auth_token, url, params).verify(&signature).is_ok(),
+        Err(_) => false,
+    }
+}
+
+fn mac(auth_token: &str, url: &str, params: &[(String, String)]) -> HmacSha1 {
+    let mut params: Vec<&(String, String)> = params.iter().collect();
+    params.sort();
+    let mut mac =
+        HmacSha1::new_from_slice(auth_token.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(url.as_bytes());
+    for (name, value) in params {
+        mac.update(name.as_bytes());
+        mac.update(value.as_bytes());
+    }
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_valid, sign};
+
+    const URL: &str = "https://mycompany.com/myapp.php?foo=1&bar=2";
+
+    fn params() -> Vec<(String, String)> {
+        [
+            ("CallSid", "CA1234567890ABCDE"),
+            ("Caller", "+12349013030"),
+            ("Digits", "1234"),
+            ("From", "+12349013030"),
+            ("To", "+18005551212"),
+        ]
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+    }
+
+    #[test]
+    fn signature_matches_twilios_example() {
+        assert_eq!(
+            "0/KCTR6DLpKmkAf8muzZqo1nDgQ=",
+            sign("12345", URL, &params())
+        );
+    }
+
+    #[test]
+    fn signature_does_not_depend_on_parameter_order() {
+        let mut reversed = params();
+        reversed.reverse();
+        assert!(is_valid(
+            "12345",
+            URL,
+            &reversed,
+            "0/KCTR6DLpKmkAf8muzZqo1nDgQ="
+        ));
+    }
+
+    #[test]
+    fn tampered_requests_are_rejected() {
+        let signature = sign("12345", URL, &params());
+        let mut tampered = params();
+        tampered[2].1 = "1".to_string();
+        assert!(!is_valid("12345", URL, &tampered, &signature));
+        assert!(!is_valid("54321", URL, &params(), &signature));
+        assert!(!is_valid(
+            "12345",
+            "https://mycompany.com/myapp.php",
+            &params(),
+            &signature
+        ));
+        assert!(!is_valid("12345", URL, &params(), "not base64!"));
+    }
+}
@@ -0,0 +1,316 @@
+//! Contains a typed builder for TwiML, the XML documents that tell Twilio what to do during a call
+//! or how to reply to an incoming SMS.
+use std::fmt::Write;
+
+/// A TwiML `<Response>` document.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Twiml {
+    verbs: Vec<Verb>,
+}
+
+/// An instruction executed during a call.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verb {
+    Say(Say),
+    /// Waits silently for the given number of seconds.
+    Pause(u32),
+    Play(Play),
+    Gather(Gather),
+    /// Replies to an incoming SMS.
+    Message(String),
+}
+
+/// Reads text out loud.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Say {
+    text: String,
+    voice: Option<String>,
+    language: Option<String>,
+    loop_count: Option<u32>,
+}
+
+/// Plays an audio file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Play {
+    url: String,
+    loop_count: Option<u32>,
+}
+
+/// Collects digits pressed by the callee while its nested verbs are executed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gather {
+    num_digits: Option<u32>,
+    action: Option<String>,
+    timeout: Option<u32>,
+    verbs: Vec<Verb>,
+}
+
+impl Twiml {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn say(mut self, say: Say) -> Self {
+        self.verbs.push(Verb::Say(say));
+        self
+    }
+
+    pub fn pause(mut self, seconds: u32) -> Self {
+        self.verbs.push(Verb::Pause(seconds));
+        self
+    }
+
+    pub fn play(mut self, play: Play) -> Self {
+        self.verbs.push(Verb::Play(play));
+        self
+    }
+
+    pub fn gather(mut self, gather: Gather) -> Self {
+        self.verbs.push(Verb::Gather(gather));
+        self
+    }
+
+    pub fn message(mut self, text: impl Into<String>) -> Self {
+        self.verbs.push(Verb::Message(text.into()));
+        self
+    }
+
+    /// Serializes the document, escaping all text and attribute values.
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from("<Response>");
+        for verb in &self.verbs {
+            verb.write(&mut xml);
+        }
+        xml.push_str("</Response>");
+        xml
+    }
+}
+
+impl Say {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            voice: None,
+            language: None,
+            loop_count: None,
+        }
+    }
+
+    /// Voice used to read the text, e.g. `alice` or `Polly.Lupe`.
+    pub fn voice(mut self, voice: impl Into<String>) -> Self {
+        self.voice = Some(voice.into());
+        self
+    }
+
+    /// Language the text is read in, e.g. `es-MX`.
+    pub fn language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    /// Number of times the text is read.
+    pub fn loop_count(mut self, loop_count: u32) -> Self {
+        self.loop_count = Some(loop_count);
+        self
+    }
+}
+
+impl Play {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            loop_count: None,
+        }
+    }
+
+    /// Number of times the audio file is played.
+    pub fn loop_count(mut self, loop_count: u32) -> Self {
+        self.loop_count = Some(loop_count);
+        self
+    }
+}
+
+impl Gather {
+    pub fn new() -> Self {
+        Self {
+            num_digits: None,
+            action: None,
+            timeout: None,
+            verbs: Vec::new(),
+        }
+    }
+
+    /// Number of digits to collect before submitting them.
+    pub fn num_digits(mut self, num_digits: u32) -> Self {
+        self.num_digits = Some(num_digits);
+        self
+    }
+
+    /// URL Twilio posts the collected digits to.
+    pub fn action(mut self, action: impl Into<String>) -> Self {
+        self.action = Some(action.into());
+        self
+    }
+
+    /// Seconds to wait for the next digit.
+    pub fn timeout(mut self, timeout: u32) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn say(mut self, say: Say) -> Self {
+        self.verbs.push(Verb::Say(say));
+        self
+    }
+
+    pub fn pause(mut self, seconds: u32) -> Self {
+        self.verbs.push(Verb::Pause(seconds));
+        self
+    }
+
+    pub fn play(mut self, play: Play) -> Self {
+        self.verbs.push(Verb::Play(play));
+        self
+    }
+}
+
+impl Default for Gather {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Verb {
+    fn write(&self, xml: &mut String) {
+        match self {
+            Self::Say(say) => {
+                xml.push_str("<Say");
+                write_attribute(xml, "voice", say.voice.as_deref());
+                write_attribute(xml, "language", say.language.as_deref());
+                write_attribute(xml, "loop", say.loop_count);
+                xml.push('>');
+                xml.push_str(&escape(&say.text));
+                xml.push_str("</Say>");
+            }
+            Self::Pause(seconds) => {
+                let _ = write!(xml, r#"<Pause length="{}"/>"#, seconds);
+            }
+            Self::Play(play) => {
+                xml.push_str("<Play");
+                write_attribute(xml, "loop", play.loop_count);
+                xml.push('>');
+                xml.push_str(&escape(&play.url));
+                xml.push_str("</Play>");
+            }
+            Self::Gather(gather) => {
+                xml.push_str("<Gather");
+                write_attribute(xml, "numDigits", gather.num_digits);
+                write_attribute(xml, "action", gather.action.as_deref());
+                if gather.action.is_some() {
+                    write_attribute(xml, "method", Some("POST"));
+                }
+                write_attribute(xml, "timeout", gather.timeout);
+                xml.push('>');
+                for verb in &gather.verbs {
+                    verb.write(xml);
+                }
+                xml.push_str("</Gather>");
+            }
+            Self::Message(text) => {
+                xml.push_str("<Message>");
+                xml.push_str(&escape(text));
+                xml.push_str("</Message>");
+            }
+        }
+    }
+}
+
+fn write_attribute(xml: &mut String, name: &str, value: Option<impl ToString>) {
+    if let Some(value) = value {
+        let _ = write!(xml, r#" {}="{}""#, name, escape(&value.to_string()));
+    }
+}
+
+/// Escapes text for use in XML content and attribute values.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Gather, Play, Say, Twiml};
+
+    #[test]
+    fn say_is_escaped() {
+        let twiml = Twiml::new().say(Say::new("Fish & <chips>")).to_xml();
+        assert_eq!(
+            "<Response><Say>Fish &amp; &lt;chips&gt;</Say></Response>",
+            twiml
+        );
+    }
+
+    #[test]
+    fn say_supports_voice_language_and_loop() {
+        let twiml = Twiml::new()
+            .say(
+                Say::new("La mesa 4 necesita ayuda")
+                    .voice("Polly.Lupe")
+                    .language("es-US")
+                    .loop_count(2),
+            )
+            .to_xml();
+        assert_eq!(
+            r#"<Response><Say voice="Polly.Lupe" language="es-US" loop="2">La mesa 4 necesita ayuda</Say></Response>"#,
+            twiml
+        );
+    }
+
+    #[test]
+    fn pause_play_and_gather_are_nested_correctly() {
+        let twiml = Twiml::new()
+            .play(Play::new("https://example.com/chime.mp3?a=1&b=2").loop_count(1))
+            .pause(1)
+            .gather(
+                Gather::new()
+                    .num_digits(1)
+                    .action("https://hermodapp.com/twilio/gather?id=1")
+                    .timeout(5)
+                    .say(Say::new("Press 1 to acknowledge.")),
+            )
+            .to_xml();
+        assert_eq!(
+            concat!(
+                "<Response>",
+                r#"<Play loop="1">https://example.com/chime.mp3?a=1&amp;b=2</Play>"#,
+                r#"<Pause length="1"/>"#,
+                r#"<Gather numDigits="1" action="https://hermodapp.com/twilio/gather?id=1" method="POST" timeout="5">"#,
+                "<Say>Press 1 to acknowledge.</Say>",
+                "</Gather>",
+                "</Response>"
+            ),
+            twiml
+        );
+    }
+
+    #[test]
+    fn message_is_escaped() {
+        let twiml = Twiml::new()
+            .message("Thanks, <Table 4> is covered")
+            .to_xml();
+        assert_eq!(
+            "<Response><Message>Thanks, &lt;Table 4&gt; is covered</Message></Response>",
+            twiml
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use std::fmt::Debug;
+
+use chrono::{NaiveDateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Represents a logo uploaded by an account to brand its QR codes.
+#[derive(sqlx::FromRow, Clone)]
+pub struct AccountLogo {
+    pub account_id: Uuid,
+    pub content_type: String,
+    pub data: Vec<u8>,
+    pub uploaded_at: NaiveDateTime,
+}
+
+impl Debug for AccountLogo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AccountLogo")
+            .field("account_id", &self.account_id)
+            .field("content_type", &self.content_type)
+            .field("size", &self.data.len())
+            .field("uploaded_at", &self.uploaded_at)
+            .finish()
+    }
+}
+
+impl AccountLogo {
+    pub fn new(account_id: Uuid, content_type: String, data: Vec<u8>) -> Self {
+        Self {
+            account_id,
+            content_type,
+            data,
+            uploaded_at: Utc::now().naive_utc(),
+        }
+    }
+
+    /// Stores this logo, replacing any logo previously uploaded by the account.
+    pub async fn store(&self, pool: &PgPool) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            "INSERT INTO account_logo (account_id, content_type, data, uploaded_at)
+             VALUES ($1, $2, $3, $4)
+             ON CONFLICT (account_id) DO UPDATE
+             SET content_type = $2, data = $3, uploaded_at = $4",
+            self.account_id,
+            self.content_type,
+            self.data,
+            self.uploaded_at
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Returns the logo uploaded by the account with the given `account_id`, if any.
+pub async fn get_account_logo(
+    account_id: Uuid,
+    pool: &PgPool,
+) -> Result<Option<AccountLogo>, anyhow::Error> {
+    let logo = sqlx::query_as!(
+        AccountLogo,
+        "SELECT * FROM account_logo WHERE account_id = $1",
+        account_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(logo)
+}
@@ -0,0 +1,218 @@
+use std::str::FromStr;
+
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::db::NotificationStatus;
+
+/// Where an alert is in the acknowledge-and-escalate workflow.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertStatus {
+    /// Waiting for one of the QR code's recipients to acknowledge it.
+    Open,
+    /// Was not acknowledged in time and has been sent to the escalation recipients.
+    Escalated,
+    Acknowledged,
+    /// Its QR code was moved to the trash before the alert was acknowledged or escalated.
+    Cancelled,
+}
+
+impl AlertStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Escalated => "escalated",
+            Self::Acknowledged => "acknowledged",
+            Self::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl FromStr for AlertStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "open" => Ok(Self::Open),
+            "escalated" => Ok(Self::Escalated),
+            "acknowledged" => Ok(Self::Acknowledged),
+            "cancelled" => Ok(Self::Cancelled),
+            other => Err(anyhow::anyhow!(
+                "{} is not a supported alert status.",
+                other
+            )),
+        }
+    }
+}
+
+/// Represents a scan that someone needs to attend to. Every notification sent for the scan
+/// belongs to its alert, and any of them can be used to acknowledge it.
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+pub struct Alert {
+    pub id: Uuid,
+    pub qr_code_id: Uuid,
+    pub scan_event_id: Option<Uuid>,
+    /// Notification text rendered in the QR code's time zone, resent when the alert is escalated.
+    pub message: String,
+    pub status: String,
+    pub created_at: NaiveDateTime,
+    /// When the alert is escalated if it is still open, `None` if the QR code does not escalate alerts.
+    pub escalate_at: Option<NaiveDateTime>,
+    pub escalated_at: Option<NaiveDateTime>,
+    pub acknowledged_at: Option<NaiveDateTime>,
+    /// Address of the recipient who acknowledged the alert.
+    pub acknowledged_by: Option<String>,
+}
+
+impl Alert {
+    pub fn new(
+        qr_code_id: Uuid,
+        scan_event_id: Option<Uuid>,
+        message: String,
+        escalate_at: Option<NaiveDateTime>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            qr_code_id,
+            scan_event_id,
+            message,
+            status: AlertStatus::Open.as_str().to_string(),
+            created_at: Utc::now().naive_utc(),
+            escalate_at,
+            escalated_at: None,
+            acknowledged_at: None,
+            acknowledged_by: None,
+        }
+    }
+
+    pub async fn store(&self, executor: impl PgExecutor<'_>) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            "INSERT INTO alert (id, qr_code_id, scan_event_id, message, status, created_at, escalate_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            self.id,
+            self.qr_code_id,
+            self.scan_event_id,
+            self.message,
+            self.status,
+            self.created_at,
+            self.escalate_at
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Marks the alert with the given `alert_id` as acknowledged by `acknowledged_by` and cancels its
+/// notifications that have not been delivered yet, unless it is already acknowledged or cancelled.
+/// Returns the status the alert had, so an `open` or `escalated` status means it was acknowledged now.
+pub async fn acknowledge_alert(
+    alert_id: Uuid,
+    acknowledged_by: &str,
+    pool: &PgPool,
+) -> Result<AlertStatus, anyhow::Error> {
+    let mut tx = pool.begin().await?;
+    let status: AlertStatus = sqlx::query!(
+        "SELECT status FROM alert WHERE id = $1 FOR UPDATE",
+        alert_id
+    )
+    .fetch_one(&mut tx)
+    .await?
+    .status
+    .parse()?;
+    if matches!(status, AlertStatus::Open | AlertStatus::Escalated) {
+        sqlx::query!(
+            r#"
+                UPDATE alert
+                SET status = $2, acknowledged_at = $3, acknowledged_by = $4
+                WHERE id = $1
+            "#,
+            alert_id,
+            AlertStatus::Acknowledged.as_str(),
+            Utc::now().naive_utc(),
+            acknowledged_by
+        )
+        .execute(&mut tx)
+        .await?;
+        sqlx::query!(
+            "UPDATE notification SET status = $2 WHERE alert_id = $1 AND status = $3",
+            alert_id,
+            NotificationStatus::Cancelled.as_str(),
+            NotificationStatus::Pending.as_str()
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(status)
+}
+
+/// Cancels the open alerts of the QR code with the given `qr_code_id`, so that they are never escalated.
+pub async fn cancel_open_alerts(
+    qr_code_id: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        "UPDATE alert SET status = $2 WHERE qr_code_id = $1 AND status = $3",
+        qr_code_id,
+        AlertStatus::Cancelled.as_str(),
+        AlertStatus::Open.as_str()
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// Returns the latest unacknowledged alert that was sent to `recipient`, if any.
+pub async fn get_latest_unacknowledged_alert_for_recipient(
+    recipient: &str,
+    pool: &PgPool,
+) -> Result<Option<Alert>, anyhow::Error> {
+    let alert = sqlx::query_as!(
+        Alert,
+        r#"
+            SELECT alert.* FROM alert
+            WHERE alert.status IN ($2, $3) AND EXISTS (
+                SELECT 1 FROM notification
+                WHERE notification.alert_id = alert.id AND notification.recipient = $1
+            )
+            ORDER BY alert.created_at DESC
+            LIMIT 1
+        "#,
+        recipient,
+        AlertStatus::Open.as_str(),
+        AlertStatus::Escalated.as_str()
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(alert)
+}
+
+/// Returns the most recent alerts of the QR code with the given `qr_code_id`, newest first,
+/// optionally only those with the given `status`.
+pub async fn get_alerts_for_qr_code(
+    qr_code_id: Uuid,
+    status: Option<AlertStatus>,
+    limit: i64,
+    pool: &PgPool,
+) -> Result<Vec<Alert>, anyhow::Error> {
+    let alerts = sqlx::query_as!(
+        Alert,
+        r#"
+            SELECT * FROM alert
+            WHERE qr_code_id = $1 AND ($2::TEXT IS NULL OR status = $2)
+            ORDER BY created_at DESC
+            LIMIT $3
+        "#,
+        qr_code_id,
+        status.map(|status| status.as_str()),
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(alerts)
+}
@@ -0,0 +1,58 @@
+use chrono::{NaiveDateTime, Utc};
+use serde::Serialize;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+/// Represents an event Postmark reported for a sent email, such as its delivery or a bounce.
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+pub struct EmailEvent {
+    pub id: Uuid,
+    /// Notification the email was sent for, `None` for other emails such as password resets.
+    pub notification_id: Option<Uuid>,
+    /// Postmark's id of the email.
+    pub message_id: Option<String>,
+    /// Postmark's record type, e.g. `Delivery`, `Bounce`, `SpamComplaint` or `Open`.
+    pub record_type: String,
+    pub recipient: Option<String>,
+    /// Bounce type or description, when Postmark provides one.
+    pub detail: Option<String>,
+    pub received_at: NaiveDateTime,
+}
+
+impl EmailEvent {
+    pub fn new(
+        notification_id: Option<Uuid>,
+        message_id: Option<String>,
+        record_type: String,
+        recipient: Option<String>,
+        detail: Option<String>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            notification_id,
+            message_id,
+            record_type,
+            recipient,
+            detail,
+            received_at: Utc::now().naive_utc(),
+        }
+    }
+
+    pub async fn store(&self, executor: impl PgExecutor<'_>) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            "INSERT INTO email_event (id, notification_id, message_id, record_type, recipient, detail, received_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            self.id,
+            self.notification_id,
+            self.message_id,
+            self.record_type,
+            self.recipient,
+            self.detail,
+            self.received_at
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+}
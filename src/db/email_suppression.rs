@@ -0,0 +1,98 @@
+use chrono::{NaiveDateTime, Utc};
+use serde::Serialize;
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+/// Represents an email address an account no longer sends emails to, because emails to it hard-bounced.
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+pub struct EmailSuppression {
+    pub id: Uuid,
+    pub account_id: Uuid,
+    /// Suppressed address, in lowercase.
+    pub email: String,
+    /// Why the address was suppressed, e.g. `HardBounce`.
+    pub reason: String,
+    /// Postmark's id of the email that caused the suppression.
+    pub message_id: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl EmailSuppression {
+    pub fn new(account_id: Uuid, email: &str, reason: String, message_id: Option<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            account_id,
+            email: email.to_lowercase(),
+            reason,
+            message_id,
+            created_at: Utc::now().naive_utc(),
+        }
+    }
+
+    /// Stores this suppression, unless the account already suppresses the address.
+    pub async fn store(&self, executor: impl PgExecutor<'_>) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            "INSERT INTO email_suppression (id, account_id, email, reason, message_id, created_at)
+             VALUES ($1, $2, $3, $4, $5, $6)
+             ON CONFLICT (account_id, email) DO NOTHING",
+            self.id,
+            self.account_id,
+            self.email,
+            self.reason,
+            self.message_id,
+            self.created_at
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Returns every address suppressed by the account with the given `account_id`, newest first.
+pub async fn get_email_suppressions(
+    account_id: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> Result<Vec<EmailSuppression>, anyhow::Error> {
+    let suppressions = sqlx::query_as!(
+        EmailSuppression,
+        "SELECT * FROM email_suppression WHERE account_id = $1 ORDER BY created_at DESC",
+        account_id
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(suppressions)
+}
+
+/// Whether the account with the given `account_id` suppresses `email`.
+pub async fn is_email_suppressed(
+    account_id: Uuid,
+    email: &str,
+    executor: impl PgExecutor<'_>,
+) -> Result<bool, anyhow::Error> {
+    let suppression = sqlx::query!(
+        "SELECT id FROM email_suppression WHERE account_id = $1 AND email = $2",
+        account_id,
+        email.to_lowercase()
+    )
+    .fetch_optional(executor)
+    .await?;
+    Ok(suppression.is_some())
+}
+
+/// Lets the account with the given `account_id` send emails to `email` again.
+/// Returns whether the address was suppressed.
+pub async fn delete_email_suppression(
+    account_id: Uuid,
+    email: &str,
+    pool: &PgPool,
+) -> Result<bool, anyhow::Error> {
+    let deleted = sqlx::query!(
+        "DELETE FROM email_suppression WHERE account_id = $1 AND email = $2 RETURNING id",
+        account_id,
+        email.to_lowercase()
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(deleted.is_some())
+}
@@ -1,15 +1,123 @@
 use std::fmt::Debug;
+use std::str::FromStr;
 
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
+use sqlx::PgExecutor;
 use uuid::Uuid;
 
+/// Kind of answer a form field asks for, which decides how its answers are validated.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    Text,
+    Email,
+    Phone,
+    Number,
+    /// Number picked between the field's `min` and `max`.
+    Slider,
+    /// Date, time, or date and time in ISO 8601 format.
+    DateTime,
+    /// Single tick box, or several when the field has options.
+    Checkbox,
+    Radio,
+    Select,
+    /// Whole number of stars, from 1 to 5 unless configured otherwise.
+    Rating,
+    /// Uploaded image, sent as a base64 `data:` URL.
+    Image,
+}
+
+impl FieldType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Email => "email",
+            Self::Phone => "phone",
+            Self::Number => "number",
+            Self::Slider => "slider",
+            Self::DateTime => "datetime",
+            Self::Checkbox => "checkbox",
+            Self::Radio => "radio",
+            Self::Select => "select",
+            Self::Rating => "rating",
+            Self::Image => "image",
+        }
+    }
+
+    /// Whether answers are picked from the field's options.
+    pub fn has_options(&self) -> bool {
+        matches!(self, Self::Checkbox | Self::Radio | Self::Select)
+    }
+
+    /// Whether answers are free text that a pattern can constrain.
+    pub fn is_textual(&self) -> bool {
+        matches!(self, Self::Text | Self::Email | Self::Phone)
+    }
+}
+
+impl FromStr for FieldType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "email" => Ok(Self::Email),
+            "phone" => Ok(Self::Phone),
+            "number" => Ok(Self::Number),
+            "slider" => Ok(Self::Slider),
+            "datetime" => Ok(Self::DateTime),
+            "checkbox" => Ok(Self::Checkbox),
+            "radio" => Ok(Self::Radio),
+            "select" => Ok(Self::Select),
+            "rating" => Ok(Self::Rating),
+            "image" => Ok(Self::Image),
+            other => Err(anyhow::anyhow!("{} is not a supported field type.", other)),
+        }
+    }
+}
+
+/// Type-specific settings of a form field, stored as JSON alongside it.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct FieldConfig {
+    /// Whether responses must answer the field.
+    pub required: bool,
+    /// Lower bound of the answer: the length of text, the value of numbers, sliders and ratings,
+    /// or the number of options ticked in a checkbox field.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    /// Upper bound of the answer, like `min`. Limits the size of images, in kilobytes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    /// Choices of checkbox, radio and select fields.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<String>,
+    /// Regular expression the whole answer of a text, email or phone field must match.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+}
+
 #[derive(sqlx::FromRow, Serialize, Deserialize, Clone)]
 pub struct Field {
     pub id: Uuid,
     pub form_id: Uuid,
     pub field_type: String,
     pub caption: String,
+    pub config: serde_json::Value,
+    /// Order of the field among the form's fields.
+    pub position: i32,
+    /// Section the field is grouped in, if any.
+    pub section_id: Option<Uuid>,
+}
+
+impl Field {
+    pub fn field_type(&self) -> Result<FieldType, anyhow::Error> {
+        self.field_type.parse()
+    }
+
+    pub fn config(&self) -> Result<FieldConfig, anyhow::Error> {
+        Ok(serde_json::from_value(self.config.clone())?)
+    }
 }
 
 impl Debug for Field {
@@ -19,6 +127,9 @@ impl Debug for Field {
             .field("form_id", &self.form_id)
             .field("field_type", &self.field_type)
             .field("caption", &self.caption)
+            .field("config", &self.config)
+            .field("position", &self.position)
+            .field("section_id", &self.section_id)
             .finish()
     }
 }
@@ -28,6 +139,9 @@ pub struct NewField {
     pub form_id: Uuid,
     pub field_type: String,
     pub caption: String,
+    pub config: serde_json::Value,
+    pub position: i32,
+    pub section_id: Option<Uuid>,
 }
 
 impl NewField {
@@ -37,21 +151,44 @@ impl NewField {
             form_id: Uuid::new_v4(),
             field_type: String::new(),
             caption: String::new(),
+            config: serde_json::json!({}),
+            position: 0,
+            section_id: None,
         }
     }
 
-    pub async fn store(&self, pool: &PgPool) -> Result<(), anyhow::Error> {
+    pub async fn store(&self, executor: impl PgExecutor<'_>) -> Result<(), anyhow::Error> {
         sqlx::query!(
-            "INSERT INTO form_input (id, form_id, type, caption)
-             VALUES ($1, $2, $3, $4)",
+            "INSERT INTO form_input (id, form_id, type, caption, config, position, section_id)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)",
             self.id,
             self.form_id,
             self.field_type,
             self.caption,
+            self.config,
+            self.position,
+            self.section_id,
         )
-        .execute(pool)
+        .execute(executor)
         .await?;
 
         Ok(())
     }
 }
+
+/// Returns the fields of the form with the given `form_id` that have not been archived, in order.
+pub async fn get_form_fields(
+    form_id: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> Result<Vec<Field>, anyhow::Error> {
+    let fields = sqlx::query_as!(
+        Field,
+        r#"SELECT id, form_id, type AS field_type, COALESCE(caption, '') AS "caption!", config,
+                  position, section_id
+           FROM form_input WHERE form_id = $1 AND archived_at IS NULL ORDER BY position"#,
+        form_id
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(fields)
+}
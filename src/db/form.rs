@@ -1,14 +1,104 @@
 use std::fmt::Debug;
+use std::str::FromStr;
 
+use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
+use sqlx::{PgExecutor, PgPool};
 use uuid::Uuid;
 
+use crate::services::form_lifecycle::Lifecycle;
+
+/// Where a form is in its lifecycle, which decides whether it accepts responses.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FormStatus {
+    /// Still being written and only visible to its owner.
+    Draft,
+    /// Accepting responses within its open/close window.
+    Published,
+    /// No longer accepting responses.
+    Closed,
+    /// Closed and kept only for its earlier responses.
+    Archived,
+}
+
+impl Default for FormStatus {
+    fn default() -> Self {
+        Self::Published
+    }
+}
+
+impl FormStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Draft => "draft",
+            Self::Published => "published",
+            Self::Closed => "closed",
+            Self::Archived => "archived",
+        }
+    }
+}
+
+impl FromStr for FormStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "draft" => Ok(Self::Draft),
+            "published" => Ok(Self::Published),
+            "closed" => Ok(Self::Closed),
+            "archived" => Ok(Self::Archived),
+            other => Err(anyhow::anyhow!(
+                "{} is not a supported form status. Use `draft`, `published`, `closed` or `archived`.",
+                other
+            )),
+        }
+    }
+}
+
 #[derive(sqlx::FromRow, Serialize, Deserialize, Clone)]
 pub struct Form {
     pub id: Uuid,
     pub account_id: Uuid,
     pub title: String,
+    pub status: String,
+    /// Time the form starts accepting responses, if it is scheduled to open.
+    pub opens_at: Option<NaiveDateTime>,
+    /// Time the form stops accepting responses, if it is scheduled to close.
+    pub closes_at: Option<NaiveDateTime>,
+    /// Most responses the form accepts, if they are capped.
+    pub max_responses: Option<i32>,
+}
+
+impl Form {
+    /// The settings that decide whether the form accepts responses.
+    pub fn lifecycle(&self) -> Result<Lifecycle, anyhow::Error> {
+        Ok(Lifecycle {
+            status: self.status.parse()?,
+            opens_at: self.opens_at,
+            closes_at: self.closes_at,
+            max_responses: self.max_responses,
+        })
+    }
+
+    /// Changes the status, window and response cap of the form.
+    pub async fn update_lifecycle(
+        &self,
+        executor: impl PgExecutor<'_>,
+    ) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            "UPDATE form SET status = $1, opens_at = $2, closes_at = $3, max_responses = $4
+             WHERE id = $5",
+            self.status,
+            self.opens_at,
+            self.closes_at,
+            self.max_responses,
+            self.id
+        )
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
 }
 
 impl Debug for Form {
@@ -17,6 +107,7 @@ impl Debug for Form {
             .field("id", &self.id)
             .field("title", &self.title)
             .field("account_id", &self.account_id)
+            .field("status", &self.status)
             .finish()
     }
 }
@@ -25,6 +116,7 @@ pub struct NewForm {
     pub id: Uuid,
     pub account_id: Uuid,
     pub title: String,
+    pub status: String,
 }
 
 impl NewForm {
@@ -33,16 +125,18 @@ impl NewForm {
             id: Uuid::new_v4(),
             title: String::new(),
             account_id: Uuid::new_v4(),
+            status: FormStatus::default().as_str().to_string(),
         }
     }
 
     pub async fn store(&self, pool: &PgPool) -> Result<(), anyhow::Error> {
         sqlx::query!(
-            "INSERT INTO form (id, account_id, title)
-             VALUES ($1, $2, $3)",
+            "INSERT INTO form (id, account_id, title, status)
+             VALUES ($1, $2, $3, $4)",
             self.id,
             self.account_id,
-            self.title
+            self.title,
+            self.status
         )
         .execute(pool)
         .await?;
@@ -50,3 +144,56 @@ impl NewForm {
         Ok(())
     }
 }
+
+/// Returns the form with the given `id` if it belongs to the account with the given `account_id`
+/// and is not in the trash.
+pub async fn get_owned_form(
+    id: Uuid,
+    account_id: Uuid,
+    pool: &PgPool,
+) -> Result<Option<Form>, anyhow::Error> {
+    let form = sqlx::query_as!(
+        Form,
+        r#"SELECT id, account_id, COALESCE(title, '') AS "title!", status, opens_at, closes_at,
+               max_responses
+           FROM form WHERE id=$1 AND account_id=$2 AND deleted_at IS NULL"#,
+        id,
+        account_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(form)
+}
+
+/// Returns the form with the given `id` unless it is in the trash.
+pub async fn get_form_by_id(id: Uuid, pool: &PgPool) -> Result<Option<Form>, anyhow::Error> {
+    let form = sqlx::query_as!(
+        Form,
+        r#"SELECT id, account_id, COALESCE(title, '') AS "title!", status, opens_at, closes_at,
+               max_responses
+           FROM form WHERE id=$1 AND deleted_at IS NULL"#,
+        id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(form)
+}
+
+/// Returns the form with the given `id`, locking it until the end of the transaction so that
+/// concurrent submissions are checked against its lifecycle, and concurrent edits applied, one at a time.
+pub async fn lock_form(
+    id: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> Result<Option<Form>, anyhow::Error> {
+    let form = sqlx::query_as!(
+        Form,
+        r#"SELECT id, account_id, COALESCE(title, '') AS "title!", status, opens_at, closes_at,
+               max_responses
+           FROM form WHERE id=$1 AND deleted_at IS NULL
+           FOR UPDATE"#,
+        id
+    )
+    .fetch_optional(executor)
+    .await?;
+    Ok(form)
+}
@@ -0,0 +1,116 @@
+use std::str::FromStr;
+
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+/// File format form responses are attached to their notification emails in.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AttachmentFormat {
+    Csv,
+    Pdf,
+}
+
+impl AttachmentFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Pdf => "pdf",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv",
+            Self::Pdf => "application/pdf",
+        }
+    }
+}
+
+impl Default for AttachmentFormat {
+    fn default() -> Self {
+        Self::Csv
+    }
+}
+
+impl FromStr for AttachmentFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Self::Csv),
+            "pdf" => Ok(Self::Pdf),
+            other => Err(anyhow::anyhow!(
+                "{} is not a supported attachment format. Use `csv` or `pdf`.",
+                other
+            )),
+        }
+    }
+}
+
+/// Represents who is emailed when a form receives a response, and how.
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+pub struct FormNotificationSettings {
+    pub form_id: Uuid,
+    /// Email addresses every new response is sent to. Nobody is emailed when it is empty.
+    pub recipients: Vec<String>,
+    /// Whether the response is also attached to the email as a file.
+    pub include_attachment: bool,
+    pub attachment_format: String,
+    pub updated_at: NaiveDateTime,
+}
+
+impl FormNotificationSettings {
+    /// Settings of forms whose owner has not configured any, which email nobody.
+    pub fn new(form_id: Uuid) -> Self {
+        Self {
+            form_id,
+            recipients: Vec::new(),
+            include_attachment: false,
+            attachment_format: AttachmentFormat::default().as_str().to_string(),
+            updated_at: Utc::now().naive_utc(),
+        }
+    }
+
+    pub fn attachment_format(&self) -> Result<AttachmentFormat, anyhow::Error> {
+        self.attachment_format.parse()
+    }
+
+    /// Stores these settings, replacing the form's previous settings.
+    pub async fn store(&self, executor: impl PgExecutor<'_>) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            "INSERT INTO form_notification_settings
+                 (form_id, recipients, include_attachment, attachment_format, updated_at)
+             VALUES ($1, $2, $3, $4, $5)
+             ON CONFLICT (form_id) DO UPDATE
+             SET recipients = $2, include_attachment = $3, attachment_format = $4, updated_at = $5",
+            self.form_id,
+            &self.recipients,
+            self.include_attachment,
+            self.attachment_format,
+            self.updated_at
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Returns the notification settings of the form with the given `form_id`, or the default
+/// settings if its owner has not configured any.
+pub async fn get_form_notification_settings(
+    form_id: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> Result<FormNotificationSettings, anyhow::Error> {
+    let settings = sqlx::query_as!(
+        FormNotificationSettings,
+        "SELECT * FROM form_notification_settings WHERE form_id = $1",
+        form_id
+    )
+    .fetch_optional(executor)
+    .await?;
+    Ok(settings.unwrap_or_else(|| FormNotificationSettings::new(form_id)))
+}
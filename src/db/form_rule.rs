@@ -0,0 +1,155 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+/// What a rule does to a form when its conditions are met.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    /// Shows a field that is hidden unless one of its `show` rules is met.
+    Show,
+    /// Hides a field.
+    Hide,
+    /// Skips the fields between the rule's conditions and a later section.
+    Skip,
+}
+
+impl RuleAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Show => "show",
+            Self::Hide => "hide",
+            Self::Skip => "skip",
+        }
+    }
+}
+
+impl FromStr for RuleAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "show" => Ok(Self::Show),
+            "hide" => Ok(Self::Hide),
+            "skip" => Ok(Self::Skip),
+            other => Err(anyhow::anyhow!(
+                "{} is not a supported rule action. Use `show`, `hide` or `skip`.",
+                other
+            )),
+        }
+    }
+}
+
+/// How a condition tests the answer to a field.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConditionOperator {
+    /// The answer is the condition's value, compared as numbers if both are numbers.
+    Equals,
+    /// The answer includes the condition's value, or ticks it if the answer is a list of options.
+    Contains,
+    /// The answer is a number below the condition's value.
+    LessThan,
+    /// The field was answered at all.
+    Answered,
+}
+
+/// A test of the answer to an earlier field of the form.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub field_id: Uuid,
+    pub operator: ConditionOperator,
+    /// Value the answer is tested against, which `answered` conditions do without.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+}
+
+/// Represents a rule that shows, hides or skips fields of a form depending on earlier answers.
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+pub struct FormRule {
+    pub id: Uuid,
+    pub form_id: Uuid,
+    /// Order of the rule among the form's rules.
+    pub position: i32,
+    pub action: String,
+    /// Field that `show` and `hide` rules apply to.
+    pub target_field_id: Option<Uuid>,
+    /// Section that `skip` rules skip ahead to.
+    pub target_section_id: Option<Uuid>,
+    /// Whether the rule applies when any of its conditions is met, rather than all of them.
+    pub match_any: bool,
+    pub conditions: serde_json::Value,
+}
+
+impl FormRule {
+    pub fn new(form_id: Uuid, position: i32, action: RuleAction) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            form_id,
+            position,
+            action: action.as_str().to_string(),
+            target_field_id: None,
+            target_section_id: None,
+            match_any: false,
+            conditions: serde_json::json!([]),
+        }
+    }
+
+    /// Parses the stored `action`.
+    pub fn rule_action(&self) -> Result<RuleAction, anyhow::Error> {
+        RuleAction::from_str(&self.action)
+    }
+
+    /// Parses the stored `conditions`.
+    pub fn conditions(&self) -> Result<Vec<Condition>, anyhow::Error> {
+        Ok(serde_json::from_value(self.conditions.clone())?)
+    }
+
+    pub async fn store(&self, executor: impl PgExecutor<'_>) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            "INSERT INTO form_rule
+                 (id, form_id, position, action, target_field_id, target_section_id, match_any, conditions)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            self.id,
+            self.form_id,
+            self.position,
+            self.action,
+            self.target_field_id,
+            self.target_section_id,
+            self.match_any,
+            self.conditions
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Returns the rules of the form with the given `form_id`, in order.
+pub async fn get_form_rules(
+    form_id: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> Result<Vec<FormRule>, anyhow::Error> {
+    let rules = sqlx::query_as!(
+        FormRule,
+        "SELECT * FROM form_rule WHERE form_id = $1 ORDER BY position",
+        form_id
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(rules)
+}
+
+/// Deletes every rule of the form with the given `form_id`.
+pub async fn delete_form_rules(
+    form_id: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!("DELETE FROM form_rule WHERE form_id = $1", form_id)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
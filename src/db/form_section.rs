@@ -0,0 +1,61 @@
+use serde::Serialize;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+/// Represents a titled group of consecutive fields of a form.
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+pub struct FormSection {
+    pub id: Uuid,
+    pub form_id: Uuid,
+    /// Order of the section among the form's sections.
+    pub position: i32,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Whether the section starts a new page of the form.
+    pub page_break: bool,
+}
+
+impl FormSection {
+    pub fn new(form_id: Uuid, position: i32) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            form_id,
+            position,
+            title: None,
+            description: None,
+            page_break: false,
+        }
+    }
+
+    pub async fn store(&self, executor: impl PgExecutor<'_>) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            "INSERT INTO form_section (id, form_id, position, title, description, page_break)
+             VALUES ($1, $2, $3, $4, $5, $6)",
+            self.id,
+            self.form_id,
+            self.position,
+            self.title,
+            self.description,
+            self.page_break
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Returns the sections of the form with the given `form_id`, in order.
+pub async fn get_form_sections(
+    form_id: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> Result<Vec<FormSection>, anyhow::Error> {
+    let sections = sqlx::query_as!(
+        FormSection,
+        "SELECT * FROM form_section WHERE form_id = $1 ORDER BY position",
+        form_id
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(sections)
+}
@@ -0,0 +1,89 @@
+use chrono::{NaiveDateTime, Utc};
+use serde::Serialize;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::services::form_template::FormDefinition;
+
+/// Represents a form an account saved to create similar forms from.
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+pub struct FormTemplate {
+    pub id: Uuid,
+    pub account_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    /// Title, fields, sections and rules of forms created from the template, see `FormDefinition`.
+    pub definition: serde_json::Value,
+    pub created_at: NaiveDateTime,
+}
+
+impl FormTemplate {
+    pub fn new(
+        account_id: Uuid,
+        name: &str,
+        definition: &FormDefinition,
+    ) -> Result<Self, anyhow::Error> {
+        Ok(Self {
+            id: Uuid::new_v4(),
+            account_id,
+            name: name.to_string(),
+            description: None,
+            definition: serde_json::to_value(definition)?,
+            created_at: Utc::now().naive_utc(),
+        })
+    }
+
+    /// Parses the stored `definition`.
+    pub fn form_definition(&self) -> Result<FormDefinition, anyhow::Error> {
+        Ok(serde_json::from_value(self.definition.clone())?)
+    }
+
+    pub async fn store(&self, executor: impl PgExecutor<'_>) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            "INSERT INTO form_template (id, account_id, name, description, definition, created_at)
+             VALUES ($1, $2, $3, $4, $5, $6)",
+            self.id,
+            self.account_id,
+            self.name,
+            self.description,
+            self.definition,
+            self.created_at
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Returns the templates of the account with the given `account_id`, most recent first.
+pub async fn get_form_templates(
+    account_id: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> Result<Vec<FormTemplate>, anyhow::Error> {
+    let templates = sqlx::query_as!(
+        FormTemplate,
+        "SELECT * FROM form_template WHERE account_id = $1 ORDER BY created_at DESC",
+        account_id
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(templates)
+}
+
+/// Returns the template with the given `id` if it belongs to the account with the given `account_id`.
+pub async fn get_owned_form_template(
+    id: Uuid,
+    account_id: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> Result<Option<FormTemplate>, anyhow::Error> {
+    let template = sqlx::query_as!(
+        FormTemplate,
+        "SELECT * FROM form_template WHERE id = $1 AND account_id = $2",
+        id,
+        account_id
+    )
+    .fetch_optional(executor)
+    .await?;
+    Ok(template)
+}
@@ -0,0 +1,87 @@
+use chrono::{NaiveDateTime, Utc};
+use serde::Serialize;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+/// Represents the title and fields of a form as they were after it was created or edited.
+/// Versions are never changed, so responses can be read against the questions they answered.
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+pub struct FormVersion {
+    pub id: Uuid,
+    pub form_id: Uuid,
+    /// Number of the version among the form's versions, starting from 1.
+    pub version: i32,
+    pub title: String,
+    /// Fields of the form in order, each with its `field_id`, `caption`, `type`, `config`,
+    /// `position` and `section_id`.
+    pub fields: serde_json::Value,
+    pub created_at: NaiveDateTime,
+}
+
+/// Records the current title and fields of the form with the given `form_id` as its next
+/// version, and returns that version. Returns `None` if there is no such form.
+/// Forms that may be edited concurrently must be locked with `lock_form` first, or two versions
+/// could be given the same number.
+pub async fn record_form_version(
+    form_id: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> Result<Option<FormVersion>, anyhow::Error> {
+    let version = sqlx::query_as!(
+        FormVersion,
+        r#"INSERT INTO form_version (id, form_id, version, title, fields, created_at)
+           SELECT $1, form.id,
+               COALESCE((SELECT MAX(version) FROM form_version WHERE form_id = form.id), 0) + 1,
+               COALESCE(form.title, ''),
+               COALESCE((
+                   SELECT jsonb_agg(jsonb_build_object(
+                       'field_id', form_input.id,
+                       'caption', COALESCE(form_input.caption, ''),
+                       'type', form_input.type,
+                       'config', form_input.config,
+                       'position', form_input.position,
+                       'section_id', form_input.section_id
+                   ) ORDER BY form_input.position)
+                   FROM form_input
+                   WHERE form_input.form_id = form.id AND form_input.archived_at IS NULL
+               ), '[]'),
+               $2
+           FROM form WHERE form.id = $3
+           RETURNING id, form_id, version, title, fields, created_at"#,
+        Uuid::new_v4(),
+        Utc::now().naive_utc(),
+        form_id
+    )
+    .fetch_optional(executor)
+    .await?;
+    Ok(version)
+}
+
+/// Returns the latest version of the form with the given `form_id`, if it has any.
+pub async fn get_latest_form_version(
+    form_id: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> Result<Option<FormVersion>, anyhow::Error> {
+    let version = sqlx::query_as!(
+        FormVersion,
+        "SELECT * FROM form_version WHERE form_id = $1 ORDER BY version DESC LIMIT 1",
+        form_id
+    )
+    .fetch_optional(executor)
+    .await?;
+    Ok(version)
+}
+
+/// Returns every version of the form with the given `form_id`, oldest first.
+pub async fn get_form_versions(
+    form_id: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> Result<Vec<FormVersion>, anyhow::Error> {
+    let versions = sqlx::query_as!(
+        FormVersion,
+        "SELECT * FROM form_version WHERE form_id = $1 ORDER BY version",
+        form_id
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(versions)
+}
@@ -1,16 +1,40 @@
 //! Contains structs that model database tables.
+mod account_logo;
+mod alert;
+mod email_event;
+mod email_suppression;
 mod feedback;
 mod field;
 mod forgotten_password_request;
 mod form;
+mod form_notification_settings;
+mod form_rule;
+mod form_section;
+mod form_template;
+mod form_version;
+mod notification;
 mod qr_code;
+mod qr_code_recipient;
 mod response;
+mod scan_event;
 mod user;
 
+pub use account_logo::*;
+pub use alert::*;
+pub use email_event::*;
+pub use email_suppression::*;
 pub use feedback::*;
 pub use field::*;
 pub use forgotten_password_request::*;
 pub use form::*;
+pub use form_notification_settings::*;
+pub use form_rule::*;
+pub use form_section::*;
+pub use form_template::*;
+pub use form_version::*;
+pub use notification::*;
 pub use qr_code::*;
+pub use qr_code_recipient::*;
 pub use response::*;
+pub use scan_event::*;
 pub use user::*;
@@ -0,0 +1,378 @@
+use std::str::FromStr;
+
+use chrono::{NaiveDateTime, Utc};
+use serde::Serialize;
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+/// Channel a notification is delivered through.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NotificationChannel {
+    Call,
+    Sms,
+    Email,
+}
+
+impl NotificationChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Call => "call",
+            Self::Sms => "sms",
+            Self::Email => "email",
+        }
+    }
+}
+
+impl FromStr for NotificationChannel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "call" => Ok(Self::Call),
+            "sms" => Ok(Self::Sms),
+            "email" => Ok(Self::Email),
+            other => Err(anyhow::anyhow!(
+                "{} is not a supported notification channel.",
+                other
+            )),
+        }
+    }
+}
+
+/// Delivery state of a notification.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NotificationStatus {
+    /// Waiting for its first or next delivery attempt.
+    Pending,
+    Delivered,
+    /// Every delivery attempt failed and no more will be made.
+    Dead,
+    /// Its alert was acknowledged, or its QR code moved to the trash, before it could be delivered.
+    Cancelled,
+}
+
+impl NotificationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Delivered => "delivered",
+            Self::Dead => "dead",
+            Self::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// Represents a notification queued in the outbox, to be delivered by `services::notification_worker`.
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+pub struct Notification {
+    pub id: Uuid,
+    pub qr_code_id: Uuid,
+    pub scan_event_id: Option<Uuid>,
+    pub channel: String,
+    pub recipient: String,
+    /// Plain text for SMS, TwiML for calls and the text body for emails.
+    pub message: String,
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: NaiveDateTime,
+    pub last_error: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub delivered_at: Option<NaiveDateTime>,
+    /// HTML body for emails.
+    pub html_message: Option<String>,
+    /// Alert this notification asks its recipient to acknowledge.
+    pub alert_id: Option<Uuid>,
+    /// Provider's id of the call, as reported by its status callback.
+    pub provider_sid: Option<String>,
+    /// Latest status reported by the provider, e.g. `completed` or `no-answer` for calls.
+    pub provider_status: Option<String>,
+    pub provider_status_at: Option<NaiveDateTime>,
+    /// Subject of emails.
+    pub subject: Option<String>,
+}
+
+impl Notification {
+    pub fn new(
+        qr_code_id: Uuid,
+        scan_event_id: Option<Uuid>,
+        channel: NotificationChannel,
+        recipient: String,
+        message: String,
+    ) -> Self {
+        let now = Utc::now().naive_utc();
+        Self {
+            id: Uuid::new_v4(),
+            qr_code_id,
+            scan_event_id,
+            channel: channel.as_str().to_string(),
+            recipient,
+            message,
+            status: NotificationStatus::Pending.as_str().to_string(),
+            attempts: 0,
+            next_attempt_at: now,
+            last_error: None,
+            created_at: now,
+            delivered_at: None,
+            html_message: None,
+            alert_id: None,
+            provider_sid: None,
+            provider_status: None,
+            provider_status_at: None,
+            subject: None,
+        }
+    }
+
+    /// Queues this notification. Pass the transaction that records the triggering scan
+    /// so that the notification is only sent if the scan is stored.
+    pub async fn store(&self, executor: impl PgExecutor<'_>) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            "INSERT INTO notification
+                (id, qr_code_id, scan_event_id, channel, recipient, message, html_message, status, attempts, next_attempt_at, created_at, alert_id, subject)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)",
+            self.id,
+            self.qr_code_id,
+            self.scan_event_id,
+            self.channel,
+            self.recipient,
+            self.message,
+            self.html_message,
+            self.status,
+            self.attempts,
+            self.next_attempt_at,
+            self.created_at,
+            self.alert_id,
+            self.subject
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Represents a single attempt at delivering a notification.
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+pub struct NotificationAttempt {
+    pub id: Uuid,
+    pub notification_id: Uuid,
+    pub attempted_at: NaiveDateTime,
+    pub succeeded: bool,
+    pub error: Option<String>,
+}
+
+impl NotificationAttempt {
+    pub fn new(notification_id: Uuid, error: Option<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            notification_id,
+            attempted_at: Utc::now().naive_utc(),
+            succeeded: error.is_none(),
+            error,
+        }
+    }
+
+    pub async fn store(&self, executor: impl PgExecutor<'_>) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            "INSERT INTO notification_attempt (id, notification_id, attempted_at, succeeded, error)
+             VALUES ($1, $2, $3, $4, $5)",
+            self.id,
+            self.notification_id,
+            self.attempted_at,
+            self.succeeded,
+            self.error
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Represents a message a recipient sent back in reply to a notification.
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+pub struct NotificationReply {
+    pub id: Uuid,
+    pub notification_id: Uuid,
+    /// Phone number the reply was sent from.
+    pub sender: String,
+    pub body: String,
+    pub provider_sid: Option<String>,
+    pub received_at: NaiveDateTime,
+}
+
+impl NotificationReply {
+    pub fn new(
+        notification_id: Uuid,
+        sender: String,
+        body: String,
+        provider_sid: Option<String>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            notification_id,
+            sender,
+            body,
+            provider_sid,
+            received_at: Utc::now().naive_utc(),
+        }
+    }
+
+    pub async fn store(&self, executor: impl PgExecutor<'_>) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            "INSERT INTO notification_reply (id, notification_id, sender, body, provider_sid, received_at)
+             VALUES ($1, $2, $3, $4, $5, $6)",
+            self.id,
+            self.notification_id,
+            self.sender,
+            self.body,
+            self.provider_sid,
+            self.received_at
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Records the status the provider reported for the notification with the given `id`.
+/// Returns whether the notification exists.
+pub async fn record_provider_status(
+    id: Uuid,
+    provider_sid: Option<&str>,
+    provider_status: &str,
+    pool: &PgPool,
+) -> Result<bool, anyhow::Error> {
+    let updated = sqlx::query!(
+        r#"
+            UPDATE notification
+            SET provider_sid = COALESCE($2, provider_sid), provider_status = $3, provider_status_at = $4
+            WHERE id = $1
+            RETURNING id
+        "#,
+        id,
+        provider_sid,
+        provider_status,
+        Utc::now().naive_utc()
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(updated.is_some())
+}
+
+/// Returns the latest notification sent to `recipient` through one of the given channels, if any.
+pub async fn get_latest_notification_for_recipient(
+    recipient: &str,
+    channels: &[NotificationChannel],
+    pool: &PgPool,
+) -> Result<Option<Notification>, anyhow::Error> {
+    let channels: Vec<String> = channels
+        .iter()
+        .map(|channel| channel.as_str().to_string())
+        .collect();
+    let notification = sqlx::query_as!(
+        Notification,
+        r#"
+            SELECT * FROM notification
+            WHERE recipient = $1 AND channel = ANY($2)
+            ORDER BY created_at DESC
+            LIMIT 1
+        "#,
+        recipient,
+        &channels
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(notification)
+}
+
+/// Returns every reply to the notifications with the given ids, oldest first.
+pub async fn get_notification_replies(
+    notification_ids: &[Uuid],
+    pool: &PgPool,
+) -> Result<Vec<NotificationReply>, anyhow::Error> {
+    let replies = sqlx::query_as!(
+        NotificationReply,
+        "SELECT * FROM notification_reply WHERE notification_id = ANY($1) ORDER BY received_at",
+        notification_ids
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(replies)
+}
+
+/// Returns the notification the provider assigned the id `provider_sid` to, if any.
+pub async fn get_notification_by_provider_sid(
+    provider_sid: &str,
+    pool: &PgPool,
+) -> Result<Option<Notification>, anyhow::Error> {
+    let notification = sqlx::query_as!(
+        Notification,
+        "SELECT * FROM notification WHERE provider_sid = $1",
+        provider_sid
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(notification)
+}
+
+/// Returns the notification with the given `id`, if it exists.
+pub async fn get_notification(
+    id: Uuid,
+    pool: &PgPool,
+) -> Result<Option<Notification>, anyhow::Error> {
+    let notification =
+        sqlx::query_as!(Notification, "SELECT * FROM notification WHERE id = $1", id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(notification)
+}
+
+/// Cancels the notifications queued for the QR code with the given `qr_code_id` that have not
+/// been delivered yet.
+pub async fn cancel_pending_notifications(
+    qr_code_id: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        "UPDATE notification SET status = $2 WHERE qr_code_id = $1 AND status = $3",
+        qr_code_id,
+        NotificationStatus::Cancelled.as_str(),
+        NotificationStatus::Pending.as_str()
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// Returns the most recent notifications sent for the QR code with the given `qr_code_id`, newest first.
+pub async fn get_notifications_for_qr_code(
+    qr_code_id: Uuid,
+    limit: i64,
+    pool: &PgPool,
+) -> Result<Vec<Notification>, anyhow::Error> {
+    let notifications = sqlx::query_as!(
+        Notification,
+        "SELECT * FROM notification WHERE qr_code_id = $1 ORDER BY created_at DESC LIMIT $2",
+        qr_code_id,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(notifications)
+}
+
+/// Returns every delivery attempt of the notifications with the given ids, oldest first.
+pub async fn get_notification_attempts(
+    notification_ids: &[Uuid],
+    pool: &PgPool,
+) -> Result<Vec<NotificationAttempt>, anyhow::Error> {
+    let attempts = sqlx::query_as!(
+        NotificationAttempt,
+        "SELECT * FROM notification_attempt WHERE notification_id = ANY($1) ORDER BY attempted_at",
+        notification_ids
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(attempts)
+}
@@ -1,5 +1,71 @@
+use std::str::FromStr;
+
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use sqlx::PgPool;
 use uuid::Uuid;
 
+use crate::clients::twilio::twiml::{Gather, Say, Twiml};
+use crate::db::NotificationChannel;
+use crate::services::qr_image::{parse_hex_color, ModuleShape, QrStyle};
+
+/// Seconds a callee has to press a key to acknowledge an alert.
+const ACKNOWLEDGE_TIMEOUT_SECONDS: u32 = 10;
+
+/// How the phone number of a QR code is notified when the code is scanned.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PhoneChannel {
+    Call,
+    Sms,
+    Both,
+    None,
+}
+
+impl Default for PhoneChannel {
+    fn default() -> Self {
+        Self::Call
+    }
+}
+
+impl PhoneChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Call => "call",
+            Self::Sms => "sms",
+            Self::Both => "both",
+            Self::None => "none",
+        }
+    }
+
+    /// Notification channels a phone number is reached through.
+    pub fn channels(&self) -> &'static [NotificationChannel] {
+        match self {
+            Self::Call => &[NotificationChannel::Call],
+            Self::Sms => &[NotificationChannel::Sms],
+            Self::Both => &[NotificationChannel::Call, NotificationChannel::Sms],
+            Self::None => &[],
+        }
+    }
+}
+
+impl FromStr for PhoneChannel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "call" => Ok(Self::Call),
+            "sms" => Ok(Self::Sms),
+            "both" => Ok(Self::Both),
+            "none" => Ok(Self::None),
+            other => Err(anyhow::anyhow!(
+                "{} is not a supported notification channel. Use `call`, `sms`, `both` or `none`.",
+                other
+            )),
+        }
+    }
+}
+
 #[derive(serde::Serialize)]
 pub struct QrCode {
     pub id: Uuid,
@@ -8,4 +74,101 @@ pub struct QrCode {
     pub email: Option<String>,
     pub payload: Option<String>,
     pub form_id: Option<Uuid>,
+    pub foreground_color: String,
+    pub background_color: String,
+    pub module_shape: String,
+    pub quiet_zone: i32,
+    pub show_logo: bool,
+    pub label: Option<String>,
+    /// Minimum number of seconds between two notifications triggered by this QR code.
+    pub code_cooldown_seconds: i32,
+    /// Minimum number of seconds between two notifications triggered by the same client.
+    pub client_cooldown_seconds: i32,
+    /// How `phone_number` is notified of scans, see `PhoneChannel`.
+    pub notification_channel: String,
+    /// Offset of the owner's local time from UTC, used to render times in notifications.
+    pub utc_offset_minutes: i32,
+    /// Twilio voice used to read notifications in calls, e.g. `Polly.Lupe`.
+    pub voice: Option<String>,
+    /// Language notifications are read in during calls, e.g. `es-US`.
+    pub voice_language: Option<String>,
+    /// Number of times notifications are read during calls.
+    pub voice_loop: i32,
+    /// Seconds after which unacknowledged alerts are sent to the escalation recipients,
+    /// `None` if alerts are never escalated.
+    pub escalation_timeout_seconds: Option<i32>,
+    /// Time the QR code was moved to the trash, `None` unless it was deleted.
+    pub deleted_at: Option<NaiveDateTime>,
+}
+
+impl QrCode {
+    /// Text printed alongside this QR code, falling back to its id when it has no label.
+    pub fn caption(&self) -> String {
+        self.label.clone().unwrap_or_else(|| self.id.to_string())
+    }
+
+    /// Parses the stored `notification_channel`.
+    pub fn phone_channel(&self) -> Result<PhoneChannel, anyhow::Error> {
+        PhoneChannel::from_str(&self.notification_channel)
+    }
+
+    /// Builds the TwiML document reading `text` out loud with this QR code's voice settings, then
+    /// asking the callee to press 1 to acknowledge the alert, which Twilio reports to `acknowledge_url`.
+    pub fn call_twiml(&self, text: &str, acknowledge_url: &str) -> String {
+        let mut say = self.say(text);
+        if self.voice_loop > 1 {
+            say = say.loop_count(self.voice_loop as u32);
+        }
+        Twiml::new()
+            .say(say)
+            .gather(
+                Gather::new()
+                    .num_digits(1)
+                    .action(acknowledge_url)
+                    .timeout(ACKNOWLEDGE_TIMEOUT_SECONDS)
+                    .say(self.say("Press 1 to acknowledge this alert.")),
+            )
+            .to_xml()
+    }
+
+    /// Reads `text` with this QR code's voice and language.
+    fn say(&self, text: &str) -> Say {
+        let mut say = Say::new(text);
+        if let Some(voice) = &self.voice {
+            say = say.voice(voice.as_str());
+        }
+        if let Some(language) = &self.voice_language {
+            say = say.language(language.as_str());
+        }
+        say
+    }
+
+    /// Builds the rendering style stored on this QR code, without a logo.
+    pub fn style(&self) -> Result<QrStyle, anyhow::Error> {
+        Ok(QrStyle {
+            foreground: parse_hex_color(&self.foreground_color)?,
+            background: parse_hex_color(&self.background_color)?,
+            module_shape: ModuleShape::from_str(&self.module_shape)?,
+            quiet_zone: self.quiet_zone as u32,
+            logo: None,
+        })
+    }
+}
+
+/// Returns the QR code with the given `id` if it belongs to the account with the given `account_id`
+/// and is not in the trash.
+pub async fn get_owned_qr_code(
+    id: Uuid,
+    account_id: Uuid,
+    pool: &PgPool,
+) -> Result<Option<QrCode>, anyhow::Error> {
+    let qr_code = sqlx::query_as!(
+        QrCode,
+        "SELECT * FROM qr_code WHERE id=$1 AND account_id=$2 AND deleted_at IS NULL",
+        id,
+        account_id
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(qr_code)
 }
@@ -0,0 +1,216 @@
+use std::str::FromStr;
+
+use chrono::{Duration, NaiveDateTime, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::db::NotificationChannel;
+
+/// How a recipient of a QR code's notifications is reached.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RecipientChannel {
+    Call,
+    Sms,
+    Both,
+    Email,
+}
+
+impl RecipientChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Call => "call",
+            Self::Sms => "sms",
+            Self::Both => "both",
+            Self::Email => "email",
+        }
+    }
+
+    /// Notification channels the recipient's address is reached through.
+    pub fn channels(&self) -> &'static [NotificationChannel] {
+        match self {
+            Self::Call => &[NotificationChannel::Call],
+            Self::Sms => &[NotificationChannel::Sms],
+            Self::Both => &[NotificationChannel::Call, NotificationChannel::Sms],
+            Self::Email => &[NotificationChannel::Email],
+        }
+    }
+
+    /// Whether the recipient's address is a phone number rather than an email address.
+    pub fn is_phone(&self) -> bool {
+        !matches!(self, Self::Email)
+    }
+}
+
+impl FromStr for RecipientChannel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "call" => Ok(Self::Call),
+            "sms" => Ok(Self::Sms),
+            "both" => Ok(Self::Both),
+            "email" => Ok(Self::Email),
+            other => Err(anyhow::anyhow!(
+                "{} is not a supported recipient channel. Use `call`, `sms`, `both` or `email`.",
+                other
+            )),
+        }
+    }
+}
+
+/// Represents an additional person notified when a QR code is scanned.
+#[derive(sqlx::FromRow, Serialize, Debug, Clone)]
+pub struct QrCodeRecipient {
+    pub id: Uuid,
+    pub qr_code_id: Uuid,
+    pub name: Option<String>,
+    pub channel: String,
+    /// Phone number or email address, depending on `channel`.
+    pub address: String,
+    pub active: bool,
+    /// Local time from which the recipient should not be notified.
+    pub quiet_hours_start: Option<NaiveTime>,
+    /// Local time from which the recipient can be notified again.
+    pub quiet_hours_end: Option<NaiveTime>,
+    /// Offset of the recipient's local time from UTC, used to interpret quiet hours.
+    pub utc_offset_minutes: i32,
+    pub created_at: NaiveDateTime,
+    /// Whether the recipient is only notified of alerts that were not acknowledged in time.
+    pub escalation: bool,
+}
+
+impl QrCodeRecipient {
+    pub fn new(qr_code_id: Uuid, channel: RecipientChannel, address: String) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            qr_code_id,
+            name: None,
+            channel: channel.as_str().to_string(),
+            address,
+            active: true,
+            quiet_hours_start: None,
+            quiet_hours_end: None,
+            utc_offset_minutes: 0,
+            created_at: Utc::now().naive_utc(),
+            escalation: false,
+        }
+    }
+
+    /// Parses the stored `channel`.
+    pub fn recipient_channel(&self) -> Result<RecipientChannel, anyhow::Error> {
+        RecipientChannel::from_str(&self.channel)
+    }
+
+    /// Whether `now`, in UTC, falls within the recipient's quiet hours.
+    /// Quiet hours may wrap around midnight, e.g. 22:00 to 07:00.
+    pub fn is_quiet_at(&self, now: NaiveDateTime) -> bool {
+        let (start, end) = match (self.quiet_hours_start, self.quiet_hours_end) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return false,
+        };
+        let local = (now + Duration::minutes(self.utc_offset_minutes as i64)).time();
+        if start <= end {
+            start <= local && local < end
+        } else {
+            local >= start || local < end
+        }
+    }
+
+    pub async fn store(&self, pool: &PgPool) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            "INSERT INTO qr_code_recipient
+                (id, qr_code_id, name, channel, address, active, quiet_hours_start, quiet_hours_end, utc_offset_minutes, created_at, escalation)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+            self.id,
+            self.qr_code_id,
+            self.name,
+            self.channel,
+            self.address,
+            self.active,
+            self.quiet_hours_start,
+            self.quiet_hours_end,
+            self.utc_offset_minutes,
+            self.created_at,
+            self.escalation
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Returns every recipient of the QR code with the given `qr_code_id`, oldest first.
+pub async fn get_qr_code_recipients(
+    qr_code_id: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> Result<Vec<QrCodeRecipient>, anyhow::Error> {
+    let recipients = sqlx::query_as!(
+        QrCodeRecipient,
+        "SELECT * FROM qr_code_recipient WHERE qr_code_id = $1 ORDER BY created_at, id",
+        qr_code_id
+    )
+    .fetch_all(executor)
+    .await?;
+    Ok(recipients)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveTime};
+    use uuid::Uuid;
+
+    use super::{QrCodeRecipient, RecipientChannel};
+
+    fn recipient(start: &str, end: &str, utc_offset_minutes: i32) -> QrCodeRecipient {
+        let mut recipient = QrCodeRecipient::new(
+            Uuid::new_v4(),
+            RecipientChannel::Sms,
+            "+15555550123".to_string(),
+        );
+        recipient.quiet_hours_start = Some(NaiveTime::parse_from_str(start, "%H:%M").unwrap());
+        recipient.quiet_hours_end = Some(NaiveTime::parse_from_str(end, "%H:%M").unwrap());
+        recipient.utc_offset_minutes = utc_offset_minutes;
+        recipient
+    }
+
+    fn at(hour: u32, minute: u32) -> chrono::NaiveDateTime {
+        NaiveDate::from_ymd(2021, 12, 7).and_hms(hour, minute, 0)
+    }
+
+    #[test]
+    fn recipients_without_quiet_hours_are_never_quiet() {
+        let recipient = QrCodeRecipient::new(
+            Uuid::new_v4(),
+            RecipientChannel::Email,
+            "staff@hermodapp.com".to_string(),
+        );
+        assert!(!recipient.is_quiet_at(at(3, 0)));
+    }
+
+    #[test]
+    fn quiet_hours_within_a_day() {
+        let recipient = recipient("13:00", "15:00", 0);
+        assert!(!recipient.is_quiet_at(at(12, 59)));
+        assert!(recipient.is_quiet_at(at(13, 0)));
+        assert!(!recipient.is_quiet_at(at(15, 0)));
+    }
+
+    #[test]
+    fn quiet_hours_wrapping_around_midnight() {
+        let recipient = recipient("22:00", "07:00", 0);
+        assert!(recipient.is_quiet_at(at(23, 0)));
+        assert!(recipient.is_quiet_at(at(6, 59)));
+        assert!(!recipient.is_quiet_at(at(12, 0)));
+    }
+
+    #[test]
+    fn quiet_hours_are_in_local_time() {
+        // 22:00 to 07:00 in UTC-6 is 04:00 to 13:00 in UTC
+        let recipient = recipient("22:00", "07:00", -6 * 60);
+        assert!(recipient.is_quiet_at(at(5, 0)));
+        assert!(!recipient.is_quiet_at(at(23, 0)));
+    }
+}
@@ -1,13 +1,15 @@
 use std::fmt::Debug;
 
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
+use sqlx::PgExecutor;
 use uuid::Uuid;
 
 #[derive(sqlx::FromRow, Serialize, Deserialize, Clone)]
 pub struct Response {
     pub id: Uuid,
     pub form_id: Uuid,
+    /// Version of the form the response was submitted against.
+    pub form_version_id: Option<Uuid>,
 }
 
 impl Debug for Response {
@@ -15,6 +17,7 @@ impl Debug for Response {
         f.debug_struct("Response")
             .field("id", &self.id)
             .field("form_id", &self.form_id)
+            .field("form_version_id", &self.form_version_id)
             .finish()
     }
 }
@@ -22,6 +25,7 @@ impl Debug for Response {
 pub struct NewResponse {
     pub id: Uuid,
     pub form_id: Uuid,
+    pub form_version_id: Option<Uuid>,
 }
 
 impl NewResponse {
@@ -29,19 +33,35 @@ impl NewResponse {
         Self {
             id: Uuid::new_v4(),
             form_id: Uuid::new_v4(),
+            form_version_id: None,
         }
     }
 
-    pub async fn store(&self, pool: &PgPool) -> Result<(), anyhow::Error> {
+    pub async fn store(&self, executor: impl PgExecutor<'_>) -> Result<(), anyhow::Error> {
         sqlx::query!(
-            "INSERT INTO response (id, form_id)
-             VALUES ($1, $2)",
+            "INSERT INTO response (id, form_id, form_version_id)
+             VALUES ($1, $2, $3)",
             self.id,
             self.form_id,
+            self.form_version_id,
         )
-        .execute(pool)
+        .execute(executor)
         .await?;
 
         Ok(())
     }
 }
+
+/// Returns how many responses the form with the given `form_id` has received.
+pub async fn count_form_responses(
+    form_id: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> Result<i64, anyhow::Error> {
+    let count = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM response WHERE form_id = $1"#,
+        form_id
+    )
+    .fetch_one(executor)
+    .await?;
+    Ok(count)
+}
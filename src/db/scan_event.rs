@@ -0,0 +1,167 @@
+use chrono::{NaiveDateTime, Utc};
+use serde::Serialize;
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+/// Represents a single scan of a QR code.
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct ScanEvent {
+    pub id: Uuid,
+    pub qr_code_id: Uuid,
+    pub scanned_at: NaiveDateTime,
+    pub user_agent: Option<String>,
+    pub referrer: Option<String>,
+    /// Salted hash of the scanner's coarse network, see `services::analytics::client_hash`.
+    pub client_hash: Option<String>,
+    /// Whether this scan sent notifications to the QR code's owner.
+    pub notified: bool,
+    /// Whether notifications were withheld because of a cooldown window.
+    pub throttled: bool,
+}
+
+impl ScanEvent {
+    pub fn new(
+        qr_code_id: Uuid,
+        user_agent: Option<String>,
+        referrer: Option<String>,
+        client_hash: Option<String>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            qr_code_id,
+            scanned_at: Utc::now().naive_utc(),
+            user_agent,
+            referrer,
+            client_hash,
+            notified: false,
+            throttled: false,
+        }
+    }
+
+    pub async fn store(&self, executor: impl PgExecutor<'_>) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            "INSERT INTO scan_event
+                (id, qr_code_id, scanned_at, user_agent, referrer, client_hash, notified, throttled)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            self.id,
+            self.qr_code_id,
+            self.scanned_at,
+            self.user_agent,
+            self.referrer,
+            self.client_hash,
+            self.notified,
+            self.throttled
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Returns whether a notification was sent for the QR code with the given `qr_code_id` after `code_since`,
+/// or for a scan by the client with the given `client_hash` after `client_since`.
+pub async fn is_notification_throttled(
+    qr_code_id: Uuid,
+    client_hash: Option<&str>,
+    code_since: NaiveDateTime,
+    client_since: NaiveDateTime,
+    executor: impl PgExecutor<'_>,
+) -> Result<bool, anyhow::Error> {
+    let throttled = sqlx::query!(
+        r#"
+            SELECT EXISTS (
+                SELECT 1 FROM scan_event
+                WHERE qr_code_id = $1 AND notified
+                AND (scanned_at > $3 OR (client_hash = $2 AND scanned_at > $4))
+            ) AS "throttled!""#,
+        qr_code_id,
+        client_hash,
+        code_since,
+        client_since
+    )
+    .fetch_one(executor)
+    .await?
+    .throttled;
+    Ok(throttled)
+}
+
+/// Granularity of a scan time series.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScanInterval {
+    Hour,
+    Day,
+}
+
+impl ScanInterval {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Hour => "hour",
+            Self::Day => "day",
+        }
+    }
+}
+
+/// Scan counts over a QR code's whole lifetime.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ScanTotals {
+    pub total_scans: i64,
+    pub unique_scanners: i64,
+}
+
+/// Scan counts within one bucket of a time series.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ScanBucket {
+    pub bucket: NaiveDateTime,
+    pub scans: i64,
+    pub unique_scanners: i64,
+}
+
+/// Counts every scan of the QR code with the given `qr_code_id`.
+pub async fn get_scan_totals(qr_code_id: Uuid, pool: &PgPool) -> Result<ScanTotals, anyhow::Error> {
+    let totals = sqlx::query_as!(
+        ScanTotals,
+        r#"
+            SELECT COUNT(*) AS "total_scans!", COUNT(DISTINCT client_hash) AS "unique_scanners!"
+            FROM scan_event WHERE qr_code_id = $1"#,
+        qr_code_id
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(totals)
+}
+
+/// Counts scans of the QR code with the given `qr_code_id` per `interval` from `since` until `until`.
+/// Buckets without scans are included with zero counts.
+pub async fn get_scan_series(
+    qr_code_id: Uuid,
+    interval: ScanInterval,
+    since: NaiveDateTime,
+    until: NaiveDateTime,
+    pool: &PgPool,
+) -> Result<Vec<ScanBucket>, anyhow::Error> {
+    let series = sqlx::query_as!(
+        ScanBucket,
+        r#"
+            SELECT
+                bucket AS "bucket!",
+                COUNT(scan_event.id) AS "scans!",
+                COUNT(DISTINCT scan_event.client_hash) AS "unique_scanners!"
+            FROM generate_series(
+                date_trunc($2, $3::timestamp),
+                date_trunc($2, $4::timestamp),
+                ('1 ' || $2)::interval
+            ) AS bucket
+            LEFT JOIN scan_event
+                ON scan_event.qr_code_id = $1 AND date_trunc($2, scan_event.scanned_at) = bucket
+            GROUP BY bucket
+            ORDER BY bucket"#,
+        qr_code_id,
+        interval.as_str(),
+        since,
+        until
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(series)
+}
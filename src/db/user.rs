@@ -116,3 +116,17 @@ pub async fn get_user_by_id(user_id: String, db_pool: &PgPool) -> Result<User, a
         .context(format!("Failed to fetch user with user_id {}", user_id))?;
     Ok(user)
 }
+
+/// Returns the ids of the accounts whose email address is `email`, ignoring case.
+pub async fn get_account_ids_by_email(
+    email: &str,
+    db_pool: &PgPool,
+) -> Result<Vec<Uuid>, anyhow::Error> {
+    let accounts = sqlx::query!(
+        "SELECT id FROM account WHERE lower(email) = lower($1)",
+        email
+    )
+    .fetch_all(db_pool)
+    .await?;
+    Ok(accounts.into_iter().map(|account| account.id).collect())
+}
@@ -0,0 +1,126 @@
+use actix_web::{http::header, web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::field::Empty;
+
+use super::ApplicationResponse;
+use crate::{
+    db::{
+        delete_email_suppression, get_account_logo, get_email_suppressions, AccountLogo,
+        EmailSuppression,
+    },
+    handlers::{json_response, ApplicationError},
+    services::{jwt::JwtClient, qr_image, telemetry::spawn_blocking_with_tracing},
+};
+
+/// Content types accepted for uploaded logos.
+const LOGO_CONTENT_TYPES: [&str; 2] = ["image/png", "image/jpeg"];
+
+#[tracing::instrument(name = "handlers::account::upload_logo", skip(pool, body, request, jwt), fields(username=Empty, user_id=Empty))]
+/// post(/account/logo) stores the PNG or JPEG image in the request body as the logo shown on the user's QR codes
+pub async fn upload_logo(
+    pool: web::Data<PgPool>,
+    body: web::Bytes,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+) -> ApplicationResponse {
+    let content_type = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_lowercase())
+        .filter(|value| LOGO_CONTENT_TYPES.contains(&value.as_str()))
+        .ok_or_else(|| {
+            ApplicationError::BadRequestError(
+                "Logos must be uploaded as image/png or image/jpeg.".to_string(),
+            )
+        })?;
+    let user = jwt.user_or_403(request).await?;
+
+    // Make sure the logo can actually be drawn before accepting it
+    let data = body.to_vec();
+    let decoded = spawn_blocking_with_tracing(move || qr_image::decode_logo(&data).map(|_| data))
+        .await
+        .context("Failed to spawn blocking task.")?;
+    let data = decoded.map_err(|e| ApplicationError::BadRequestError(e.to_string()))?;
+
+    AccountLogo::new(user.id, content_type, data)
+        .store(pool.as_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[tracing::instrument(name = "handlers::account::get_logo", skip(pool, request, jwt), fields(username=Empty, user_id=Empty))]
+/// get(/account/logo) returns the logo uploaded by the user
+pub async fn get_logo(
+    pool: web::Data<PgPool>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+) -> ApplicationResponse {
+    let user = jwt.user_or_403(request).await?;
+    let logo = get_account_logo(user.id, pool.as_ref())
+        .await?
+        .ok_or_else(|| ApplicationError::NotFoundError("No logo uploaded.".to_string()))?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(logo.content_type)
+        .body(logo.data))
+}
+
+#[tracing::instrument(name = "handlers::account::delete_logo", skip(pool, request, jwt), fields(username=Empty, user_id=Empty))]
+/// get(/account/logo/delete) removes the logo uploaded by the user
+pub async fn delete_logo(
+    pool: web::Data<PgPool>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+) -> ApplicationResponse {
+    let user = jwt.user_or_403(request).await?;
+    sqlx::query!("DELETE FROM account_logo WHERE account_id = $1", user.id)
+        .execute(pool.as_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[derive(Serialize, Debug)]
+pub struct ListEmailSuppressionsResponse {
+    pub suppressions: Vec<EmailSuppression>,
+}
+
+#[tracing::instrument(name = "handlers::account::list_email_suppressions", skip(pool, request, jwt), fields(username=Empty, user_id=Empty))]
+/// get(/account/email_suppressions) lists the email addresses the user's account no longer sends emails to
+/// because emails to them hard-bounced
+pub async fn list_email_suppressions(
+    pool: web::Data<PgPool>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+) -> ApplicationResponse {
+    let user = jwt.user_or_403(request).await?;
+    let suppressions = get_email_suppressions(user.id, pool.as_ref()).await?;
+    json_response(&ListEmailSuppressionsResponse { suppressions })
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DeleteEmailSuppressionQuery {
+    pub email: String,
+}
+
+#[tracing::instrument(name = "handlers::account::clear_email_suppression", skip(pool, request, jwt), fields(username=Empty, user_id=Empty))]
+/// get(/account/email_suppressions/delete?email={EMAIL}) lets the user's account send emails to a suppressed address again
+pub async fn clear_email_suppression(
+    pool: web::Data<PgPool>,
+    query: web::Query<DeleteEmailSuppressionQuery>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+) -> ApplicationResponse {
+    let user = jwt.user_or_403(request).await?;
+    if delete_email_suppression(user.id, &query.email, pool.as_ref()).await? {
+        Ok(HttpResponse::Ok().finish())
+    } else {
+        Err(ApplicationError::NotFoundError(
+            "This address is not suppressed.".to_string(),
+        ))
+    }
+}
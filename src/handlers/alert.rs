@@ -0,0 +1,128 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::field::Empty;
+use uuid::Uuid;
+
+use super::ApplicationResponse;
+use crate::{
+    db::{
+        acknowledge_alert, get_alerts_for_qr_code, get_notification, get_owned_qr_code, Alert,
+        AlertStatus,
+    },
+    handlers::{json_response, ApplicationError},
+    services::{auth::AuthenticationError, jwt::JwtClient},
+};
+
+/// Maximum number of alerts returned for a QR code.
+const MAX_ALERTS: i64 = 100;
+
+pub(crate) const ACKNOWLEDGED_MESSAGE: &str = "Thank you, the alert has been acknowledged.";
+const ALREADY_ACKNOWLEDGED_MESSAGE: &str = "This alert has already been acknowledged.";
+const CANCELLED_MESSAGE: &str = "This alert was cancelled because its QR code was deleted.";
+
+/// Page the link in email notifications opens, `{token}` is replaced with the notification's id.
+/// Acknowledging takes a button press so that link prefetchers and scanners cannot do it.
+const CONFIRM_ACKNOWLEDGEMENT_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Acknowledge alert</title>
+</head>
+<body>
+<form method="post" action="/alert/acknowledge?token={token}">
+<button type="submit">Acknowledge alert</button>
+</form>
+</body>
+</html>
+"#;
+
+#[derive(Deserialize, Debug)]
+pub struct AcknowledgeAlertQuery {
+    /// Id of the notification the recipient is acknowledging the alert from.
+    pub token: Uuid,
+}
+
+/// Acknowledges the alert of the notification with the given id on behalf of its recipient,
+/// returning the status the alert had.
+pub(crate) async fn acknowledge_notification_alert(
+    notification_id: Uuid,
+    pool: &PgPool,
+) -> Result<AlertStatus, ApplicationError> {
+    let notification = get_notification(notification_id, pool).await?;
+    match notification.and_then(|n| n.alert_id.map(|alert_id| (alert_id, n.recipient))) {
+        Some((alert_id, recipient)) => Ok(acknowledge_alert(alert_id, &recipient, pool).await?),
+        None => Err(ApplicationError::NotFoundError(
+            "Alert not found".to_string(),
+        )),
+    }
+}
+
+/// Tells the recipient of a notification what acknowledging an alert with the given previous `status` did.
+pub(crate) fn acknowledgement_message(status: AlertStatus) -> &'static str {
+    match status {
+        AlertStatus::Open | AlertStatus::Escalated => ACKNOWLEDGED_MESSAGE,
+        AlertStatus::Acknowledged => ALREADY_ACKNOWLEDGED_MESSAGE,
+        AlertStatus::Cancelled => CANCELLED_MESSAGE,
+    }
+}
+
+#[tracing::instrument(name = "handlers::alert::confirm_acknowledgement", skip(pool))]
+/// get(/alert/acknowledge?token={ID}) shows the page the link sent in email notifications opens,
+/// which asks the recipient to confirm that they are acknowledging the alert
+pub async fn confirm_alert_acknowledgement(
+    pool: web::Data<PgPool>,
+    query: web::Query<AcknowledgeAlertQuery>,
+) -> ApplicationResponse {
+    get_notification(query.token, pool.as_ref())
+        .await?
+        .filter(|notification| notification.alert_id.is_some())
+        .ok_or_else(|| ApplicationError::NotFoundError("Alert not found".to_string()))?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(CONFIRM_ACKNOWLEDGEMENT_PAGE.replace("{token}", &query.token.to_string())))
+}
+
+#[tracing::instrument(name = "handlers::alert::acknowledge", skip(pool))]
+/// post(/alert/acknowledge?token={ID}) acknowledges an alert once its recipient confirms it on the
+/// page the link sent in its email notifications opens
+pub async fn acknowledge_alert_link(
+    pool: web::Data<PgPool>,
+    query: web::Query<AcknowledgeAlertQuery>,
+) -> ApplicationResponse {
+    let status = acknowledge_notification_alert(query.token, pool.as_ref()).await?;
+    Ok(HttpResponse::Ok().body(acknowledgement_message(status)))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct QrCodeAlertsQuery {
+    pub id: Uuid,
+    pub status: Option<AlertStatus>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct QrCodeAlertsResponse {
+    pub alerts: Vec<Alert>,
+}
+
+#[tracing::instrument(name = "handlers::alert::list", skip(pool, request, jwt), fields(username=Empty, user_id=Empty))]
+/// get(/qr_code/alerts?id={ID}&status={STATUS}) lists the latest alerts of one of the user's QR codes,
+/// optionally only those that are `open`, `escalated` or `acknowledged`
+pub async fn list_alerts(
+    pool: web::Data<PgPool>,
+    query: web::Query<QrCodeAlertsQuery>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+) -> ApplicationResponse {
+    let user = jwt.user_or_403(request).await?;
+    get_owned_qr_code(query.id, user.id, pool.as_ref())
+        .await?
+        .ok_or(ApplicationError::AuthError(
+            AuthenticationError::Unauthorized,
+        ))?;
+
+    let alerts = get_alerts_for_qr_code(query.id, query.status, MAX_ALERTS, pool.as_ref()).await?;
+    json_response(&QrCodeAlertsResponse { alerts })
+}
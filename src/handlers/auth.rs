@@ -4,8 +4,14 @@ use tracing::field::Empty;
 use uuid::Uuid;
 
 use crate::{
-    clients::postmark::PostmarkClient, db::NewForgottenPasswordRequest, db::NewUser, db::User,
-    services::auth::validate_request_with_basic_auth, services::jwt::JwtClient,
+    clients::email::{Email, TemplateRef},
+    clients::Providers,
+    db::is_email_suppressed,
+    db::NewForgottenPasswordRequest,
+    db::NewUser,
+    db::User,
+    services::auth::validate_request_with_basic_auth,
+    services::jwt::JwtClient,
 };
 
 use super::ApplicationResponse;
@@ -67,12 +73,12 @@ pub struct ForgottenPasswordQuery {
     pub username: String,
 }
 
-#[tracing::instrument(name = "handlers::auth::forgot_password", skip(postmark_client, pool), fields(username=Empty, user_id=Empty))]
+#[tracing::instrument(name = "handlers::auth::forgot_password", skip(providers, pool), fields(username=Empty, user_id=Empty))]
 /// get(/password/forgot) lets a user request a password reset link to their e-mail.
 pub async fn forgot_password(
     pool: web::Data<PgPool>,
     query: web::Query<ForgottenPasswordQuery>,
-    postmark_client: web::Data<PostmarkClient>,
+    providers: web::Data<Providers>,
 ) -> ApplicationResponse {
     let user = sqlx::query!(
         r#"SELECT * FROM account
@@ -86,15 +92,33 @@ pub async fn forgot_password(
         Some(e) => e,
         None => return Ok(HttpResponse::BadRequest().body("No email found.")),
     };
+    if is_email_suppressed(user.id, &addr, pool.as_ref()).await? {
+        return Ok(HttpResponse::BadRequest()
+            .body("Emails to this address bounced, please update your email address."));
+    }
     let newfpr = NewForgottenPasswordRequest::new(user.id);
     newfpr.store(pool.as_ref()).await?;
-    postmark_client
-        .send_email(
+    let reset_url = format!("http://hermodapp.com/password/reset?id={}", &newfpr.id);
+    let email = match &providers.email.templates().password_reset {
+        Some(alias) => Email::template(
             &addr,
-            &format!(
-                "http://hermodapp.com/password/reset?id={}\n\n{}",
-                &newfpr.id, &newfpr.id
+            TemplateRef::Alias(alias.clone()),
+            serde_json::json!({ "username": user.username, "reset_url": reset_url }),
+        ),
+        None => Email::text(
+            &addr,
+            format!(
+                "A password reset was requested for your Hermod account {}. Reset your password here:\n\n{}",
+                user.username, reset_url
             ),
+        ),
+    };
+    providers
+        .email
+        .send(
+            &email
+                .subject("Reset your Hermod password")
+                .tag("password-reset"),
         )
         .await?;
 
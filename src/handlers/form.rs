@@ -1,16 +1,40 @@
 use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::{NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
+use sqlx::{PgExecutor, PgPool};
 use std::collections::HashMap;
 use tracing::field::Empty;
+use tracing::Instrument;
 use uuid::Uuid;
 
 use super::ApplicationResponse;
 use crate::{
-    clients::postmark::PostmarkClient, db::NewForm, db::NewResponse, handlers::ApplicationError,
+    clients::email::Email,
+    clients::Providers,
+    db::{
+        count_form_responses, delete_form_rules, get_form_by_id, get_form_fields,
+        get_form_notification_settings, get_form_rules, get_form_sections, get_form_versions,
+        get_latest_form_version, get_owned_form, is_email_suppressed, lock_form,
+        record_form_version, AttachmentFormat, Condition, FieldConfig, FieldType, Form,
+        FormNotificationSettings, FormRule, FormSection, FormStatus, FormVersion, NewField,
+        NewForm, NewResponse, RuleAction,
+    },
+    handlers::{json_response, ApplicationError},
+    services::auth::AuthenticationError,
+    services::form_document::{FormDocument, FORM_DOCUMENT_SCHEMA},
+    services::form_layout::{arrange, paginate, Placement},
+    services::form_lifecycle::Lifecycle,
+    services::form_logic::{hidden_fields, validate_rule, RuleSchema},
+    services::form_summary::{Answer, ResponseSummary},
+    services::form_template::FormDefinition,
+    services::form_validation::{validate_config, validate_response, FieldSchema},
     services::jwt::JwtClient,
+    startup::ApplicationBaseUrl,
 };
 
+/// Most addresses a form's responses can be emailed to.
+const MAX_FORM_NOTIFICATION_RECIPIENTS: usize = 10;
+
 #[derive(Debug, Deserialize)]
 pub struct ViewFormQuery {
     pub id: Option<Uuid>,
@@ -39,13 +63,18 @@ pub struct IndividualResponse {
 #[derive(Serialize)]
 pub struct ResponseGroup {
     pub response_id: Uuid,
+    /// Version of the form the response was submitted against, if it is known.
+    pub version: Option<i32>,
     pub replies: Vec<IndividualResponse>,
 }
 
 #[derive(Serialize)]
 pub struct ViewFormResponse {
     pub title: String,
+    /// Current caption of every field the form has had, including archived ones.
     pub questions: HashMap<Uuid, String>,
+    /// Fields of the form as they were in each of its versions, oldest first.
+    pub versions: Vec<FormVersion>,
     pub responses: Vec<ResponseGroup>,
 }
 
@@ -57,17 +86,15 @@ pub async fn view_form_responses(
     form_id: Uuid,
     jwt: web::Data<JwtClient>,
 ) -> ApplicationResponse {
-    let _current_user = jwt.user_or_403(request).await?;
+    let user = jwt.user_or_403(request).await?;
 
-    let form = sqlx::query!(
-        r#"SELECT * FROM form
-        WHERE id = $1"#,
-        form_id
-    )
-    .fetch_one(pool.as_ref())
-    .await?;
+    let form = get_owned_form(form_id, user.id, pool.as_ref())
+        .await?
+        .ok_or_else(|| {
+            ApplicationError::NotFoundError(format!("No form found with id {}.", form_id))
+        })?;
 
-    let title = form.title.unwrap();
+    let title = form.title;
 
     let fields = sqlx::query!(
         r#"SELECT * FROM form_input
@@ -83,6 +110,8 @@ pub async fn view_form_responses(
         questions.insert(field.id, field.caption.as_ref().unwrap().clone());
     }
 
+    let versions = get_form_versions(form_id, pool.as_ref()).await?;
+
     let responses_data = sqlx::query!(
         r#"SELECT * FROM response 
         WHERE form_id = $1"#,
@@ -113,6 +142,10 @@ pub async fn view_form_responses(
 
         responses.push(ResponseGroup {
             response_id: response.id,
+            version: response
+                .form_version_id
+                .and_then(|id| versions.iter().find(|version| version.id == id))
+                .map(|version| version.version),
             replies,
         });
     }
@@ -120,6 +153,7 @@ pub async fn view_form_responses(
     let view_form_responses_data = ViewFormResponse {
         title,
         questions,
+        versions,
         responses,
     };
 
@@ -130,6 +164,7 @@ pub async fn view_form_responses(
 pub struct ListedFormResponse {
     pub title: String,
     pub form_id: Uuid,
+    pub status: String,
 }
 
 #[derive(Serialize)]
@@ -149,7 +184,7 @@ pub async fn list_forms(
     let forms = sqlx::query!(
         r#"
             SELECT * FROM form
-            WHERE account_id=$1"#,
+            WHERE account_id=$1 AND deleted_at IS NULL"#,
         current_user.id,
     )
     .fetch_all(pool.as_ref())
@@ -161,6 +196,7 @@ pub async fn list_forms(
             .map(|f| ListedFormResponse {
                 title: String::from(f.title.as_ref().unwrap()),
                 form_id: f.id,
+                status: f.status.clone(),
             })
             .collect(),
     };
@@ -168,21 +204,6 @@ pub async fn list_forms(
     Ok(HttpResponse::Ok().body(serde_json::to_string(&form_list_response_data).unwrap()))
 }
 
-/*
-#[derive(serde::Serialize, serde::Deserialize)]
-pub enum FieldType {
-    Checkbox,
-    Image,
-    Radio,
-    Text,
-    Email,
-    Phone,
-    NumField,
-    NumSlider,
-    DateTime,
-}
- */
-
 #[derive(Deserialize)]
 pub struct FormGetRequest {
     pub id: Uuid,
@@ -192,42 +213,122 @@ pub struct FormGetRequest {
 pub struct FieldGetResponse {
     pub field_id: Uuid,
     pub caption: String,
-    pub r#type: String,
+    pub r#type: FieldType,
+    pub config: FieldConfig,
+    pub position: i32,
+    pub section_id: Option<Uuid>,
+    pub page: i32,
+}
+
+#[derive(Serialize)]
+pub struct SectionGetResponse {
+    pub section_id: Uuid,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub page_break: bool,
+    pub position: i32,
+    pub page: i32,
 }
 
 #[derive(Serialize)]
 pub struct FormGetResponse {
     pub title: String,
+    pub status: FormStatus,
+    pub opens_at: Option<NaiveDateTime>,
+    pub closes_at: Option<NaiveDateTime>,
+    pub max_responses: Option<i32>,
     pub fields: Vec<FieldGetResponse>,
+    pub sections: Vec<SectionGetResponse>,
+    pub page_count: i32,
+    /// Rules the client applies to show, hide and skip fields as the form is filled in.
+    pub rules: Vec<FormRule>,
 }
 
-#[tracing::instrument(name = "handlers::form::get", skip(query, pool))]
-/// get(form/submit) and get(form/edit) runs an SQL query on a provided form id and returns a JSON object of the fields
+/// Returns why the form does not accept responses right now, or `None` if it does.
+async fn closed_reason(
+    form: &Form,
+    executor: impl PgExecutor<'_>,
+) -> Result<Option<String>, anyhow::Error> {
+    let responses = match form.max_responses {
+        Some(_) => count_form_responses(form.id, executor).await?,
+        None => 0,
+    };
+    Ok(form
+        .lifecycle()?
+        .closed_reason(Utc::now().naive_utc(), responses))
+}
+
+#[tracing::instrument(name = "handlers::form::get", skip(query, pool, request, jwt), fields(username=Empty, user_id=Empty))]
+/// get(form/submit) and get(form/edit) runs an SQL query on a provided form id and returns a JSON object of the fields.
+/// Forms that do not accept responses are only returned to their owner.
 pub async fn get_form(
     query: web::Query<FormGetRequest>,
     pool: web::Data<PgPool>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
 ) -> ApplicationResponse {
     // Validate that such a requested form exists
-    if let Some(form) = sqlx::query!("SELECT * FROM form WHERE id=$1", &query.id)
-        .fetch_optional(pool.as_ref())
-        .await?
-    {
-        // Retrieve fields associated with form
-        let fields = sqlx::query!("SELECT * FROM form_input WHERE form_id=$1", form.id)
-            .fetch_all(pool.as_ref())
-            .await?;
+    if let Some(form) = get_form_by_id(query.id, pool.as_ref()).await? {
+        if let Some(reason) = closed_reason(&form, pool.as_ref()).await? {
+            let is_owner = jwt
+                .user_or_403(request)
+                .await
+                .map_or(false, |user| user.id == form.account_id);
+            if !is_owner {
+                return Err(ApplicationError::ForbiddenError(reason));
+            }
+        }
+
+        // Retrieve fields and sections associated with form, in order
+        let fields = get_form_fields(form.id, pool.as_ref()).await?;
+        let sections = get_form_sections(form.id, pool.as_ref()).await?;
+        let rules = get_form_rules(form.id, pool.as_ref()).await?;
+        let page_breaks: Vec<bool> = sections.iter().map(|section| section.page_break).collect();
+        let pages = paginate(
+            fields.iter().any(|field| field.section_id.is_none()),
+            &page_breaks,
+        );
+        let page_of = |section_id: Option<Uuid>| {
+            section_id
+                .and_then(|id| sections.iter().position(|section| section.id == id))
+                .map_or(1, |index| pages[index])
+        };
 
         // Gather field types into a struct
+        let mut field_responses = Vec::with_capacity(fields.len());
+        for field in fields.iter() {
+            field_responses.push(FieldGetResponse {
+                field_id: field.id,
+                caption: field.caption.clone(),
+                r#type: field.field_type()?,
+                config: field.config()?,
+                position: field.position,
+                section_id: field.section_id,
+                page: page_of(field.section_id),
+            });
+        }
+        let lifecycle = form.lifecycle()?;
         let form_response_data = FormGetResponse {
-            title: form.title.unwrap(),
-            fields: fields
+            title: form.title,
+            status: lifecycle.status,
+            opens_at: lifecycle.opens_at,
+            closes_at: lifecycle.closes_at,
+            max_responses: lifecycle.max_responses,
+            fields: field_responses,
+            sections: sections
                 .iter()
-                .map(|f| FieldGetResponse {
-                    field_id: f.id,
-                    caption: String::from(f.caption.as_ref().unwrap()),
-                    r#type: String::from(&f.r#type),
+                .zip(pages.iter())
+                .map(|(section, &page)| SectionGetResponse {
+                    section_id: section.id,
+                    title: section.title.clone(),
+                    description: section.description.clone(),
+                    page_break: section.page_break,
+                    position: section.position,
+                    page,
                 })
                 .collect(),
+            page_count: pages.last().copied().unwrap_or(1),
+            rules,
         };
 
         Ok(HttpResponse::Ok().body(serde_json::to_string(&form_response_data).unwrap()))
@@ -242,13 +343,72 @@ pub async fn get_form(
 #[derive(Deserialize)]
 pub struct FieldCreationRequest {
     pub caption: String,
-    pub r#type: String,
+    pub r#type: FieldType,
+    #[serde(default)]
+    pub config: FieldConfig,
+}
+
+#[derive(Deserialize)]
+pub struct SectionCreationRequest {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub page_break: bool,
+    #[serde(default)]
+    pub fields: Vec<FieldCreationRequest>,
 }
 
+/// A new form, whose `fields` come before its `sections` and are not grouped in any of them.
 #[derive(Deserialize)]
 pub struct FormCreationRequest {
     pub title: String,
+    /// Forms are published as soon as they are created unless created as drafts.
+    #[serde(default)]
+    pub status: FormStatus,
+    #[serde(default)]
     pub fields: Vec<FieldCreationRequest>,
+    #[serde(default)]
+    pub sections: Vec<SectionCreationRequest>,
+}
+
+impl FormCreationRequest {
+    /// Every field of the new form, whether in a section or not.
+    fn all_fields(&self) -> impl Iterator<Item = &FieldCreationRequest> {
+        self.fields.iter().chain(
+            self.sections
+                .iter()
+                .flat_map(|section| section.fields.iter()),
+        )
+    }
+}
+
+/// Rejects a field whose settings do not suit its type, naming it by its caption.
+fn validate_field(
+    caption: &str,
+    field_type: FieldType,
+    config: &FieldConfig,
+) -> Result<(), ApplicationError> {
+    validate_config(field_type, config)
+        .map_err(|message| ApplicationError::BadRequestError(format!("{}: {}", caption, message)))
+}
+
+/// Builds a new field of the form with the given `form_id`.
+fn new_field(
+    form_id: Uuid,
+    caption: &str,
+    field_type: FieldType,
+    config: &FieldConfig,
+    position: i32,
+    section_id: Option<Uuid>,
+) -> Result<NewField, ApplicationError> {
+    let mut new_field = NewField::default();
+    new_field.form_id = form_id;
+    new_field.caption = caption.to_string();
+    new_field.field_type = field_type.as_str().to_string();
+    new_field.config = serde_json::to_value(config)?;
+    new_field.position = position;
+    new_field.section_id = section_id;
+    Ok(new_field)
 }
 
 #[tracing::instrument(name = "handlers::form::store", skip(json, pool, request, jwt), fields(username=Empty, user_id=Empty))]
@@ -260,29 +420,57 @@ pub async fn store_form(
     jwt: web::Data<JwtClient>,
 ) -> ApplicationResponse {
     let current_user = jwt.user_or_403(request).await?;
+    for field in json.all_fields() {
+        validate_field(&field.caption, field.r#type, &field.config)?;
+    }
 
     // Store form first to avoid foreign key constrain
     let mut new_form = NewForm::default();
     new_form.title = json.title.clone();
     new_form.account_id = current_user.id;
+    new_form.status = json.status.as_str().to_string();
     new_form.store(pool.as_ref()).await?;
 
     // Create a transaction to store each form input
     let mut tx = pool.begin().await?;
 
-    // Queue a SQL query for each form input
+    // Queue a SQL query for each form input, numbering them in the order they were given
+    let mut position = 0;
     for field in json.fields.iter() {
-        sqlx::query!(
-            r#"INSERT INTO form_input (id, form_id, type, caption) 
-                VALUES ($1, $2, $3, $4)"#,
-            Uuid::new_v4(),
+        new_field(
             new_form.id,
-            field.r#type.clone(),
-            field.caption.clone()
-        )
-        .execute(&mut tx)
+            &field.caption,
+            field.r#type,
+            &field.config,
+            position,
+            None,
+        )?
+        .store(&mut tx)
         .await?;
+        position += 1;
     }
+    for (index, section) in json.sections.iter().enumerate() {
+        let mut new_section = FormSection::new(new_form.id, index as i32);
+        new_section.title = section.title.clone();
+        new_section.description = section.description.clone();
+        new_section.page_break = section.page_break;
+        new_section.store(&mut tx).await?;
+
+        for field in section.fields.iter() {
+            new_field(
+                new_form.id,
+                &field.caption,
+                field.r#type,
+                &field.config,
+                position,
+                Some(new_section.id),
+            )?
+            .store(&mut tx)
+            .await?;
+            position += 1;
+        }
+    }
+    record_form_version(new_form.id, &mut tx).await?;
 
     // Commit the transaction
     tx.commit().await?;
@@ -306,23 +494,89 @@ pub struct FormQuery {
     pub id: Uuid,
 }
 
-#[tracing::instrument(name = "handlers::form::store_response", skip(query, json, pool), fields(username=Empty, user_id=Empty))]
-/// post(form/submit) runs an SQL query to store a new response and all its associated fields
+#[tracing::instrument(name = "handlers::form::store_response", skip(query, json, pool, providers), fields(username=Empty, user_id=Empty))]
+/// post(form/submit) runs an SQL query to store a new response and all its associated fields,
+/// then emails the response in the background to the recipients configured in the form's notification settings
 pub async fn store_form_response(
     json: web::Json<ResponseCreationRequest>,
     pool: web::Data<PgPool>,
     query: web::Query<FormQuery>,
+    providers: web::Data<Providers>,
 ) -> ApplicationResponse {
-    // Store response first to avoid foreign key constrain
-    let mut new_response = NewResponse::default();
-    new_response.form_id = query.id;
-    new_response.store(pool.as_ref()).await?;
+    let form = get_form_by_id(query.id, pool.as_ref())
+        .await?
+        .ok_or_else(|| {
+            ApplicationError::NotFoundError(format!("No form found with id {}.", query.id))
+        })?;
+    if let Some(reason) = closed_reason(&form, pool.as_ref()).await? {
+        return Err(ApplicationError::ForbiddenError(reason));
+    }
 
-    // Create a transaction to store each form input
+    // Fields the form's rules hide are not required, and answers to them are dropped
+    let form_fields = get_form_fields(form.id, pool.as_ref()).await?;
+    let placements: Vec<Placement> = form_fields
+        .iter()
+        .map(|field| Placement::new(field.id, field.section_id))
+        .collect();
+    let sections: Vec<Uuid> = get_form_sections(form.id, pool.as_ref())
+        .await?
+        .iter()
+        .map(|section| section.id)
+        .collect();
+    let rules = rule_schemas(&get_form_rules(form.id, pool.as_ref()).await?)?;
+    let mut first_answers = HashMap::new();
+    for response in json.responses.iter() {
+        first_answers
+            .entry(response.field_id)
+            .or_insert(response.content.as_str());
+    }
+    let hidden = hidden_fields(&placements, &sections, &rules, &first_answers);
+    let replies: Vec<&FeedbackCreationRequest> = json
+        .responses
+        .iter()
+        .filter(|response| !hidden.contains(&response.field_id))
+        .collect();
+
+    let mut fields = Vec::new();
+    for field in form_fields
+        .iter()
+        .filter(|field| !hidden.contains(&field.id))
+    {
+        fields.push(FieldSchema {
+            id: field.id,
+            field_type: field.field_type()?,
+            config: field.config()?,
+        });
+    }
+    let answers: Vec<(Uuid, &str)> = replies
+        .iter()
+        .map(|response| (response.field_id, response.content.as_str()))
+        .collect();
+    let errors = validate_response(&fields, &answers);
+    if !errors.is_empty() {
+        return Err(ApplicationError::ValidationError(errors));
+    }
+
+    // Lock the form until the response is stored, so that concurrent submissions are counted
+    // one at a time and cannot take it past its response cap
     let mut tx = pool.begin().await?;
+    let form = lock_form(form.id, &mut tx).await?.ok_or_else(|| {
+        ApplicationError::NotFoundError(format!("No form found with id {}.", query.id))
+    })?;
+    if let Some(reason) = closed_reason(&form, &mut tx).await? {
+        return Err(ApplicationError::ForbiddenError(reason));
+    }
+
+    // Store response first to avoid foreign key constrain
+    let mut new_response = NewResponse::default();
+    new_response.form_id = form.id;
+    new_response.form_version_id = get_latest_form_version(form.id, &mut tx)
+        .await?
+        .map(|version| version.id);
+    new_response.store(&mut tx).await?;
 
     // Queue a SQL query for each form input
-    for response in json.responses.iter() {
+    for response in replies.iter() {
         sqlx::query!(
             r#"INSERT INTO feedback (id, form_input_id, response_id, content) 
                 VALUES ($1, $2, $3, $4)"#,
@@ -338,25 +592,389 @@ pub async fn store_form_response(
     // Commit the transaction
     tx.commit().await?;
 
+    // The response is stored, so it is emailed in the background rather than making the
+    // respondent wait for every recipient, and failing to email it cannot fail the submission
+    let replies: Vec<(Uuid, String)> = replies
+        .iter()
+        .map(|reply| (reply.field_id, reply.content.clone()))
+        .collect();
+    let response_id = new_response.id;
+    let pool = pool.get_ref().clone();
+    tokio::spawn(
+        async move {
+            if let Err(e) =
+                email_form_response(&form, response_id, &replies, &pool, providers.as_ref()).await
+            {
+                tracing::error!("Failed to email form response {}: {:?}", response_id, e);
+            }
+        }
+        .instrument(tracing::Span::current()),
+    );
+
     Ok(HttpResponse::Ok().body(format!("Stored new response with id {}.", new_response.id)))
 }
 
+/// Emails a new response to the recipients in its form's notification settings, with the
+/// response attached as a file if the settings ask for it.
+async fn email_form_response(
+    form: &Form,
+    response_id: Uuid,
+    replies: &[(Uuid, String)],
+    pool: &PgPool,
+    providers: &Providers,
+) -> Result<(), anyhow::Error> {
+    let settings = get_form_notification_settings(form.id, pool).await?;
+    if settings.recipients.is_empty() {
+        return Ok(());
+    }
+
+    let captions: HashMap<Uuid, String> = sqlx::query!(
+        "SELECT id, caption FROM form_input WHERE form_id = $1",
+        form.id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|field| (field.id, field.caption.unwrap_or_default()))
+    .collect();
+    let summary = ResponseSummary {
+        form_title: form.title.clone(),
+        response_id,
+        submitted_at: chrono::Utc::now().naive_utc(),
+        answers: replies
+            .iter()
+            .filter_map(|(field_id, content)| {
+                captions.get(field_id).map(|caption| Answer {
+                    question: caption.clone(),
+                    answer: content.clone(),
+                })
+            })
+            .collect(),
+    };
+    let attachment = if settings.include_attachment {
+        Some(summary.attachment(settings.attachment_format()?)?)
+    } else {
+        None
+    };
+
+    for recipient in settings.recipients.iter() {
+        if is_email_suppressed(form.account_id, recipient, pool).await? {
+            tracing::info!(
+                "Not emailing form response to suppressed address {}",
+                recipient
+            );
+            continue;
+        }
+        let mut email = Email::html(recipient, summary.text(), summary.html())
+            .subject(summary.subject())
+            .tag("form-response");
+        if let Some(attachment) = &attachment {
+            email = email.attachment(attachment.clone());
+        }
+        if let Err(e) = providers.email.send(&email).await {
+            tracing::error!("Failed to email form response to {}: {:?}", recipient, e);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Deserialize, Debug)]
+pub struct FormNotificationSettingsRequest {
+    #[serde(default)]
+    pub recipients: Vec<String>,
+    #[serde(default)]
+    pub include_attachment: bool,
+    #[serde(default)]
+    pub attachment_format: AttachmentFormat,
+}
+
+impl FormNotificationSettingsRequest {
+    fn validate(&self) -> Result<(), ApplicationError> {
+        if self.recipients.len() > MAX_FORM_NOTIFICATION_RECIPIENTS {
+            return Err(ApplicationError::BadRequestError(format!(
+                "Form responses can be emailed to at most {} recipients.",
+                MAX_FORM_NOTIFICATION_RECIPIENTS
+            )));
+        }
+        if let Some(invalid) = self
+            .recipients
+            .iter()
+            .find(|address| !address.contains('@') || address.contains(char::is_whitespace))
+        {
+            return Err(ApplicationError::BadRequestError(format!(
+                "{} is not a valid email address.",
+                invalid
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[tracing::instrument(name = "handlers::form::get_notifications", skip(query, pool, request, jwt), fields(username=Empty, user_id=Empty))]
+/// get(/form/notifications?id={ID}) returns who is emailed when one of the user's forms receives a response
+pub async fn get_form_notifications(
+    query: web::Query<FormQuery>,
+    pool: web::Data<PgPool>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+) -> ApplicationResponse {
+    let user = jwt.user_or_403(request).await?;
+    get_owned_form(query.id, user.id, pool.as_ref())
+        .await?
+        .ok_or(ApplicationError::AuthError(
+            AuthenticationError::Unauthorized,
+        ))?;
+
+    json_response(&get_form_notification_settings(query.id, pool.as_ref()).await?)
+}
+
+#[tracing::instrument(name = "handlers::form::edit_notifications", skip(query, json, pool, request, jwt), fields(username=Empty, user_id=Empty))]
+/// post(/form/notifications?id={ID}) replaces who is emailed when one of the user's forms receives a response
+pub async fn edit_form_notifications(
+    query: web::Query<FormQuery>,
+    json: web::Json<FormNotificationSettingsRequest>,
+    pool: web::Data<PgPool>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+) -> ApplicationResponse {
+    let user = jwt.user_or_403(request).await?;
+    json.validate()?;
+    get_owned_form(query.id, user.id, pool.as_ref())
+        .await?
+        .ok_or(ApplicationError::AuthError(
+            AuthenticationError::Unauthorized,
+        ))?;
+
+    let mut settings = FormNotificationSettings::new(query.id);
+    settings.recipients = json.recipients.clone();
+    settings.include_attachment = json.include_attachment;
+    settings.attachment_format = json.attachment_format.as_str().to_string();
+    settings.store(pool.as_ref()).await?;
+
+    json_response(&settings)
+}
+
+#[derive(Serialize)]
+pub struct FormStatusResponse {
+    #[serde(flatten)]
+    pub lifecycle: Lifecycle,
+    pub responses: i64,
+    /// Why the form does not accept responses right now, if it does not.
+    pub closed_reason: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct FormStatusRequest {
+    pub status: FormStatus,
+    pub opens_at: Option<NaiveDateTime>,
+    pub closes_at: Option<NaiveDateTime>,
+    pub max_responses: Option<i32>,
+}
+
+/// Describes whether the form accepts responses, and why not if it does not.
+async fn form_status_response(form: &Form, pool: &PgPool) -> ApplicationResponse {
+    let lifecycle = form.lifecycle()?;
+    let responses = count_form_responses(form.id, pool).await?;
+    json_response(&FormStatusResponse {
+        lifecycle,
+        responses,
+        closed_reason: lifecycle.closed_reason(Utc::now().naive_utc(), responses),
+    })
+}
+
+#[tracing::instrument(name = "handlers::form::get_status", skip(query, pool, request, jwt), fields(username=Empty, user_id=Empty))]
+/// get(/form/status?id={ID}) returns the status, open/close window and response cap of one of the user's forms
+pub async fn get_form_status(
+    query: web::Query<FormQuery>,
+    pool: web::Data<PgPool>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+) -> ApplicationResponse {
+    let user = jwt.user_or_403(request).await?;
+    let form = get_owned_form(query.id, user.id, pool.as_ref())
+        .await?
+        .ok_or(ApplicationError::AuthError(
+            AuthenticationError::Unauthorized,
+        ))?;
+
+    form_status_response(&form, pool.as_ref()).await
+}
+
+#[tracing::instrument(name = "handlers::form::edit_status", skip(query, json, pool, request, jwt), fields(username=Empty, user_id=Empty))]
+/// post(/form/status?id={ID}) replaces the status, open/close window and response cap of one of the user's forms
+pub async fn edit_form_status(
+    query: web::Query<FormQuery>,
+    json: web::Json<FormStatusRequest>,
+    pool: web::Data<PgPool>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+) -> ApplicationResponse {
+    let user = jwt.user_or_403(request).await?;
+    let mut form = get_owned_form(query.id, user.id, pool.as_ref())
+        .await?
+        .ok_or(ApplicationError::AuthError(
+            AuthenticationError::Unauthorized,
+        ))?;
+
+    let lifecycle = Lifecycle {
+        status: json.status,
+        opens_at: json.opens_at,
+        closes_at: json.closes_at,
+        max_responses: json.max_responses,
+    };
+    lifecycle
+        .validate()
+        .map_err(ApplicationError::BadRequestError)?;
+
+    form.status = lifecycle.status.as_str().to_string();
+    form.opens_at = lifecycle.opens_at;
+    form.closes_at = lifecycle.closes_at;
+    form.max_responses = lifecycle.max_responses;
+    form.update_lifecycle(pool.as_ref()).await?;
+
+    form_status_response(&form, pool.as_ref()).await
+}
+
+/// Converts a form's stored rules into the form they are checked and applied in.
+fn rule_schemas(rules: &[FormRule]) -> Result<Vec<RuleSchema>, anyhow::Error> {
+    rules
+        .iter()
+        .map(|rule| {
+            Ok(RuleSchema {
+                action: rule.rule_action()?,
+                target_field_id: rule.target_field_id,
+                target_section_id: rule.target_section_id,
+                match_any: rule.match_any,
+                conditions: rule.conditions()?,
+            })
+        })
+        .collect()
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RuleRequest {
+    pub action: RuleAction,
+    /// Field of a `show` or `hide` rule.
+    pub target_field_id: Option<Uuid>,
+    /// Section a `skip` rule skips ahead to.
+    pub target_section_id: Option<Uuid>,
+    #[serde(default)]
+    pub match_any: bool,
+    pub conditions: Vec<Condition>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct FormRulesRequest {
+    pub rules: Vec<RuleRequest>,
+}
+
+#[tracing::instrument(name = "handlers::form::edit_rules", skip(query, json, pool, request, jwt), fields(username=Empty, user_id=Empty))]
+/// post(/form/rules?id={ID}) replaces the rules that show, hide and skip the fields of one of the user's forms
+pub async fn edit_form_rules(
+    query: web::Query<FormQuery>,
+    json: web::Json<FormRulesRequest>,
+    pool: web::Data<PgPool>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+) -> ApplicationResponse {
+    let user = jwt.user_or_403(request).await?;
+    get_owned_form(query.id, user.id, pool.as_ref())
+        .await?
+        .ok_or(ApplicationError::AuthError(
+            AuthenticationError::Unauthorized,
+        ))?;
+
+    let placements: Vec<Placement> = get_form_fields(query.id, pool.as_ref())
+        .await?
+        .iter()
+        .map(|field| Placement::new(field.id, field.section_id))
+        .collect();
+    let sections: Vec<Uuid> = get_form_sections(query.id, pool.as_ref())
+        .await?
+        .iter()
+        .map(|section| section.id)
+        .collect();
+
+    let mut rules = Vec::with_capacity(json.rules.len());
+    for (index, request) in json.rules.iter().enumerate() {
+        let schema = RuleSchema {
+            action: request.action,
+            target_field_id: request.target_field_id,
+            target_section_id: request.target_section_id,
+            match_any: request.match_any,
+            conditions: request.conditions.clone(),
+        };
+        validate_rule(&placements, &sections, &schema).map_err(|message| {
+            ApplicationError::BadRequestError(format!("Rule {}: {}", index + 1, message))
+        })?;
+
+        let mut rule = FormRule::new(query.id, index as i32, request.action);
+        rule.target_field_id = request.target_field_id;
+        rule.target_section_id = request.target_section_id;
+        rule.match_any = request.match_any;
+        rule.conditions = serde_json::to_value(&request.conditions)?;
+        rules.push(rule);
+    }
+
+    let mut tx = pool.begin().await?;
+    delete_form_rules(query.id, &mut tx).await?;
+    for rule in rules.iter() {
+        rule.store(&mut tx).await?;
+    }
+    tx.commit().await?;
+
+    json_response(&rules)
+}
+
 #[derive(Debug, Deserialize)]
 pub struct FieldEditRequest {
     field_id: Option<Uuid>,
     caption: String,
-    r#type: String,
+    r#type: FieldType,
+    #[serde(default)]
+    config: FieldConfig,
+    delete: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SectionEditRequest {
+    section_id: Option<Uuid>,
+    title: Option<String>,
+    description: Option<String>,
+    #[serde(default)]
+    page_break: bool,
+    #[serde(default)]
     delete: bool,
+    #[serde(default)]
+    fields: Vec<FieldEditRequest>,
 }
 
+/// Changes to a form. Fields are placed in the section they are listed under, or outside any
+/// section if listed in `fields`, in the order they are listed. Sections are reordered the same
+/// way. Fields and sections that are not listed keep their place after the listed ones.
 #[derive(Debug, Deserialize)]
 pub struct FormEditRequest {
     pub title: String,
+    #[serde(default)]
     pub fields: Vec<FieldEditRequest>,
+    #[serde(default)]
+    pub sections: Vec<SectionEditRequest>,
+}
+
+impl FormEditRequest {
+    /// Every field edit, whether listed in a section or not.
+    fn all_fields(&self) -> impl Iterator<Item = &FieldEditRequest> {
+        self.fields.iter().chain(
+            self.sections
+                .iter()
+                .flat_map(|section| section.fields.iter()),
+        )
+    }
 }
 
 #[tracing::instrument(name = "handlers::form::edit", skip(query, json, pool, request, jwt), fields(username=Empty, user_id=Empty))]
-/// post(form/edit) runs an SQL query to edit a form
+/// post(form/edit) runs an SQL query to edit a form, its sections and the order of its fields,
+/// recording the result as a new version of the form
 pub async fn edit_form(
     json: web::Json<FormEditRequest>,
     pool: web::Data<PgPool>,
@@ -364,77 +982,394 @@ pub async fn edit_form(
     query: web::Query<FormQuery>,
     jwt: web::Data<JwtClient>,
 ) -> ApplicationResponse {
-    let _current_user = jwt.user_or_403(request).await?;
+    let user = jwt.user_or_403(request).await?;
+    let form = get_owned_form(query.id, user.id, pool.as_ref())
+        .await?
+        .ok_or_else(|| {
+            ApplicationError::NotFoundError(format!("No form found with id {}.", query.id))
+        })?;
+    for edit in json.all_fields().filter(|edit| !edit.delete) {
+        validate_field(&edit.caption, edit.r#type, &edit.config)?;
+    }
+
+    // Lock the form until the edit is committed, so that concurrent edits are applied, and
+    // numbered as versions, one at a time
+    let mut tx = pool.begin().await?;
+    lock_form(form.id, &mut tx).await?.ok_or_else(|| {
+        ApplicationError::NotFoundError(format!("No form found with id {}.", query.id))
+    })?;
+
+    // Only fields and sections of this form can be edited or moved
+    let existing_sections = get_form_sections(form.id, &mut tx).await?;
+    let existing_fields = get_form_fields(form.id, &mut tx).await?;
+    if let Some(section_id) = json
+        .sections
+        .iter()
+        .filter_map(|section| section.section_id)
+        .find(|id| !existing_sections.iter().any(|section| section.id == *id))
+    {
+        return Err(ApplicationError::BadRequestError(format!(
+            "No section with id {} on this form.",
+            section_id
+        )));
+    }
+    if let Some(field_id) = json
+        .all_fields()
+        .filter_map(|edit| edit.field_id)
+        .find(|id| !existing_fields.iter().any(|field| field.id == *id))
+    {
+        return Err(ApplicationError::BadRequestError(format!(
+            "No field with id {} on this form.",
+            field_id
+        )));
+    }
 
     sqlx::query!(
         r#"UPDATE form
            SET title = $1
            WHERE id = $2"#,
         json.title,
-        query.id
+        form.id
     )
-    .fetch_optional(pool.as_ref())
+    .execute(&mut tx)
     .await?;
 
-    let mut tx = pool.begin().await?;
-
-    for edit in json.fields.iter() {
-        match edit.field_id {
-            Some(field_id) => {
+    // The section each listed section's fields are placed in, or none if it is deleted
+    let mut targets: Vec<Option<Uuid>> = Vec::with_capacity(json.sections.len());
+    for (index, edit) in json.sections.iter().enumerate() {
+        match edit.section_id {
+            Some(section_id) => {
                 if edit.delete {
                     sqlx::query!(
-                        r#"DELETE FROM feedback
-                           WHERE form_input_id = $1"#,
-                        field_id
-                    )
-                    .execute(&mut tx)
-                    .await?;
-
-                    sqlx::query!(
-                        r#"DELETE FROM form_input
+                        r#"DELETE FROM form_section
                            WHERE id = $1"#,
-                        field_id
+                        section_id
                     )
                     .execute(&mut tx)
                     .await?;
+                    targets.push(None);
                 } else {
                     sqlx::query!(
-                        r#"UPDATE form_input
-                           SET caption = $1, type = $2
-                           WHERE id = $3"#,
-                        edit.caption,
-                        edit.r#type,
-                        field_id
+                        r#"UPDATE form_section
+                           SET title = $1, description = $2, page_break = $3
+                           WHERE id = $4"#,
+                        edit.title,
+                        edit.description,
+                        edit.page_break,
+                        section_id
                     )
                     .execute(&mut tx)
                     .await?;
+                    targets.push(Some(section_id));
                 }
             }
             None => {
-                sqlx::query!(
-                    r#"INSERT INTO form_input (id, form_id, type, caption)
-                       VALUES($1, $2, $3, $4)"#,
-                    Uuid::new_v4(),
-                    query.id.clone(),
-                    edit.r#type,
-                    edit.caption
-                )
-                .execute(&mut tx)
-                .await?;
+                let mut new_section = FormSection::new(form.id, index as i32);
+                new_section.title = edit.title.clone();
+                new_section.description = edit.description.clone();
+                new_section.page_break = edit.page_break;
+                new_section.store(&mut tx).await?;
+                targets.push(Some(new_section.id));
             }
-        };
+        }
     }
 
+    let mut listed = Vec::new();
+    let mut deleted = Vec::new();
+    let groups = std::iter::once((None, &json.fields)).chain(
+        targets
+            .iter()
+            .copied()
+            .zip(json.sections.iter().map(|section| &section.fields)),
+    );
+    for (section_id, edits) in groups {
+        for edit in edits.iter() {
+            match edit.field_id {
+                Some(field_id) => {
+                    if edit.delete {
+                        // Archive rather than delete the field, keeping earlier answers to it
+                        sqlx::query!(
+                            r#"UPDATE form_input
+                               SET archived_at = $1
+                               WHERE id = $2 AND form_id = $3"#,
+                            chrono::Utc::now().naive_utc(),
+                            field_id,
+                            form.id
+                        )
+                        .execute(&mut tx)
+                        .await?;
+                        deleted.push(field_id);
+                    } else {
+                        sqlx::query!(
+                            r#"UPDATE form_input
+                               SET caption = $1, type = $2, config = $3
+                               WHERE id = $4"#,
+                            edit.caption,
+                            edit.r#type.as_str(),
+                            serde_json::to_value(&edit.config)?,
+                            field_id
+                        )
+                        .execute(&mut tx)
+                        .await?;
+                        listed.push(Placement::new(field_id, section_id));
+                    }
+                }
+                None => {
+                    let field = new_field(
+                        form.id,
+                        &edit.caption,
+                        edit.r#type,
+                        &edit.config,
+                        0,
+                        section_id,
+                    )?;
+                    field.store(&mut tx).await?;
+                    listed.push(Placement::new(field.id, section_id));
+                }
+            };
+        }
+    }
+
+    // Renumber the sections and fields in their new order
+    let mut sections: Vec<Uuid> = targets.iter().flatten().copied().collect();
+    for section in existing_sections.iter() {
+        let removed = json
+            .sections
+            .iter()
+            .any(|edit| edit.delete && edit.section_id == Some(section.id));
+        if !removed && !sections.contains(&section.id) {
+            sections.push(section.id);
+        }
+    }
+    for (position, section_id) in sections.iter().enumerate() {
+        sqlx::query!(
+            r#"UPDATE form_section
+               SET position = $1
+               WHERE id = $2"#,
+            position as i32,
+            section_id
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+
+    let existing: Vec<Placement> = existing_fields
+        .iter()
+        .filter(|field| !deleted.contains(&field.id))
+        .map(|field| Placement::new(field.id, field.section_id))
+        .collect();
+    for (position, field) in arrange(&sections, &listed, &existing).iter().enumerate() {
+        sqlx::query!(
+            r#"UPDATE form_input
+               SET position = $1, section_id = $2
+               WHERE id = $3"#,
+            position as i32,
+            field.section_id,
+            field.field_id
+        )
+        .execute(&mut tx)
+        .await?;
+    }
+
+    // Earlier responses keep the version they answered, so the edit becomes a new version
+    record_form_version(form.id, &mut tx).await?;
     tx.commit().await?;
 
-    Ok(HttpResponse::Ok().body(format!("Successfully edited form with id {}", query.id)))
+    Ok(HttpResponse::Ok().body(format!("Successfully edited form with id {}", form.id)))
+}
+
+/// Describes one of the forms, keeping the fields, sections and rules it has now.
+pub(super) async fn form_definition(
+    form: &Form,
+    pool: &PgPool,
+) -> Result<FormDefinition, anyhow::Error> {
+    FormDefinition::of(
+        &form.title,
+        &get_form_fields(form.id, pool).await?,
+        &get_form_sections(form.id, pool).await?,
+        &get_form_rules(form.id, pool).await?,
+    )
+}
+
+/// Stores a new form of the account with the given `account_id` from a definition, returning
+/// the new form's id.
+pub(super) async fn store_form_definition(
+    definition: &FormDefinition,
+    status: FormStatus,
+    account_id: Uuid,
+    pool: &PgPool,
+) -> Result<Uuid, ApplicationError> {
+    let mut new_form = NewForm::default();
+    new_form.title = definition.title.clone();
+    new_form.account_id = account_id;
+    new_form.status = status.as_str().to_string();
+    let instance = definition
+        .instantiate(new_form.id)
+        .map_err(ApplicationError::BadRequestError)?;
+
+    // Store form first to avoid foreign key constrain
+    new_form.store(pool).await?;
+    let mut tx = pool.begin().await?;
+    for section in instance.sections.iter() {
+        section.store(&mut tx).await?;
+    }
+    for field in instance.fields.iter() {
+        field.store(&mut tx).await?;
+    }
+    for rule in instance.rules.iter() {
+        rule.store(&mut tx).await?;
+    }
+    record_form_version(new_form.id, &mut tx).await?;
+    tx.commit().await?;
+
+    Ok(new_form.id)
+}
+
+#[tracing::instrument(name = "handlers::form::duplicate", skip(query, pool, request, jwt), fields(username=Empty, user_id=Empty))]
+/// post(/form/duplicate?id={ID}) copies one of the user's forms, with its fields, sections and rules, into a new form.
+/// Responses, notification settings and the status of the form are not copied.
+pub async fn duplicate_form(
+    query: web::Query<FormQuery>,
+    pool: web::Data<PgPool>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+) -> ApplicationResponse {
+    let user = jwt.user_or_403(request).await?;
+    let form = get_owned_form(query.id, user.id, pool.as_ref())
+        .await?
+        .ok_or(ApplicationError::AuthError(
+            AuthenticationError::Unauthorized,
+        ))?;
+
+    let mut definition = form_definition(&form, pool.as_ref()).await?;
+    definition.title = format!("{} (copy)", definition.title);
+    let form_id =
+        store_form_definition(&definition, FormStatus::default(), user.id, pool.as_ref()).await?;
+
+    Ok(HttpResponse::Ok().body(format!("Stored new form with id {}.", form_id)))
+}
+
+#[tracing::instrument(name = "handlers::form::export", skip(query, pool, request, jwt, base_url), fields(username=Empty, user_id=Empty))]
+/// get(/form/export?id={ID}) describes one of the user's forms as a portable JSON document, see `/form/export/schema`
+pub async fn export_form(
+    query: web::Query<FormQuery>,
+    pool: web::Data<PgPool>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> ApplicationResponse {
+    let user = jwt.user_or_403(request).await?;
+    let form = get_owned_form(query.id, user.id, pool.as_ref())
+        .await?
+        .ok_or(ApplicationError::AuthError(
+            AuthenticationError::Unauthorized,
+        ))?;
+
+    let definition = form_definition(&form, pool.as_ref()).await?;
+    let schema = format!("{}/form/export/schema", base_url.0.trim_end_matches('/'));
+    json_response(&FormDocument::export(definition, Some(schema)))
+}
+
+#[tracing::instrument(name = "handlers::form::export_schema")]
+/// get(/form/export/schema) returns the JSON Schema of the documents exported and imported by the API
+pub async fn get_form_document_schema() -> ApplicationResponse {
+    Ok(HttpResponse::Ok()
+        .content_type("application/schema+json")
+        .body(FORM_DOCUMENT_SCHEMA))
+}
+
+#[tracing::instrument(name = "handlers::form::import", skip(json, pool, request, jwt), fields(username=Empty, user_id=Empty))]
+/// post(/form/import) creates a new form from a document exported by `/form/export`, checking its fields and rules first
+pub async fn import_form(
+    json: web::Json<FormDocument>,
+    pool: web::Data<PgPool>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+) -> ApplicationResponse {
+    let user = jwt.user_or_403(request).await?;
+    json.check_version()
+        .map_err(ApplicationError::BadRequestError)?;
+
+    let form_id = store_form_definition(
+        &json.form,
+        json.status.unwrap_or_default(),
+        user.id,
+        pool.as_ref(),
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().body(format!("Stored new form with id {}.", form_id)))
+}
+
+#[tracing::instrument(name = "handlers::form::delete", skip(query, pool, request, jwt), fields(username=Empty, user_id=Empty))]
+/// get(/form/delete?id={ID}) moves one of the user's forms to the trash, it stops accepting responses
+/// and is purged along with them once the retention period is over unless it is restored
+pub async fn delete_form(
+    query: web::Query<FormQuery>,
+    pool: web::Data<PgPool>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+) -> ApplicationResponse {
+    let user = jwt.user_or_403(request).await?;
+
+    let deleted = sqlx::query!(
+        r#"
+            UPDATE form SET deleted_at=$3
+            WHERE id=$1 AND account_id=$2 AND deleted_at IS NULL
+            RETURNING true
+        "#,
+        query.id,
+        user.id,
+        Utc::now().naive_utc()
+    )
+    .fetch_optional(pool.as_ref())
+    .await?;
+    if deleted.is_some() {
+        Ok(HttpResponse::Ok().finish())
+    } else {
+        Err(ApplicationError::AuthError(
+            AuthenticationError::Unauthorized,
+        ))
+    }
+}
+
+#[tracing::instrument(name = "handlers::form::restore", skip(query, pool, request, jwt), fields(username=Empty, user_id=Empty))]
+/// get(/form/restore?id={ID}) takes one of the user's forms back out of the trash
+pub async fn restore_form(
+    query: web::Query<FormQuery>,
+    pool: web::Data<PgPool>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+) -> ApplicationResponse {
+    let user = jwt.user_or_403(request).await?;
+
+    let restored = sqlx::query!(
+        r#"
+            UPDATE form SET deleted_at=NULL
+            WHERE id=$1 AND account_id=$2 AND deleted_at IS NOT NULL
+            RETURNING true
+        "#,
+        query.id,
+        user.id
+    )
+    .fetch_optional(pool.as_ref())
+    .await?;
+    if restored.is_some() {
+        Ok(HttpResponse::Ok().finish())
+    } else {
+        Err(ApplicationError::NotFoundError(format!(
+            "No form found in the trash with id {}.",
+            query.id
+        )))
+    }
 }
 
-#[tracing::instrument(name = "handlers::form::test_email", skip(postmark_client))]
-/// post(form/edit) runs an SQL query to edit a form
-pub async fn test_email(postmark_client: web::Data<PostmarkClient>) -> ApplicationResponse {
-    postmark_client
-        .send_email("japence@crimson.ua.edu", "Hello, Postmark!")
+#[tracing::instrument(name = "handlers::form::test_email", skip(providers))]
+/// get(/form/test) sends a test email through the configured email backend to check that delivery works
+pub async fn test_email(providers: web::Data<Providers>) -> ApplicationResponse {
+    providers
+        .email
+        .send(&Email::text("japence@crimson.ua.edu", "Hello, Postmark!"))
         .await?;
     Ok(HttpResponse::Ok().body("E-mail request made to Postmark".to_string()))
 }
@@ -0,0 +1,167 @@
+use std::str::FromStr;
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::field::Empty;
+use uuid::Uuid;
+
+use super::form::{form_definition, store_form_definition, FormQuery};
+use super::{json_response, ApplicationError, ApplicationResponse};
+use crate::db::{
+    get_form_templates, get_owned_form, get_owned_form_template, FormStatus, FormTemplate,
+};
+use crate::services::auth::AuthenticationError;
+use crate::services::form_template::{built_in_template, FormDefinition, BUILT_IN_TEMPLATES};
+use crate::services::jwt::JwtClient;
+
+#[derive(Serialize)]
+pub struct ListedFormTemplate {
+    /// Id of the account's template, or name of the starter template.
+    pub template_id: String,
+    pub name: String,
+    pub description: Option<String>,
+    /// Whether the template is one of the starter templates shipped with the API.
+    pub built_in: bool,
+    pub created_at: Option<NaiveDateTime>,
+    pub definition: FormDefinition,
+}
+
+#[derive(Serialize)]
+pub struct FormTemplateListResponse {
+    pub templates: Vec<ListedFormTemplate>,
+}
+
+#[tracing::instrument(name = "handlers::form_template::list", skip(pool, request, jwt), fields(username=Empty, user_id=Empty))]
+/// get(/form/templates) lists the starter templates followed by the user's own templates, most recent first
+pub async fn list_form_templates(
+    pool: web::Data<PgPool>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+) -> ApplicationResponse {
+    let user = jwt.user_or_403(request).await?;
+
+    let mut templates = Vec::new();
+    for template in BUILT_IN_TEMPLATES.iter() {
+        templates.push(ListedFormTemplate {
+            template_id: template.template_id.to_string(),
+            name: template.name.to_string(),
+            description: Some(template.description.to_string()),
+            built_in: true,
+            created_at: None,
+            definition: template.definition()?,
+        });
+    }
+    for template in get_form_templates(user.id, pool.as_ref()).await? {
+        templates.push(ListedFormTemplate {
+            template_id: template.id.to_string(),
+            definition: template.form_definition()?,
+            name: template.name,
+            description: template.description,
+            built_in: false,
+            created_at: Some(template.created_at),
+        });
+    }
+
+    json_response(&FormTemplateListResponse { templates })
+}
+
+#[derive(Deserialize, Debug)]
+pub struct FormTemplateCreationRequest {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+#[tracing::instrument(name = "handlers::form_template::save", skip(query, json, pool, request, jwt), fields(username=Empty, user_id=Empty))]
+/// post(/form/templates/save?id={FORM_ID}) saves the fields, sections and rules of one of the user's forms as a new template
+pub async fn save_form_template(
+    query: web::Query<FormQuery>,
+    json: web::Json<FormTemplateCreationRequest>,
+    pool: web::Data<PgPool>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+) -> ApplicationResponse {
+    let user = jwt.user_or_403(request).await?;
+    if json.name.trim().is_empty() {
+        return Err(ApplicationError::BadRequestError(
+            "Templates need a name.".to_string(),
+        ));
+    }
+    let form = get_owned_form(query.id, user.id, pool.as_ref())
+        .await?
+        .ok_or(ApplicationError::AuthError(
+            AuthenticationError::Unauthorized,
+        ))?;
+
+    let definition = form_definition(&form, pool.as_ref()).await?;
+    let mut template = FormTemplate::new(user.id, json.name.trim(), &definition)?;
+    template.description = json.description.clone();
+    template.store(pool.as_ref()).await?;
+
+    json_response(&template)
+}
+
+#[derive(Deserialize, Debug)]
+pub struct FormTemplateQuery {
+    /// Id of one of the user's templates, or name of a starter template.
+    pub id: String,
+}
+
+#[tracing::instrument(name = "handlers::form_template::use", skip(query, pool, request, jwt), fields(username=Empty, user_id=Empty))]
+/// post(/form/templates/use?id={TEMPLATE_ID}) creates a new form from one of the user's templates or a starter template
+pub async fn use_form_template(
+    query: web::Query<FormTemplateQuery>,
+    pool: web::Data<PgPool>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+) -> ApplicationResponse {
+    let user = jwt.user_or_403(request).await?;
+
+    let definition = match Uuid::from_str(&query.id) {
+        Ok(id) => get_owned_form_template(id, user.id, pool.as_ref())
+            .await?
+            .map(|template| template.form_definition())
+            .transpose()?,
+        Err(_) => built_in_template(&query.id)
+            .map(|template| template.definition())
+            .transpose()?,
+    }
+    .ok_or_else(|| {
+        ApplicationError::NotFoundError(format!("No template found with id {}.", query.id))
+    })?;
+    let form_id =
+        store_form_definition(&definition, FormStatus::default(), user.id, pool.as_ref()).await?;
+
+    Ok(HttpResponse::Ok().body(format!("Stored new form with id {}.", form_id)))
+}
+
+#[tracing::instrument(name = "handlers::form_template::delete", skip(query, pool, request, jwt), fields(username=Empty, user_id=Empty))]
+/// get(/form/templates/delete?id={TEMPLATE_ID}) deletes one of the user's templates, leaving the forms created from it
+pub async fn delete_form_template(
+    query: web::Query<FormQuery>,
+    pool: web::Data<PgPool>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+) -> ApplicationResponse {
+    let user = jwt.user_or_403(request).await?;
+
+    let deleted = sqlx::query!(
+        r#"
+            DELETE FROM form_template
+            WHERE id=$1 AND account_id=$2
+            RETURNING true
+        "#,
+        query.id,
+        user.id
+    )
+    .fetch_optional(pool.as_ref())
+    .await?;
+    if deleted.is_some() {
+        Ok(HttpResponse::Ok().finish())
+    } else {
+        Err(ApplicationError::AuthError(
+            AuthenticationError::Unauthorized,
+        ))
+    }
+}
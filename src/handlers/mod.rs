@@ -1,19 +1,34 @@
 //! Contains HTTP Handlers that directly receive and respond to requests to the server.
+mod account;
+mod alert;
 mod auth;
 mod form;
+mod form_template;
 mod health_check;
+mod postmark_webhook;
 mod qr_code;
+mod qr_code_recipient;
+mod trash;
+mod twilio_webhook;
 
+pub use account::*;
 use actix_web::{
     http::{header, HeaderValue, StatusCode},
     HttpResponse, ResponseError,
 };
+pub use alert::*;
 pub use auth::*;
 pub use form::*;
+pub use form_template::*;
 pub use health_check::*;
+pub use postmark_webhook::*;
 pub use qr_code::*;
+pub use qr_code_recipient::*;
+pub use trash::*;
+pub use twilio_webhook::*;
 
 use crate::services::auth::AuthenticationError;
+use crate::services::form_validation::FieldError;
 
 /// Alias used for all HTTP responses. Uses custom `ApplicationError` error handler.
 pub type ApplicationResponse = Result<HttpResponse, ApplicationError>;
@@ -27,6 +42,22 @@ pub enum ApplicationError {
     UnexpectedError(#[from] anyhow::Error),
     #[error("Not Found")]
     NotFoundError(String),
+    #[error("Bad Request: {0}")]
+    BadRequestError(String),
+    #[error("Forbidden: {0}")]
+    ForbiddenError(String),
+    #[error("Validation failed for {} field(s)", .0.len())]
+    ValidationError(Vec<FieldError>),
+}
+
+/// RFC 7807 problem document describing why a request's fields are invalid.
+#[derive(serde::Serialize)]
+struct ValidationProblem<'a> {
+    r#type: &'static str,
+    title: &'static str,
+    status: u16,
+    detail: &'static str,
+    errors: &'a [FieldError],
 }
 
 impl ResponseError for ApplicationError {
@@ -42,6 +73,17 @@ impl ResponseError for ApplicationError {
                 response
             }
             Self::NotFoundError(_message) => HttpResponse::new(StatusCode::NOT_FOUND),
+            Self::BadRequestError(message) => HttpResponse::BadRequest().body(message.clone()),
+            Self::ForbiddenError(message) => HttpResponse::Forbidden().body(message.clone()),
+            Self::ValidationError(errors) => HttpResponse::UnprocessableEntity()
+                .content_type("application/problem+json")
+                .json(ValidationProblem {
+                    r#type: "about:blank",
+                    title: "Unprocessable Entity",
+                    status: StatusCode::UNPROCESSABLE_ENTITY.as_u16(),
+                    detail: "One or more fields are invalid.",
+                    errors,
+                }),
         }
     }
 }
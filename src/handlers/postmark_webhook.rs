@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::ApplicationResponse;
+use crate::{
+    clients::email::NOTIFICATION_ID_METADATA,
+    db::{
+        get_account_ids_by_email, get_notification, get_notification_by_provider_sid,
+        record_provider_status, EmailEvent, EmailSuppression, Notification,
+    },
+    services::auth::validate_request_with_static_basic_auth,
+    startup::PostmarkWebhookCredentials,
+};
+
+/// Bounce type of emails that can never be delivered to their recipient.
+const HARD_BOUNCE: &str = "HardBounce";
+
+/// Event Postmark posts to its webhooks. Only the fields shared by the handled record types are read.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub struct PostmarkEvent {
+    pub record_type: String,
+    #[serde(rename = "MessageID")]
+    pub message_id: Option<String>,
+    /// Recipient of `Delivery` and `Open` events.
+    pub recipient: Option<String>,
+    /// Recipient of `Bounce` and `SpamComplaint` events.
+    pub email: Option<String>,
+    /// Bounce type, e.g. `HardBounce` or `SoftBounce`.
+    #[serde(rename = "Type")]
+    pub bounce_type: Option<String>,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+
+impl PostmarkEvent {
+    /// Status recorded on the event's notification, `None` for record types that are not handled.
+    fn provider_status(&self) -> Option<&'static str> {
+        match self.record_type.as_str() {
+            "Delivery" => Some("delivered"),
+            "Bounce" => Some("bounced"),
+            "SpamComplaint" => Some("spam_complaint"),
+            "Open" => Some("opened"),
+            _ => None,
+        }
+    }
+
+    fn recipient(&self) -> Option<&str> {
+        self.recipient.as_deref().or_else(|| self.email.as_deref())
+    }
+
+    fn is_hard_bounce(&self) -> bool {
+        self.record_type == "Bounce" && self.bounce_type.as_deref() == Some(HARD_BOUNCE)
+    }
+}
+
+/// Finds the notification an event was sent for, through the `MessageID` Postmark returned when
+/// the email was sent or, failing that, the notification id the email carries in its metadata.
+async fn event_notification(
+    event: &PostmarkEvent,
+    pool: &PgPool,
+) -> Result<Option<Notification>, anyhow::Error> {
+    if let Some(message_id) = &event.message_id {
+        if let Some(notification) = get_notification_by_provider_sid(message_id, pool).await? {
+            return Ok(Some(notification));
+        }
+    }
+    match event
+        .metadata
+        .get(NOTIFICATION_ID_METADATA)
+        .and_then(|id| Uuid::parse_str(id).ok())
+    {
+        Some(id) => get_notification(id, pool).await,
+        None => Ok(None),
+    }
+}
+
+#[tracing::instrument(name = "handlers::postmark_webhook", skip(pool, request, credentials))]
+/// post(/webhooks/postmark) receives delivery, bounce, spam complaint and open events from Postmark.
+/// Addresses that hard-bounce are suppressed by the account that emailed them
+pub async fn postmark_webhook(
+    pool: web::Data<PgPool>,
+    event: web::Json<PostmarkEvent>,
+    request: HttpRequest,
+    credentials: web::Data<PostmarkWebhookCredentials>,
+) -> ApplicationResponse {
+    validate_request_with_static_basic_auth(
+        &request,
+        &credentials.username,
+        &credentials.password,
+    )?;
+    // Postmark retries events it does not get a 2xx for, so other record types are acknowledged and ignored
+    let status = match event.provider_status() {
+        Some(status) => status,
+        None => return Ok(HttpResponse::Ok().finish()),
+    };
+
+    let notification = event_notification(&event, pool.as_ref()).await?;
+    EmailEvent::new(
+        notification.as_ref().map(|notification| notification.id),
+        event.message_id.clone(),
+        event.record_type.clone(),
+        event.recipient().map(|recipient| recipient.to_string()),
+        event
+            .bounce_type
+            .clone()
+            .or_else(|| event.description.clone()),
+    )
+    .store(pool.as_ref())
+    .await?;
+    if let Some(notification) = &notification {
+        record_provider_status(
+            notification.id,
+            event.message_id.as_deref(),
+            status,
+            pool.as_ref(),
+        )
+        .await?;
+    }
+
+    if event.is_hard_bounce() {
+        if let Some(email) = event.recipient() {
+            // Emails without a notification, such as password resets, are suppressed by every account using the address
+            let account_ids = match &notification {
+                Some(notification) => sqlx::query!(
+                    "SELECT account_id FROM qr_code WHERE id = $1",
+                    notification.qr_code_id
+                )
+                .fetch_optional(pool.as_ref())
+                .await?
+                .map(|qr_code| vec![qr_code.account_id])
+                .unwrap_or_default(),
+                None => get_account_ids_by_email(email, pool.as_ref()).await?,
+            };
+            for account_id in account_ids {
+                EmailSuppression::new(
+                    account_id,
+                    email,
+                    HARD_BOUNCE.to_string(),
+                    event.message_id.clone(),
+                )
+                .store(pool.as_ref())
+                .await?;
+            }
+        }
+    }
+
+    Ok(HttpResponse::Ok().finish())
+}
@@ -4,15 +4,32 @@ use actix_web::{web, HttpRequest, HttpResponse};
 use sqlx::PgPool;
 use uuid::Uuid;
 
-use super::ApplicationResponse;
+use super::{qr_code_recipient::MAX_UTC_OFFSET_MINUTES, ApplicationResponse};
 use crate::{
-    clients::{postmark::PostmarkClient, twilio::TwilioClient},
-    db::QrCode,
+    db::{
+        cancel_open_alerts, cancel_pending_notifications, get_account_logo, get_email_suppressions,
+        get_notification_attempts, get_notification_replies, get_notifications_for_qr_code,
+        get_owned_qr_code, get_qr_code_recipients, get_scan_series, get_scan_totals,
+        is_notification_throttled, Alert, Notification, NotificationAttempt, NotificationChannel,
+        NotificationReply, PhoneChannel, QrCode, ScanBucket, ScanEvent, ScanInterval, ScanTotals,
+    },
     handlers::{json_response, ApplicationError},
+    services::alert::alert_notification,
+    services::analytics,
     services::auth::AuthenticationError,
     services::jwt::JwtClient,
+    services::qr_image::{
+        self, parse_hex_color, ErrorCorrection, ImageFormat, ModuleShape, DEFAULT_IMAGE_SIZE,
+        MAX_IMAGE_SIZE, MAX_QUIET_ZONE, MIN_IMAGE_SIZE,
+    },
+    services::qr_sheet::{self, SheetEntry, SheetLayout},
+    services::telemetry::spawn_blocking_with_tracing,
+    services::template::{Template, TemplateContext, DEFAULT_TEMPLATE},
+    startup::{ApplicationBaseUrl, ScanClientSettings},
 };
-use serde::Deserialize;
+use anyhow::Context;
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
 use tracing::field::Empty;
 
 #[derive(Deserialize, Clone)]
@@ -22,10 +39,125 @@ pub struct EditQrCodeRequest {
     pub email: Option<String>,
     pub payload: Option<String>,
     pub form_id: Option<Uuid>,
+    pub label: Option<String>,
+    pub foreground_color: Option<String>,
+    pub background_color: Option<String>,
+    pub module_shape: Option<String>,
+    pub quiet_zone: Option<u32>,
+    pub show_logo: Option<bool>,
+    pub code_cooldown_seconds: Option<u32>,
+    pub client_cooldown_seconds: Option<u32>,
+    pub notification_channel: Option<PhoneChannel>,
+    pub utc_offset_minutes: Option<i32>,
+    pub voice: Option<String>,
+    pub voice_language: Option<String>,
+    pub voice_loop: Option<u32>,
+    /// Seconds after which unacknowledged alerts are escalated, 0 to stop escalating them.
+    pub escalation_timeout_seconds: Option<u32>,
+}
+
+/// Most times a notification can be repeated during a call.
+const MAX_VOICE_LOOP: u32 = 10;
+
+/// Longest cooldown window that can be configured on a QR code, one week.
+const MAX_COOLDOWN_SECONDS: u32 = 7 * 24 * 60 * 60;
+
+/// Longest time an alert can wait for an acknowledgement before it is escalated, one day.
+const MAX_ESCALATION_TIMEOUT_SECONDS: u32 = 24 * 60 * 60;
+
+impl EditQrCodeRequest {
+    /// Rejects styling values that could not be rendered.
+    fn validate_style(&self) -> Result<(), ApplicationError> {
+        let invalid = |e: anyhow::Error| ApplicationError::BadRequestError(e.to_string());
+        if let Some(color) = &self.foreground_color {
+            parse_hex_color(color).map_err(invalid)?;
+        }
+        if let Some(color) = &self.background_color {
+            parse_hex_color(color).map_err(invalid)?;
+        }
+        if let Some(shape) = &self.module_shape {
+            ModuleShape::from_str(shape).map_err(invalid)?;
+        }
+        if let Some(quiet_zone) = self.quiet_zone {
+            if quiet_zone > MAX_QUIET_ZONE {
+                return Err(ApplicationError::BadRequestError(format!(
+                    "quiet_zone must be at most {} modules.",
+                    MAX_QUIET_ZONE
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Rejects payloads that are not valid message templates and invalid time zone offsets.
+    fn validate_message(&self) -> Result<(), ApplicationError> {
+        validate_message(&self.payload, self.utc_offset_minutes)
+    }
+
+    /// Rejects voice settings Twilio would not accept.
+    fn validate_voice(&self) -> Result<(), ApplicationError> {
+        let invalid = |message: &str| Err(ApplicationError::BadRequestError(message.to_string()));
+        if let Some(voice) = &self.voice {
+            let allowed = |c: char| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_';
+            if voice.is_empty() || voice.len() > 64 || !voice.chars().all(allowed) {
+                return invalid(
+                    "voice must be a Twilio voice name such as `alice` or `Polly.Lupe`.",
+                );
+            }
+        }
+        if let Some(language) = &self.voice_language {
+            let mut parts = language.splitn(2, '-');
+            let primary = parts.next().unwrap_or_default();
+            let region = parts.next().unwrap_or("ab");
+            if !(2..=3).contains(&primary.len())
+                || !primary.chars().all(|c| c.is_ascii_lowercase())
+                || !(2..=4).contains(&region.len())
+                || !region.chars().all(|c| c.is_ascii_alphanumeric())
+            {
+                return invalid(
+                    "voice_language must be a language tag such as `en-US` or `es-MX`.",
+                );
+            }
+        }
+        if let Some(voice_loop) = self.voice_loop {
+            if !(1..=MAX_VOICE_LOOP).contains(&voice_loop) {
+                return Err(ApplicationError::BadRequestError(format!(
+                    "voice_loop must be between 1 and {}.",
+                    MAX_VOICE_LOOP
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Rejects notification cooldown windows and escalation timeouts that are unreasonably long.
+    fn validate_cooldowns(&self) -> Result<(), ApplicationError> {
+        for cooldown in [self.code_cooldown_seconds, self.client_cooldown_seconds]
+            .iter()
+            .flatten()
+        {
+            if *cooldown > MAX_COOLDOWN_SECONDS {
+                return Err(ApplicationError::BadRequestError(format!(
+                    "Cooldowns must be at most {} seconds.",
+                    MAX_COOLDOWN_SECONDS
+                )));
+            }
+        }
+        if let Some(timeout) = self.escalation_timeout_seconds {
+            if timeout > MAX_ESCALATION_TIMEOUT_SECONDS {
+                return Err(ApplicationError::BadRequestError(format!(
+                    "escalation_timeout_seconds must be at most {} seconds.",
+                    MAX_ESCALATION_TIMEOUT_SECONDS
+                )));
+            }
+        }
+        Ok(())
+    }
 }
 
 #[tracing::instrument(name = "handlers::qr_code::edit", skip(pool, json, jwt), fields(user_id=Empty))]
-/// get(/qr_code/edit?id={ID}) edits a QR code with the relevant information
+/// get(/qr_code/edit?id={ID}) edits a QR code with the relevant information.
+/// Styling, cooldown, notification channel, voice and escalation fields that are left out keep their current value.
 pub async fn edit_qr_code(
     pool: web::Data<PgPool>,
     json: web::Json<EditQrCodeRequest>,
@@ -34,20 +166,58 @@ pub async fn edit_qr_code(
 ) -> ApplicationResponse {
     let user = jwt.user_or_403(request).await?;
     tracing::Span::current().record("user_id", &tracing::field::display(&user.id));
+    json.validate_style()?;
+    json.validate_cooldowns()?;
+    json.validate_message()?;
+    json.validate_voice()?;
+    validate_email(&json.email)?;
 
     let query = sqlx::query!(
         r#"
             UPDATE qr_code
-            SET phone_number=$2, email=$3, payload=$4, form_id=$5
-            WHERE id=$1 AND account_id=$6
-            RETURNING true
+            SET phone_number=$2, email=$3, payload=$4, form_id=$5, label=$12,
+                foreground_color=COALESCE($7, foreground_color),
+                background_color=COALESCE($8, background_color),
+                module_shape=COALESCE($9, module_shape),
+                quiet_zone=COALESCE($10, quiet_zone),
+                show_logo=COALESCE($11, show_logo),
+                code_cooldown_seconds=COALESCE($13, code_cooldown_seconds),
+                client_cooldown_seconds=COALESCE($14, client_cooldown_seconds),
+                notification_channel=COALESCE($15, notification_channel),
+                utc_offset_minutes=COALESCE($16, utc_offset_minutes),
+                voice=COALESCE($17, voice),
+                voice_language=COALESCE($18, voice_language),
+                voice_loop=COALESCE($19, voice_loop),
+                escalation_timeout_seconds=CASE WHEN $20::INTEGER IS NULL
+                    THEN escalation_timeout_seconds ELSE NULLIF($20, 0) END
+            WHERE id=$1 AND account_id=$6 AND deleted_at IS NULL
+            RETURNING id
         "#,
         json.id,
         json.phone_number,
         json.email,
         json.payload,
         json.form_id,
-        user.id
+        user.id,
+        json.foreground_color
+            .as_ref()
+            .map(|color| color.to_lowercase()),
+        json.background_color
+            .as_ref()
+            .map(|color| color.to_lowercase()),
+        json.module_shape.as_ref().map(|shape| shape.to_lowercase()),
+        json.quiet_zone.map(|quiet_zone| quiet_zone as i32),
+        json.show_logo,
+        json.label,
+        json.code_cooldown_seconds.map(|cooldown| cooldown as i32),
+        json.client_cooldown_seconds.map(|cooldown| cooldown as i32),
+        json.notification_channel.map(|channel| channel.as_str()),
+        json.utc_offset_minutes,
+        json.voice,
+        json.voice_language,
+        json.voice_loop.map(|voice_loop| voice_loop as i32),
+        json.escalation_timeout_seconds
+            .map(|timeout| timeout as i32)
     )
     .fetch_optional(pool.as_ref())
     .await?;
@@ -67,7 +237,9 @@ pub struct DeleteQrCodeRequest {
 }
 
 #[tracing::instrument(name = "handlers::qr_code::delete", skip(pool, query, jwt), fields(username=Empty, user_id=Empty))]
-/// get(/qr_code/delete?id={ID}) edits a QR code with the relevant information
+/// get(/qr_code/delete?id={ID}) moves a QR code to the trash, it stops responding to scans and
+/// its open alerts and pending notifications are cancelled. It is purged once the retention
+/// period is over unless it is restored
 pub async fn delete_qr_code(
     pool: web::Data<PgPool>,
     query: web::Query<EditQrCodeRequest>,
@@ -78,18 +250,24 @@ pub async fn delete_qr_code(
     tracing::Span::current().record("username", &tracing::field::display(&user.username));
     tracing::Span::current().record("user_id", &tracing::field::display(&user.id));
 
-    let query = sqlx::query!(
+    let mut tx = pool.begin().await?;
+    let deleted = sqlx::query!(
         r#"
-            DELETE FROM qr_code
-            WHERE id=$1 AND account_id=$2
+            UPDATE qr_code SET deleted_at=$3
+            WHERE id=$1 AND account_id=$2 AND deleted_at IS NULL
             RETURNING true
         "#,
         query.id,
-        user.id
+        user.id,
+        Utc::now().naive_utc()
     )
-    .fetch_optional(pool.as_ref())
+    .fetch_optional(&mut tx)
     .await?;
-    if query.is_some() {
+    if deleted.is_some() {
+        // Codes in the trash stop alerting, so nothing queued for them is sent any more
+        cancel_open_alerts(query.id, &mut tx).await?;
+        cancel_pending_notifications(query.id, &mut tx).await?;
+        tx.commit().await?;
         Ok(HttpResponse::Ok().finish())
     } else {
         Err(ApplicationError::AuthError(
@@ -98,12 +276,84 @@ pub async fn delete_qr_code(
     }
 }
 
+#[tracing::instrument(name = "handlers::qr_code::restore", skip(pool, query, request, jwt), fields(username=Empty, user_id=Empty))]
+/// get(/qr_code/restore?id={ID}) takes one of the user's QR codes back out of the trash
+pub async fn restore_qr_code(
+    pool: web::Data<PgPool>,
+    query: web::Query<DeleteQrCodeRequest>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+) -> ApplicationResponse {
+    let user = jwt.user_or_403(request).await?;
+
+    let restored = sqlx::query!(
+        r#"
+            UPDATE qr_code SET deleted_at=NULL
+            WHERE id=$1 AND account_id=$2 AND deleted_at IS NOT NULL
+            RETURNING true
+        "#,
+        query.id,
+        user.id
+    )
+    .fetch_optional(pool.as_ref())
+    .await?;
+    if restored.is_some() {
+        Ok(HttpResponse::Ok().finish())
+    } else {
+        Err(ApplicationError::NotFoundError(format!(
+            "No QR code found in the trash with id {}.",
+            query.id
+        )))
+    }
+}
+
 #[derive(Deserialize, Clone)]
 pub struct GenerateQrCodeRequest {
     pub phone_number: Option<String>,
     pub email: Option<String>,
     pub payload: Option<String>,
     pub form_id: Option<Uuid>,
+    pub label: Option<String>,
+    #[serde(default)]
+    pub notification_channel: PhoneChannel,
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+}
+
+/// Rejects payloads that are not valid message templates and invalid time zone offsets.
+fn validate_message(
+    payload: &Option<String>,
+    utc_offset_minutes: Option<i32>,
+) -> Result<(), ApplicationError> {
+    if let Some(payload) = payload {
+        Template::parse(payload)
+            .map_err(|e| ApplicationError::BadRequestError(format!("Invalid payload: {}", e)))?;
+    }
+    if let Some(utc_offset_minutes) = utc_offset_minutes {
+        if utc_offset_minutes.abs() > MAX_UTC_OFFSET_MINUTES {
+            return Err(ApplicationError::BadRequestError(format!(
+                "utc_offset_minutes must be between -{0} and {0}.",
+                MAX_UTC_OFFSET_MINUTES
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Rejects email addresses that scans could not be notified at, an empty address notifies no one.
+fn validate_email(email: &Option<String>) -> Result<(), ApplicationError> {
+    match email {
+        Some(email)
+            if !email.is_empty()
+                && (!email.contains('@')
+                    || email.contains(|c: char| c.is_whitespace() || c == '<' || c == '>')) =>
+        {
+            Err(ApplicationError::BadRequestError(
+                "QR codes need a valid email address.".to_string(),
+            ))
+        }
+        _ => Ok(()),
+    }
 }
 
 #[derive(serde::Serialize)]
@@ -121,19 +371,24 @@ pub async fn generate_qr_code(
 ) -> ApplicationResponse {
     let user = jwt.user_or_403(request).await?;
     tracing::Span::current().record("user_id", &tracing::field::display(&user.id));
+    validate_message(&json.payload, Some(json.utc_offset_minutes))?;
+    validate_email(&json.email)?;
 
     let qr_code_id = Uuid::new_v4();
 
     sqlx::query!(
         r#"
-            INSERT INTO qr_code (id, account_id, phone_number, email, payload, form_id)
-            VALUES ($1, $2, $3, $4, $5, $6)"#,
+            INSERT INTO qr_code (id, account_id, phone_number, email, payload, form_id, label, notification_channel, utc_offset_minutes)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)"#,
         qr_code_id,
         user.id,
         json.phone_number,
         json.email,
         json.payload,
         json.form_id,
+        json.label,
+        json.notification_channel.as_str(),
+        json.utc_offset_minutes,
     )
     .execute(pool.as_ref())
     .await?;
@@ -161,51 +416,304 @@ pub async fn list_qr_codes(
         QrCode,
         r#"
             SELECT * FROM qr_code
-            WHERE account_id=$1"#,
+            WHERE account_id=$1 AND deleted_at IS NULL"#,
         user.id,
     )
     .fetch_all(pool.as_ref())
     .await?;
     json_response(&ListQrCodesResponse { qr_codes })
 }
+#[derive(Deserialize, Debug)]
+pub struct QrCodeImageQuery {
+    pub id: Uuid,
+    #[serde(default)]
+    pub format: ImageFormat,
+    pub size: Option<u32>,
+    #[serde(default)]
+    pub ecc: ErrorCorrection,
+}
+
+#[tracing::instrument(name = "handlers::qr_code::image", skip(pool, base_url))]
+/// get(/qr_code/image?id={ID}&format={png|svg}&size={PIXELS}&ecc={L|M|Q|H}) renders a QR code's scan URL as an image
+/// using the QR code's stored styling. Codes showing their account's logo are always rendered with `ecc=H`.
+pub async fn get_qr_code_image(
+    pool: web::Data<PgPool>,
+    query: web::Query<QrCodeImageQuery>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> ApplicationResponse {
+    let size = query.size.unwrap_or(DEFAULT_IMAGE_SIZE);
+    if !(MIN_IMAGE_SIZE..=MAX_IMAGE_SIZE).contains(&size) {
+        return Err(ApplicationError::BadRequestError(format!(
+            "size must be between {} and {} pixels.",
+            MIN_IMAGE_SIZE, MAX_IMAGE_SIZE
+        )));
+    }
+
+    let qr_code = sqlx::query_as!(
+        QrCode,
+        "SELECT * FROM qr_code WHERE id=$1 AND deleted_at IS NULL",
+        query.id
+    )
+    .fetch_optional(pool.as_ref())
+    .await?
+    .ok_or_else(|| {
+        ApplicationError::NotFoundError(format!("No QR code found with id {}.", query.id))
+    })?;
+
+    let mut style = qr_code.style()?;
+    let logo = if qr_code.show_logo {
+        get_account_logo(qr_code.account_id, pool.as_ref()).await?
+    } else {
+        None
+    };
+
+    let url = qr_image::scan_url(&base_url.0, qr_code.id);
+    let (format, ecc) = (query.format, query.ecc);
+    let image = spawn_blocking_with_tracing(move || {
+        if let Some(logo) = logo {
+            style.logo = Some(qr_image::decode_logo(&logo.data)?);
+        }
+        qr_image::render(&url, format, size, ecc, &style)
+    })
+    .await
+    .context("Failed to spawn blocking task.")??;
+
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .body(image))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct QrCodeSheetQuery {
+    /// Comma separated QR code ids. All of the user's QR codes are printed when omitted.
+    pub ids: Option<String>,
+    #[serde(default)]
+    pub layout: SheetLayout,
+}
+
+#[tracing::instrument(name = "handlers::qr_code::sheet", skip(pool, request, jwt, base_url), fields(username=Empty, user_id=Empty))]
+/// get(/qr_codes/sheet.pdf?ids={ID},{ID}&layout={LAYOUT}) lays out the user's QR codes on printable label sheets
+pub async fn get_qr_code_sheet(
+    pool: web::Data<PgPool>,
+    query: web::Query<QrCodeSheetQuery>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> ApplicationResponse {
+    let user = jwt.user_or_403(request).await?;
+
+    let ids = match &query.ids {
+        Some(ids) => Some(
+            ids.split(',')
+                .map(|id| Uuid::from_str(id.trim()))
+                .collect::<Result<Vec<Uuid>, _>>()
+                .map_err(|e| ApplicationError::BadRequestError(e.to_string()))?,
+        ),
+        None => None,
+    };
+
+    let mut qr_codes = sqlx::query_as!(
+        QrCode,
+        r#"
+            SELECT * FROM qr_code
+            WHERE account_id=$1 AND deleted_at IS NULL AND ($2::uuid[] IS NULL OR id = ANY($2))
+            ORDER BY label, id"#,
+        user.id,
+        ids.as_deref(),
+    )
+    .fetch_all(pool.as_ref())
+    .await?;
+
+    if let Some(ids) = &ids {
+        if let Some(missing) = ids.iter().find(|id| !qr_codes.iter().any(|q| &q.id == *id)) {
+            return Err(ApplicationError::NotFoundError(format!(
+                "No QR code found with id {}.",
+                missing
+            )));
+        }
+        // Print the codes in the order they were requested
+        qr_codes.sort_by_key(|q| ids.iter().position(|id| id == &q.id));
+    }
+
+    let logo = if qr_codes.iter().any(|q| q.show_logo) {
+        get_account_logo(user.id, pool.as_ref()).await?
+    } else {
+        None
+    };
+
+    let layout = query.layout;
+    let base_url = base_url.0.clone();
+    let sheet = spawn_blocking_with_tracing(move || {
+        let logo = logo
+            .map(|logo| qr_image::decode_logo(&logo.data))
+            .transpose()?;
+        let entries = qr_codes
+            .iter()
+            .map(|qr_code| {
+                let mut style = qr_code.style()?;
+                if qr_code.show_logo {
+                    style.logo = logo.clone();
+                }
+                Ok(SheetEntry {
+                    data: qr_image::scan_url(&base_url, qr_code.id),
+                    caption: qr_code.caption(),
+                    style,
+                })
+            })
+            .collect::<Result<Vec<SheetEntry>, anyhow::Error>>()?;
+        qr_sheet::render_sheet(&entries, layout)
+    })
+    .await
+    .context("Failed to spawn blocking task.")??;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/pdf")
+        .body(sheet))
+}
+
 #[derive(Deserialize, Clone)]
 pub struct ScanQrCodeRequest {
     pub id: String,
 }
 
+/// get(/scan?id={ID}) records a scan and alerts the QR code's owner, unless a cooldown window is still open
 pub async fn scan(
     pool: web::Data<PgPool>,
     query: web::Query<ScanQrCodeRequest>,
-    twilio: web::Data<TwilioClient>,
-    mail: web::Data<PostmarkClient>,
+    request: HttpRequest,
+    scan_client_settings: web::Data<ScanClientSettings>,
+    base_url: web::Data<ApplicationBaseUrl>,
 ) -> ApplicationResponse {
     let id = query.id.as_ref();
     let id = Uuid::from_str(id).map_err(|e| anyhow::anyhow!(e))?;
-    let qr_code = sqlx::query!("select * from qr_code where id=$1", id)
-        .fetch_one(pool.as_ref())
-        .await?;
+    let mut tx = pool.begin().await?;
+    // Lock the QR code so that concurrent scans, possibly on other instances, see each other's events
+    let qr_code = sqlx::query_as!(
+        QrCode,
+        "select * from qr_code where id=$1 AND deleted_at IS NULL FOR UPDATE",
+        id
+    )
+    .fetch_optional(&mut tx)
+    .await?
+    .ok_or_else(|| ApplicationError::NotFoundError(format!("No QR code found with id {}.", id)))?;
 
-    if qr_code.phone_number.is_some() || qr_code.email.is_some() {
-        let message = qr_code.payload.ok_or_else(|| {
-            ApplicationError::UnexpectedError(anyhow::anyhow!(
-                "expect qr_code.payload when qr_code.phone_number or qr_code.email is defined"
-            ))
-        })?;
+    let mut recipients = Vec::new();
+    // Check if there is an assosciated phone number with this QR code
+    if let Some(phone_number) = &qr_code.phone_number {
+        if !phone_number.is_empty() {
+            for channel in qr_code.phone_channel()?.channels() {
+                recipients.push((*channel, phone_number.clone(), qr_code.utc_offset_minutes));
+            }
+        }
+    }
+    // Check if there is an assosciated email address with this QR code
+    if let Some(email) = &qr_code.email {
+        if !email.is_empty() {
+            recipients.push((
+                NotificationChannel::Email,
+                email.clone(),
+                qr_code.utc_offset_minutes,
+            ));
+        }
+    }
 
-        // Check if there is an assosciated phone number with this QR code
-        if let Some(phone_number) = qr_code.phone_number {
-            if !phone_number.is_empty() {
-                twilio.send_call(phone_number, message.clone()).await?;
+    let mut event = scan_event(&request, qr_code.id, &scan_client_settings);
+    // Fan out to the additional recipients that are active and outside their quiet hours,
+    // escalation recipients are only notified once the alert is escalated
+    for recipient in get_qr_code_recipients(qr_code.id, &mut tx).await? {
+        if !recipient.escalation && recipient.active && !recipient.is_quiet_at(event.scanned_at) {
+            for channel in recipient.recipient_channel()?.channels() {
+                recipients.push((
+                    *channel,
+                    recipient.address.clone(),
+                    recipient.utc_offset_minutes,
+                ));
             }
         }
+    }
+
+    // Addresses the account suppressed because emails to them hard-bounced are not emailed again
+    let suppressed: Vec<String> = get_email_suppressions(qr_code.account_id, &mut tx)
+        .await?
+        .into_iter()
+        .map(|suppression| suppression.email)
+        .collect();
+    recipients.retain(|(channel, address, _)| {
+        *channel != NotificationChannel::Email || !suppressed.contains(&address.to_lowercase())
+    });
+
+    if !recipients.is_empty() {
+        let code_since = event.scanned_at - Duration::seconds(qr_code.code_cooldown_seconds as i64);
+        let client_since =
+            event.scanned_at - Duration::seconds(qr_code.client_cooldown_seconds as i64);
+        event.throttled = is_notification_throttled(
+            qr_code.id,
+            event.client_hash.as_deref(),
+            code_since,
+            client_since,
+            &mut tx,
+        )
+        .await?;
+        event.notified = !event.throttled;
+    }
+    event.store(&mut tx).await?;
 
-        // Check if there is an assosciated email address with this QR code
-        if let Some(email) = qr_code.email {
-            if !&email.is_empty() {
-                mail.send_email(&email, message.as_str()).await?;
+    // Queue the alert and its notifications in the scan's transaction, they are delivered by the notification worker
+    if event.notified {
+        // Payloads stored before templates were validated are sent verbatim if they do not parse
+        let template = match &qr_code.payload {
+            Some(payload) => {
+                Template::parse(payload).unwrap_or_else(|_| Template::literal(payload))
             }
+            None => Template::parse(DEFAULT_TEMPLATE)?,
+        };
+        let form_title = match qr_code.form_id {
+            Some(form_id) => sqlx::query!(
+                "SELECT title FROM form WHERE id=$1 AND deleted_at IS NULL",
+                form_id
+            )
+            .fetch_optional(&mut tx)
+            .await?
+            .and_then(|form| form.title),
+            None => None,
+        };
+        let mut context = TemplateContext {
+            qr_id: qr_code.id,
+            qr_label: qr_code.label.clone(),
+            form_id: qr_code.form_id,
+            form_title,
+            scan_time: event.scanned_at,
+            utc_offset_minutes: qr_code.utc_offset_minutes,
+        };
+
+        let escalate_at = qr_code
+            .escalation_timeout_seconds
+            .map(|timeout| event.scanned_at + Duration::seconds(timeout as i64));
+        let alert = Alert::new(
+            qr_code.id,
+            Some(event.id),
+            template.render(&context),
+            escalate_at,
+        );
+        alert.store(&mut tx).await?;
+
+        let subject = format!("Alert: {}", qr_code.caption());
+        for (channel, recipient, utc_offset_minutes) in recipients {
+            context.utc_offset_minutes = utc_offset_minutes;
+            alert_notification(
+                &qr_code,
+                &alert,
+                channel,
+                recipient,
+                &template.render_message(&context),
+                &subject,
+                &base_url.0,
+            )
+            .store(&mut tx)
+            .await?;
         }
     }
+    tx.commit().await?;
 
     // Check if there is an assosciated form with this QR code
     if let Some(form_id) = qr_code.form_id {
@@ -216,5 +724,180 @@ pub async fn scan(
         ));
         return Ok(x.finish());
     }
+    if event.throttled {
+        return Ok(HttpResponse::Ok().body(
+            "Thank you for scanning a Hermod QR Code. Someone has already been notified and will be with you shortly.",
+        ));
+    }
     Ok(HttpResponse::Ok().body("Thank you for scanning a Hermod QR Code."))
 }
+
+/// Builds the analytics event for a scan from the scanner's request.
+fn scan_event(request: &HttpRequest, qr_code_id: Uuid, settings: &ScanClientSettings) -> ScanEvent {
+    let header = |name: &str| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string())
+    };
+    // Every X-Forwarded-For header counts, proxies may append their own instead of extending one
+    let forwarded_for = request
+        .headers()
+        .get_all("X-Forwarded-For")
+        .filter_map(|value| value.to_str().ok())
+        .collect::<Vec<_>>()
+        .join(",");
+    let client_hash = request.peer_addr().map(|peer| {
+        let ip = analytics::client_ip(peer.ip(), Some(&forwarded_for), &settings.trusted_proxies);
+        analytics::client_hash(&settings.hash_salt, ip)
+    });
+    ScanEvent::new(
+        qr_code_id,
+        header("User-Agent"),
+        header("Referer"),
+        client_hash,
+    )
+}
+
+/// Number of days covered by the daily scan series when none is requested.
+const DEFAULT_STATS_DAYS: u32 = 30;
+/// Maximum number of days the daily scan series may cover.
+const MAX_STATS_DAYS: u32 = 365;
+/// Number of hours covered by the hourly scan series.
+const STATS_HOURS: i64 = 48;
+
+#[derive(Deserialize, Debug)]
+pub struct QrCodeStatsQuery {
+    pub id: Uuid,
+    pub days: Option<u32>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct QrCodeStatsResponse {
+    pub qr_code_id: Uuid,
+    #[serde(flatten)]
+    pub totals: ScanTotals,
+    /// Scans per hour over the last 48 hours.
+    pub hourly: Vec<ScanBucket>,
+    /// Scans per day over the requested number of days.
+    pub daily: Vec<ScanBucket>,
+}
+
+#[tracing::instrument(name = "handlers::qr_code::stats", skip(pool, request, jwt), fields(username=Empty, user_id=Empty))]
+/// get(/qr_code/stats?id={ID}&days={DAYS}) reports scan totals and time series for one of the user's QR codes
+pub async fn get_qr_code_stats(
+    pool: web::Data<PgPool>,
+    query: web::Query<QrCodeStatsQuery>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+) -> ApplicationResponse {
+    let user = jwt.user_or_403(request).await?;
+
+    let days = query.days.unwrap_or(DEFAULT_STATS_DAYS);
+    if !(1..=MAX_STATS_DAYS).contains(&days) {
+        return Err(ApplicationError::BadRequestError(format!(
+            "days must be between 1 and {}.",
+            MAX_STATS_DAYS
+        )));
+    }
+
+    get_owned_qr_code(query.id, user.id, pool.as_ref())
+        .await?
+        .ok_or(ApplicationError::AuthError(
+            AuthenticationError::Unauthorized,
+        ))?;
+
+    let now = Utc::now().naive_utc();
+    let totals = get_scan_totals(query.id, pool.as_ref()).await?;
+    let hourly = get_scan_series(
+        query.id,
+        ScanInterval::Hour,
+        now - Duration::hours(STATS_HOURS - 1),
+        now,
+        pool.as_ref(),
+    )
+    .await?;
+    let daily = get_scan_series(
+        query.id,
+        ScanInterval::Day,
+        now - Duration::days(days as i64 - 1),
+        now,
+        pool.as_ref(),
+    )
+    .await?;
+
+    json_response(&QrCodeStatsResponse {
+        qr_code_id: query.id,
+        totals,
+        hourly,
+        daily,
+    })
+}
+
+/// Maximum number of notifications returned for a QR code.
+const MAX_NOTIFICATIONS: i64 = 100;
+
+#[derive(Deserialize, Debug)]
+pub struct QrCodeNotificationsQuery {
+    pub id: Uuid,
+}
+
+#[derive(Serialize, Debug)]
+pub struct NotificationWithAttempts {
+    #[serde(flatten)]
+    pub notification: Notification,
+    pub delivery_attempts: Vec<NotificationAttempt>,
+    /// SMS messages the recipient sent back, oldest first.
+    pub replies: Vec<NotificationReply>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct QrCodeNotificationsResponse {
+    pub notifications: Vec<NotificationWithAttempts>,
+}
+
+#[tracing::instrument(name = "handlers::qr_code::notifications", skip(pool, request, jwt), fields(username=Empty, user_id=Empty))]
+/// get(/qr_code/notifications?id={ID}) lists the latest notifications sent for one of the user's QR codes
+/// along with their delivery status, attempts and replies
+pub async fn get_qr_code_notifications(
+    pool: web::Data<PgPool>,
+    query: web::Query<QrCodeNotificationsQuery>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+) -> ApplicationResponse {
+    let user = jwt.user_or_403(request).await?;
+
+    get_owned_qr_code(query.id, user.id, pool.as_ref())
+        .await?
+        .ok_or(ApplicationError::AuthError(
+            AuthenticationError::Unauthorized,
+        ))?;
+
+    let notifications =
+        get_notifications_for_qr_code(query.id, MAX_NOTIFICATIONS, pool.as_ref()).await?;
+    let ids: Vec<Uuid> = notifications.iter().map(|n| n.id).collect();
+    let mut attempts = get_notification_attempts(&ids, pool.as_ref()).await?;
+    let mut replies = get_notification_replies(&ids, pool.as_ref()).await?;
+
+    let notifications = notifications
+        .into_iter()
+        .map(|notification| {
+            let (delivery_attempts, rest) = attempts
+                .drain(..)
+                .partition(|attempt| attempt.notification_id == notification.id);
+            attempts = rest;
+            let (notification_replies, rest) = replies
+                .drain(..)
+                .partition(|reply| reply.notification_id == notification.id);
+            replies = rest;
+            NotificationWithAttempts {
+                notification,
+                delivery_attempts,
+                replies: notification_replies,
+            }
+        })
+        .collect();
+
+    json_response(&QrCodeNotificationsResponse { notifications })
+}
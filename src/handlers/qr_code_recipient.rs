@@ -0,0 +1,220 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::field::Empty;
+use uuid::Uuid;
+
+use super::ApplicationResponse;
+use crate::{
+    db::{get_owned_qr_code, get_qr_code_recipients, QrCodeRecipient, RecipientChannel},
+    handlers::{json_response, ApplicationError},
+    services::{auth::AuthenticationError, jwt::JwtClient},
+};
+
+/// Largest offset from UTC of any time zone, in minutes.
+pub(crate) const MAX_UTC_OFFSET_MINUTES: i32 = 14 * 60;
+
+fn default_active() -> bool {
+    true
+}
+
+/// Settings shared by recipient creation and edition requests.
+#[derive(Deserialize, Debug)]
+pub struct RecipientFields {
+    pub name: Option<String>,
+    pub channel: RecipientChannel,
+    pub address: String,
+    #[serde(default = "default_active")]
+    pub active: bool,
+    pub quiet_hours_start: Option<NaiveTime>,
+    pub quiet_hours_end: Option<NaiveTime>,
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+    /// Only notify the recipient of alerts that were not acknowledged in time.
+    #[serde(default)]
+    pub escalation: bool,
+}
+
+impl RecipientFields {
+    /// Rejects addresses that do not match the channel and incomplete quiet hours.
+    fn validate(&self) -> Result<(), ApplicationError> {
+        let invalid = |message: &str| Err(ApplicationError::BadRequestError(message.to_string()));
+        if self.channel.is_phone() {
+            let digits = self.address.strip_prefix('+').unwrap_or("");
+            if self.address.len() != 12 || !digits.chars().all(|c| c.is_ascii_digit()) {
+                return invalid("Phone numbers must be formatted as + followed by 11 digits.");
+            }
+        } else if !self.address.contains('@') || self.address.contains(char::is_whitespace) {
+            return invalid("Email recipients need a valid email address.");
+        }
+        if self.quiet_hours_start.is_some() != self.quiet_hours_end.is_some() {
+            return invalid("Quiet hours need both a start and an end.");
+        }
+        if self.utc_offset_minutes.abs() > MAX_UTC_OFFSET_MINUTES {
+            return invalid("utc_offset_minutes must be between -840 and 840.");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct QrCodeRecipientsQuery {
+    pub qr_code_id: Uuid,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ListRecipientsResponse {
+    pub recipients: Vec<QrCodeRecipient>,
+}
+
+#[tracing::instrument(name = "handlers::qr_code_recipient::list", skip(pool, request, jwt), fields(username=Empty, user_id=Empty))]
+/// get(/qr_code/recipients?qr_code_id={ID}) lists the recipients notified when one of the user's QR codes is scanned
+pub async fn list_recipients(
+    pool: web::Data<PgPool>,
+    query: web::Query<QrCodeRecipientsQuery>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+) -> ApplicationResponse {
+    let user = jwt.user_or_403(request).await?;
+    get_owned_qr_code(query.qr_code_id, user.id, pool.as_ref())
+        .await?
+        .ok_or(ApplicationError::AuthError(
+            AuthenticationError::Unauthorized,
+        ))?;
+
+    let recipients = get_qr_code_recipients(query.qr_code_id, pool.as_ref()).await?;
+    json_response(&ListRecipientsResponse { recipients })
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreateRecipientRequest {
+    pub qr_code_id: Uuid,
+    #[serde(flatten)]
+    pub fields: RecipientFields,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CreateRecipientResponse {
+    pub id: Uuid,
+}
+
+#[tracing::instrument(name = "handlers::qr_code_recipient::create", skip(pool, json, request, jwt), fields(username=Empty, user_id=Empty))]
+/// post(/qr_code/recipients) adds a recipient to one of the user's QR codes
+pub async fn create_recipient(
+    pool: web::Data<PgPool>,
+    json: web::Json<CreateRecipientRequest>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+) -> ApplicationResponse {
+    let user = jwt.user_or_403(request).await?;
+    json.fields.validate()?;
+    get_owned_qr_code(json.qr_code_id, user.id, pool.as_ref())
+        .await?
+        .ok_or(ApplicationError::AuthError(
+            AuthenticationError::Unauthorized,
+        ))?;
+
+    let fields = &json.fields;
+    let mut recipient =
+        QrCodeRecipient::new(json.qr_code_id, fields.channel, fields.address.clone());
+    recipient.name = fields.name.clone();
+    recipient.active = fields.active;
+    recipient.quiet_hours_start = fields.quiet_hours_start;
+    recipient.quiet_hours_end = fields.quiet_hours_end;
+    recipient.utc_offset_minutes = fields.utc_offset_minutes;
+    recipient.escalation = fields.escalation;
+    recipient.store(pool.as_ref()).await?;
+
+    json_response(&CreateRecipientResponse { id: recipient.id })
+}
+
+#[derive(Deserialize, Debug)]
+pub struct EditRecipientRequest {
+    pub id: Uuid,
+    #[serde(flatten)]
+    pub fields: RecipientFields,
+}
+
+#[tracing::instrument(name = "handlers::qr_code_recipient::edit", skip(pool, json, request, jwt), fields(username=Empty, user_id=Empty))]
+/// post(/qr_code/recipients/edit) replaces the settings of a recipient of one of the user's QR codes
+pub async fn edit_recipient(
+    pool: web::Data<PgPool>,
+    json: web::Json<EditRecipientRequest>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+) -> ApplicationResponse {
+    let user = jwt.user_or_403(request).await?;
+    json.fields.validate()?;
+
+    let fields = &json.fields;
+    let updated = sqlx::query!(
+        r#"
+            UPDATE qr_code_recipient
+            SET name=$3, channel=$4, address=$5, active=$6,
+                quiet_hours_start=$7, quiet_hours_end=$8, utc_offset_minutes=$9, escalation=$10
+            FROM qr_code
+            WHERE qr_code_recipient.id=$1
+            AND qr_code.id=qr_code_recipient.qr_code_id AND qr_code.account_id=$2
+            RETURNING qr_code_recipient.id
+        "#,
+        json.id,
+        user.id,
+        fields.name,
+        fields.channel.as_str(),
+        fields.address,
+        fields.active,
+        fields.quiet_hours_start,
+        fields.quiet_hours_end,
+        fields.utc_offset_minutes,
+        fields.escalation
+    )
+    .fetch_optional(pool.as_ref())
+    .await?;
+
+    if updated.is_some() {
+        Ok(HttpResponse::Ok().finish())
+    } else {
+        Err(ApplicationError::AuthError(
+            AuthenticationError::Unauthorized,
+        ))
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DeleteRecipientQuery {
+    pub id: Uuid,
+}
+
+#[tracing::instrument(name = "handlers::qr_code_recipient::delete", skip(pool, request, jwt), fields(username=Empty, user_id=Empty))]
+/// get(/qr_code/recipients/delete?id={ID}) removes a recipient from one of the user's QR codes
+pub async fn delete_recipient(
+    pool: web::Data<PgPool>,
+    query: web::Query<DeleteRecipientQuery>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+) -> ApplicationResponse {
+    let user = jwt.user_or_403(request).await?;
+
+    let deleted = sqlx::query!(
+        r#"
+            DELETE FROM qr_code_recipient
+            USING qr_code
+            WHERE qr_code_recipient.id=$1
+            AND qr_code.id=qr_code_recipient.qr_code_id AND qr_code.account_id=$2
+            RETURNING qr_code_recipient.id
+        "#,
+        query.id,
+        user.id
+    )
+    .fetch_optional(pool.as_ref())
+    .await?;
+
+    if deleted.is_some() {
+        Ok(HttpResponse::Ok().finish())
+    } else {
+        Err(ApplicationError::AuthError(
+            AuthenticationError::Unauthorized,
+        ))
+    }
+}
@@ -0,0 +1,87 @@
+use actix_web::{web, HttpRequest};
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::PgPool;
+use tracing::field::Empty;
+use uuid::Uuid;
+
+use super::{json_response, ApplicationResponse};
+use crate::services::jwt::JwtClient;
+use crate::services::trash::{purge_at, TRASH_RETENTION_DAYS};
+
+#[derive(Serialize)]
+pub struct TrashedForm {
+    pub form_id: Uuid,
+    pub title: String,
+    pub deleted_at: NaiveDateTime,
+    /// Time the form and its responses are permanently deleted unless it is restored.
+    pub purge_at: NaiveDateTime,
+}
+
+#[derive(Serialize)]
+pub struct TrashedQrCode {
+    pub qr_code_id: Uuid,
+    pub label: Option<String>,
+    pub deleted_at: NaiveDateTime,
+    /// Time the QR code and its scans are permanently deleted unless it is restored.
+    pub purge_at: NaiveDateTime,
+}
+
+#[derive(Serialize)]
+pub struct TrashResponse {
+    pub retention_days: i64,
+    pub forms: Vec<TrashedForm>,
+    pub qr_codes: Vec<TrashedQrCode>,
+}
+
+#[tracing::instrument(name = "handlers::trash::list", skip(pool, request, jwt), fields(username=Empty, user_id=Empty))]
+/// get(/trash) lists the user's deleted forms and QR codes that can still be restored, most recently deleted first
+pub async fn list_trash(
+    pool: web::Data<PgPool>,
+    request: HttpRequest,
+    jwt: web::Data<JwtClient>,
+) -> ApplicationResponse {
+    let user = jwt.user_or_403(request).await?;
+
+    let forms = sqlx::query!(
+        r#"
+            SELECT id, COALESCE(title, '') AS "title!", deleted_at AS "deleted_at!" FROM form
+            WHERE account_id=$1 AND deleted_at IS NOT NULL
+            ORDER BY deleted_at DESC"#,
+        user.id
+    )
+    .fetch_all(pool.as_ref())
+    .await?
+    .into_iter()
+    .map(|form| TrashedForm {
+        form_id: form.id,
+        title: form.title,
+        deleted_at: form.deleted_at,
+        purge_at: purge_at(form.deleted_at),
+    })
+    .collect();
+
+    let qr_codes = sqlx::query!(
+        r#"
+            SELECT id, label, deleted_at AS "deleted_at!" FROM qr_code
+            WHERE account_id=$1 AND deleted_at IS NOT NULL
+            ORDER BY deleted_at DESC"#,
+        user.id
+    )
+    .fetch_all(pool.as_ref())
+    .await?
+    .into_iter()
+    .map(|qr_code| TrashedQrCode {
+        qr_code_id: qr_code.id,
+        label: qr_code.label,
+        deleted_at: qr_code.deleted_at,
+        purge_at: purge_at(qr_code.deleted_at),
+    })
+    .collect();
+
+    json_response(&TrashResponse {
+        retention_days: TRASH_RETENTION_DAYS,
+        forms,
+        qr_codes,
+    })
+}
@@ -0,0 +1,184 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{
+    alert::{acknowledge_notification_alert, acknowledgement_message, AcknowledgeAlertQuery},
+    ApplicationResponse,
+};
+use crate::{
+    clients::twilio::{
+        twiml::{Say, Twiml},
+        TwilioClient,
+    },
+    db::{
+        acknowledge_alert, get_latest_notification_for_recipient,
+        get_latest_unacknowledged_alert_for_recipient, record_provider_status, NotificationChannel,
+        NotificationReply,
+    },
+    handlers::ApplicationError,
+    services::{alert::is_acknowledgement, auth::AuthenticationError},
+    startup::ApplicationBaseUrl,
+};
+
+/// Form-encoded parameters of a webhook request, in the order Twilio sent them.
+type WebhookParams = web::Form<Vec<(String, String)>>;
+
+/// Rejects requests that do not carry a valid `X-Twilio-Signature`. Twilio signs the public URL
+/// it posted to, which is rebuilt from the application's base URL since the server may sit behind a proxy.
+fn verify_signature(
+    request: &HttpRequest,
+    params: &[(String, String)],
+    base_url: &ApplicationBaseUrl,
+    twilio: &TwilioClient,
+) -> Result<(), ApplicationError> {
+    let signature = request
+        .headers()
+        .get("X-Twilio-Signature")
+        .and_then(|value| value.to_str().ok())
+        .ok_or(ApplicationError::AuthError(
+            AuthenticationError::InvalidHeaders,
+        ))?;
+    let path = request
+        .uri()
+        .path_and_query()
+        .map(|path| path.as_str())
+        .unwrap_or_else(|| request.path());
+    let url = format!("{}{}", base_url.0.trim_end_matches('/'), path);
+    if twilio.is_valid_signature(&url, params, signature) {
+        Ok(())
+    } else {
+        tracing::warn!("Rejected a Twilio webhook with an invalid signature");
+        Err(ApplicationError::AuthError(
+            AuthenticationError::InvalidHeaders,
+        ))
+    }
+}
+
+/// Returns the value of the parameter `name`, if Twilio sent it.
+fn param<'a>(params: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    params
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.as_str())
+}
+
+fn required_param<'a>(
+    params: &'a [(String, String)],
+    name: &str,
+) -> Result<&'a str, ApplicationError> {
+    param(params, name)
+        .ok_or_else(|| ApplicationError::BadRequestError(format!("{} is missing.", name)))
+}
+
+fn twiml_response(twiml: Twiml) -> ApplicationResponse {
+    Ok(HttpResponse::Ok()
+        .content_type("text/xml")
+        .body(twiml.to_xml()))
+}
+
+#[tracing::instrument(
+    name = "handlers::twilio_webhook::sms",
+    skip(pool, params, request, base_url, twilio)
+)]
+/// post(/webhooks/twilio/sms) receives SMS replies from Twilio. Replies are recorded against the latest
+/// notification sent to the sender, and keywords such as `ACK` acknowledge the latest alert sent to them
+pub async fn twilio_sms(
+    pool: web::Data<PgPool>,
+    params: WebhookParams,
+    request: HttpRequest,
+    base_url: web::Data<ApplicationBaseUrl>,
+    twilio: web::Data<TwilioClient>,
+) -> ApplicationResponse {
+    verify_signature(&request, &params, &base_url, &twilio)?;
+    let from = required_param(&params, "From")?;
+    let body = param(&params, "Body").unwrap_or_default();
+
+    let phone_channels = [NotificationChannel::Sms, NotificationChannel::Call];
+    if let Some(notification) =
+        get_latest_notification_for_recipient(from, &phone_channels, pool.as_ref()).await?
+    {
+        NotificationReply::new(
+            notification.id,
+            from.to_string(),
+            body.to_string(),
+            param(&params, "MessageSid").map(|sid| sid.to_string()),
+        )
+        .store(pool.as_ref())
+        .await?;
+    }
+
+    let reply = if !is_acknowledgement(body) {
+        "Reply ACK to acknowledge the latest alert."
+    } else {
+        match get_latest_unacknowledged_alert_for_recipient(from, pool.as_ref()).await? {
+            Some(alert) => {
+                acknowledgement_message(acknowledge_alert(alert.id, from, pool.as_ref()).await?)
+            }
+            None => "There is no alert to acknowledge.",
+        }
+    };
+    twiml_response(Twiml::new().message(reply))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct VoiceStatusQuery {
+    pub notification_id: Uuid,
+}
+
+#[tracing::instrument(
+    name = "handlers::twilio_webhook::voice_status",
+    skip(pool, params, request, base_url, twilio)
+)]
+/// post(/webhooks/twilio/voice-status?notification_id={ID}) records the outcome Twilio reports for the call of a notification
+pub async fn twilio_voice_status(
+    pool: web::Data<PgPool>,
+    query: web::Query<VoiceStatusQuery>,
+    params: WebhookParams,
+    request: HttpRequest,
+    base_url: web::Data<ApplicationBaseUrl>,
+    twilio: web::Data<TwilioClient>,
+) -> ApplicationResponse {
+    verify_signature(&request, &params, &base_url, &twilio)?;
+    let status = required_param(&params, "CallStatus")?;
+
+    let recorded = record_provider_status(
+        query.notification_id,
+        param(&params, "CallSid"),
+        status,
+        pool.as_ref(),
+    )
+    .await?;
+    if recorded {
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Err(ApplicationError::NotFoundError(
+            "Notification not found".to_string(),
+        ))
+    }
+}
+
+#[tracing::instrument(
+    name = "handlers::twilio_webhook::gather",
+    skip(pool, params, request, base_url, twilio)
+)]
+/// post(/webhooks/twilio/gather?token={ID}) receives the key pressed during a call notification from Twilio,
+/// acknowledging the alert when it is 1
+pub async fn twilio_gather(
+    pool: web::Data<PgPool>,
+    query: web::Query<AcknowledgeAlertQuery>,
+    params: WebhookParams,
+    request: HttpRequest,
+    base_url: web::Data<ApplicationBaseUrl>,
+    twilio: web::Data<TwilioClient>,
+) -> ApplicationResponse {
+    verify_signature(&request, &params, &base_url, &twilio)?;
+
+    let message = if param(&params, "Digits") == Some("1") {
+        acknowledgement_message(acknowledge_notification_alert(query.token, pool.as_ref()).await?)
+    } else {
+        "The alert was not acknowledged."
+    };
+    twiml_response(Twiml::new().say(Say::new(message)))
+}
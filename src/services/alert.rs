@@ -0,0 +1,201 @@
+//! Contains the acknowledge-and-escalate workflow of scan alerts.
+use chrono::Utc;
+use sqlx::PgPool;
+use tracing::field::{display, Empty};
+use uuid::Uuid;
+
+use crate::db::{
+    cancel_open_alerts, get_qr_code_recipients, Alert, AlertStatus, Notification,
+    NotificationChannel, QrCode,
+};
+use crate::services::notification_worker::ExecutionOutcome;
+use crate::services::template::{escape, RenderedMessage};
+
+/// Words that acknowledge the latest alert when they are replied to an SMS notification.
+const ACKNOWLEDGE_KEYWORDS: &[&str] = &["ack", "ok", "yes", "1"];
+
+/// Link to the page acknowledging the alert of the notification with the given id, sent in emails.
+pub fn acknowledge_url(base_url: &str, notification_id: Uuid) -> String {
+    format!(
+        "{}/alert/acknowledge?token={}",
+        base_url.trim_end_matches('/'),
+        notification_id
+    )
+}
+
+/// URL Twilio reports the key pressed during the call of the notification with the given id to.
+pub fn call_acknowledge_url(base_url: &str, notification_id: Uuid) -> String {
+    format!(
+        "{}/webhooks/twilio/gather?token={}",
+        base_url.trim_end_matches('/'),
+        notification_id
+    )
+}
+
+/// Whether an SMS reply acknowledges an alert, e.g. `ACK` or `ok`.
+pub fn is_acknowledgement(body: &str) -> bool {
+    let body = body.trim().trim_end_matches(|c: char| c == '.' || c == '!');
+    ACKNOWLEDGE_KEYWORDS
+        .iter()
+        .any(|keyword| body.eq_ignore_ascii_case(keyword))
+}
+
+/// Builds the notification of `alert` for a recipient, telling them how to acknowledge it
+/// through the notification's channel. `subject` is only used by emails.
+pub fn alert_notification(
+    qr_code: &QrCode,
+    alert: &Alert,
+    channel: NotificationChannel,
+    recipient: String,
+    message: &RenderedMessage,
+    subject: &str,
+    base_url: &str,
+) -> Notification {
+    let mut notification = Notification::new(
+        qr_code.id,
+        alert.scan_event_id,
+        channel,
+        recipient,
+        String::new(),
+    );
+    notification.alert_id = Some(alert.id);
+    match channel {
+        NotificationChannel::Call => {
+            let url = call_acknowledge_url(base_url, notification.id);
+            notification.message = qr_code.call_twiml(&message.text, &url);
+        }
+        NotificationChannel::Sms => {
+            notification.message = format!("{}\nReply ACK to acknowledge.", message.text);
+        }
+        NotificationChannel::Email => {
+            let url = acknowledge_url(base_url, notification.id);
+            notification.subject = Some(subject.to_string());
+            notification.message = format!("{}\n\nAcknowledge this alert: {}", message.text, url);
+            notification.html_message = Some(format!(
+                r#"{}<p><a href="{}">Acknowledge this alert</a></p>"#,
+                message.html,
+                escape(&url)
+            ));
+        }
+    }
+    notification
+}
+
+/// Escalates every open alert whose escalation timeout has passed.
+pub async fn escalate_overdue_alerts(pool: &PgPool, base_url: &str) -> Result<(), anyhow::Error> {
+    while try_escalate_alert(pool, base_url).await? == ExecutionOutcome::TaskCompleted {}
+    Ok(())
+}
+
+/// Sends the next overdue alert to its QR code's escalation recipients that are active and
+/// outside their quiet hours. The alert's row stays locked until it is marked as escalated,
+/// so several instances can escalate alerts concurrently.
+#[tracing::instrument(
+    name = "services::alert::try_escalate_alert",
+    skip(pool, base_url),
+    fields(alert_id=Empty)
+)]
+pub async fn try_escalate_alert(
+    pool: &PgPool,
+    base_url: &str,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    let now = Utc::now().naive_utc();
+    let mut tx = pool.begin().await?;
+    let alert = sqlx::query_as!(
+        Alert,
+        r#"
+            SELECT * FROM alert
+            WHERE status = $1 AND escalate_at <= $2
+            ORDER BY escalate_at
+            FOR UPDATE SKIP LOCKED
+            LIMIT 1"#,
+        AlertStatus::Open.as_str(),
+        now
+    )
+    .fetch_optional(&mut tx)
+    .await?;
+    let alert = match alert {
+        Some(alert) => alert,
+        None => return Ok(ExecutionOutcome::EmptyQueue),
+    };
+    tracing::Span::current().record("alert_id", &display(alert.id));
+
+    let qr_code = sqlx::query_as!(
+        QrCode,
+        "SELECT * FROM qr_code WHERE id = $1 AND deleted_at IS NULL",
+        alert.qr_code_id
+    )
+    .fetch_optional(&mut tx)
+    .await?;
+    let qr_code = match qr_code {
+        Some(qr_code) => qr_code,
+        None => {
+            // The QR code is in the trash, so nobody is expected to attend to the alert
+            cancel_open_alerts(alert.qr_code_id, &mut tx).await?;
+            tx.commit().await?;
+            return Ok(ExecutionOutcome::TaskCompleted);
+        }
+    };
+    let message = RenderedMessage::from_text(format!("Unacknowledged alert: {}", alert.message));
+    let subject = format!("Unacknowledged alert: {}", qr_code.caption());
+    for recipient in get_qr_code_recipients(qr_code.id, &mut tx).await? {
+        if recipient.escalation && recipient.active && !recipient.is_quiet_at(now) {
+            for channel in recipient.recipient_channel()?.channels() {
+                alert_notification(
+                    &qr_code,
+                    &alert,
+                    *channel,
+                    recipient.address.clone(),
+                    &message,
+                    &subject,
+                    base_url,
+                )
+                .store(&mut tx)
+                .await?;
+            }
+        }
+    }
+
+    sqlx::query!(
+        "UPDATE alert SET status = $2, escalated_at = $3 WHERE id = $1",
+        alert.id,
+        AlertStatus::Escalated.as_str(),
+        now
+    )
+    .execute(&mut tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(ExecutionOutcome::TaskCompleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::{acknowledge_url, is_acknowledgement};
+
+    #[test]
+    fn acknowledgement_keywords_are_case_insensitive() {
+        assert!(is_acknowledgement("ACK"));
+        assert!(is_acknowledgement(" ok!\n"));
+        assert!(is_acknowledgement("Yes."));
+        assert!(is_acknowledgement("1"));
+    }
+
+    #[test]
+    fn other_replies_are_not_acknowledgements() {
+        assert!(!is_acknowledgement(""));
+        assert!(!is_acknowledgement("who is this?"));
+        assert!(!is_acknowledgement("acknowledged later"));
+    }
+
+    #[test]
+    fn acknowledge_url_does_not_duplicate_slashes() {
+        let id = Uuid::new_v4();
+        assert_eq!(
+            format!("https://hermodapp.com/alert/acknowledge?token={}", id),
+            acknowledge_url("https://hermodapp.com/", id)
+        );
+    }
+}
@@ -0,0 +1,120 @@
+//! Contains helpers for recording QR code scans without storing identifying client data.
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use sha2::{Digest, Sha256};
+
+/// Parses an address from a connection or a forwarding header, which may or may not include a port.
+pub fn parse_client_ip(address: &str) -> Option<IpAddr> {
+    address
+        .parse::<SocketAddr>()
+        .map(|socket| socket.ip())
+        .or_else(|_| address.parse::<IpAddr>())
+        .ok()
+}
+
+/// Returns the address of the client behind a request received from `peer`.
+/// `X-Forwarded-For` is only honoured when `peer` is one of the `trusted_proxies`, and is read
+/// right to left so that addresses the client prepended itself are never used.
+pub fn client_ip(peer: IpAddr, forwarded_for: Option<&str>, trusted_proxies: &[IpAddr]) -> IpAddr {
+    let mut client = peer;
+    if !trusted_proxies.contains(&peer) {
+        return client;
+    }
+    for hop in forwarded_for.unwrap_or_default().rsplit(',') {
+        match parse_client_ip(hop.trim()) {
+            Some(ip) => client = ip,
+            None => break,
+        }
+        if !trusted_proxies.contains(&client) {
+            break;
+        }
+    }
+    client
+}
+
+/// Truncates an address to its network, /24 for IPv4 and /48 for IPv6,
+/// so that scanners can be told apart without pinpointing a single device.
+pub fn coarse_ip(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            IpAddr::V4(Ipv4Addr::new(a, b, c, 0))
+        }
+        IpAddr::V6(ip) => {
+            let segments = ip.segments();
+            IpAddr::V6(Ipv6Addr::new(
+                segments[0],
+                segments[1],
+                segments[2],
+                0,
+                0,
+                0,
+                0,
+                0,
+            ))
+        }
+    }
+}
+
+/// Returns a salted hash of the client's coarse network, used to count unique scanners.
+pub fn client_hash(salt: &str, ip: IpAddr) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(salt.as_bytes());
+    hasher.update(coarse_ip(ip).to_string().as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use super::{client_hash, client_ip, coarse_ip, parse_client_ip};
+
+    #[test]
+    fn parse_client_ip_accepts_addresses_with_and_without_ports() {
+        let expected: IpAddr = "10.1.2.3".parse().unwrap();
+        assert_eq!(Some(expected), parse_client_ip("10.1.2.3"));
+        assert_eq!(Some(expected), parse_client_ip("10.1.2.3:4567"));
+        assert_eq!(None, parse_client_ip("not an address"));
+    }
+
+    #[test]
+    fn client_ip_only_trusts_forwarding_headers_from_trusted_proxies() {
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+        let client: IpAddr = "203.0.113.7".parse().unwrap();
+        let spoofed = "198.51.100.1, 203.0.113.7";
+
+        assert_eq!(proxy, client_ip(proxy, Some(spoofed), &[]));
+        assert_eq!(client, client_ip(proxy, Some(spoofed), &[proxy]));
+        assert_eq!(
+            client,
+            client_ip(proxy, Some("203.0.113.7, 10.0.0.1"), &[proxy])
+        );
+        assert_eq!(proxy, client_ip(proxy, None, &[proxy]));
+        assert_eq!(proxy, client_ip(proxy, Some("unknown"), &[proxy]));
+    }
+
+    #[test]
+    fn coarse_ip_drops_host_bits() {
+        assert_eq!(
+            "10.1.2.0".parse::<IpAddr>().unwrap(),
+            coarse_ip("10.1.2.3".parse().unwrap())
+        );
+        assert_eq!(
+            "2001:db8:1::".parse::<IpAddr>().unwrap(),
+            coarse_ip("2001:db8:1:2:3:4:5:6".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn client_hash_is_shared_within_a_network_and_salted() {
+        let a = client_hash("salt", "10.1.2.3".parse().unwrap());
+        let b = client_hash("salt", "10.1.2.200".parse().unwrap());
+        let c = client_hash("salt", "10.1.3.3".parse().unwrap());
+        let d = client_hash("pepper", "10.1.2.3".parse().unwrap());
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_ne!(a, d);
+        assert_eq!(64, a.len());
+    }
+}
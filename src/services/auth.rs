@@ -5,6 +5,7 @@ use actix_web::HttpRequest;
 use anyhow::Context;
 use argon2::{Argon2, PasswordHash, PasswordVerifier};
 use sqlx::PgPool;
+use subtle::ConstantTimeEq;
 
 use crate::{db::User, handlers::ApplicationError};
 
@@ -20,6 +21,24 @@ pub async fn validate_request_with_basic_auth(
     Ok(user)
 }
 
+/// Validates a HTTP request made by a service that authenticates with a single, configured
+/// pair of Basic Auth credentials, such as a webhook. Both credentials are compared in constant time.
+pub fn validate_request_with_static_basic_auth(
+    request: &HttpRequest,
+    username: &str,
+    password: &str,
+) -> Result<(), AuthenticationError> {
+    let credentials =
+        extract_from_headers(request.headers()).map_err(|_| AuthenticationError::InvalidHeaders)?;
+    let username_matches = credentials.username.as_bytes().ct_eq(username.as_bytes());
+    let password_matches = credentials.password.as_bytes().ct_eq(password.as_bytes());
+    if bool::from(username_matches & password_matches) {
+        Ok(())
+    } else {
+        Err(AuthenticationError::InvalidCredentials)
+    }
+}
+
 #[tracing::instrument(name = "services::auth::validate_credentials", skip(credentials, pool), fields(
     username=%credentials.username,
 ))]
@@ -1,7 +1,13 @@
 //! Contains structs and helpers related to server configuration.
 use serde_aux::field_attributes::deserialize_number_from_string;
+
+use crate::clients::{email::EmailTemplates, smtp::SmtpTls};
 use sqlx::postgres::{PgConnectOptions, PgSslMode};
 use std::convert::{TryFrom, TryInto};
+use std::net::IpAddr;
+
+/// Value of the secrets in `base.yaml`, which must be overridden outside the local environment.
+const PLACEHOLDER_SECRET: &str = "DO_NOT_USE";
 
 /// Top-level configuration struct.
 #[derive(serde::Deserialize, Clone)]
@@ -10,6 +16,30 @@ pub struct Settings {
     pub application: ApplicationSettings,
     pub twilio: TwilioSettings,
     pub postmark: PostmarkSettings,
+    #[serde(default)]
+    pub delivery: DeliverySettings,
+    /// Required by the `smtp` email backend.
+    pub smtp: Option<SmtpSettings>,
+}
+
+impl Settings {
+    /// Names of the secrets that are still the placeholders from `base.yaml`. Each of them
+    /// is publicly known, so webhooks could be forged or scanner hashes reversed with it.
+    pub fn placeholder_secrets(&self) -> Vec<&'static str> {
+        [
+            (
+                "application.scan_hash_salt",
+                &self.application.scan_hash_salt,
+            ),
+            ("twilio.auth_token", &self.twilio.auth_token),
+            ("postmark.webhook_username", &self.postmark.webhook_username),
+            ("postmark.webhook_password", &self.postmark.webhook_password),
+        ]
+        .iter()
+        .filter(|(_, value)| value.as_str() == PLACEHOLDER_SECRET)
+        .map(|(name, _)| *name)
+        .collect()
+    }
 }
 
 /// Contains settings relevant at the application level.
@@ -20,6 +50,11 @@ pub struct ApplicationSettings {
     pub host: String,
     pub base_url: String,
     pub jwt_signing_key: String,
+    /// Secret mixed into client IP hashes recorded with scan events.
+    pub scan_hash_salt: String,
+    /// Reverse proxies whose `X-Forwarded-For` headers are trusted to report scanners' addresses.
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpAddr>,
     pub honeycomb_url: String,
 }
 
@@ -37,6 +72,63 @@ pub struct PostmarkSettings {
     pub base_url: String,
     pub server_auth_token: String,
     pub from: String,
+    /// Basic Auth credentials Postmark's webhooks are configured to send.
+    pub webhook_username: String,
+    pub webhook_password: String,
+    #[serde(default)]
+    pub templates: EmailTemplates,
+}
+
+/// Selects the backends emails, SMS messages and calls are sent through.
+#[derive(serde::Deserialize, Clone, Default)]
+pub struct DeliverySettings {
+    #[serde(default)]
+    pub email: EmailBackend,
+    #[serde(default)]
+    pub phone: PhoneBackend,
+    /// Directory the `local` backends write messages to, standard output when unset.
+    pub local_directory: Option<String>,
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailBackend {
+    Postmark,
+    Smtp,
+    /// Writes emails to `DeliverySettings::local_directory` instead of sending them.
+    Local,
+}
+
+impl Default for EmailBackend {
+    fn default() -> Self {
+        Self::Postmark
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PhoneBackend {
+    Twilio,
+    /// Writes SMS messages and calls to `DeliverySettings::local_directory` instead of sending them.
+    Local,
+}
+
+impl Default for PhoneBackend {
+    fn default() -> Self {
+        Self::Twilio
+    }
+}
+
+/// Contains settings for sending emails through an SMTP server.
+#[derive(serde::Deserialize, Clone)]
+pub struct SmtpSettings {
+    pub host: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub port: u16,
+    pub tls: SmtpTls,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
 }
 
 /// Contains settings for the database connection.
@@ -96,7 +188,15 @@ pub fn get_configuration() -> Result<Settings, config::ConfigError> {
     // E.g. `APP_APPLICATION__PORT=5001 would set `Settings.application.port`
     settings.merge(config::Environment::with_prefix("app").separator("__"))?;
 
-    settings.try_into()
+    let settings: Settings = settings.try_into()?;
+    let placeholders = settings.placeholder_secrets();
+    if !matches!(environment, Environment::Local) && !placeholders.is_empty() {
+        return Err(config::ConfigError::Message(format!(
+            "{} must be configured outside the local environment.",
+            placeholders.join(", ")
+        )));
+    }
+    Ok(settings)
 }
 
 /// The possible runtime environment for our application.
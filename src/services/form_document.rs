@@ -0,0 +1,143 @@
+//! Contains the portable JSON document forms are exported to and imported from, so that they can
+//! be kept under version control and moved between accounts. The format is described by the JSON
+//! Schema in `schemas/form_document.schema.json`.
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::FormStatus;
+use crate::services::form_template::FormDefinition;
+
+/// Version of the document format written by exports. Imports reject any other version.
+pub const FORM_DOCUMENT_VERSION: u32 = 1;
+
+/// JSON Schema describing version 1 documents.
+pub const FORM_DOCUMENT_SCHEMA: &str = include_str!("../../schemas/form_document.schema.json");
+
+/// A form exported as a portable document. Any body accepted by `/form/new` is a valid document
+/// once a `version` is added to it, and fields and sections may be given keys for rules to use.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FormDocument {
+    /// URL of the JSON Schema the document follows, ignored on import.
+    #[serde(rename = "$schema", default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    pub version: u32,
+    /// Status of the imported form, left out of exports so that imported forms are published.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<FormStatus>,
+    #[serde(flatten)]
+    pub form: FormDefinition,
+}
+
+impl FormDocument {
+    /// Documents a form, replacing the keys of its fields and sections with ones that only depend
+    /// on their order, so that exporting the same form from two accounts gives the same document.
+    pub fn export(mut form: FormDefinition, schema: Option<String>) -> Self {
+        let mut keys = HashMap::new();
+        let mut rekey = |key: &mut Option<String>, prefix: &str, number: usize| {
+            let new_key = format!("{}_{}", prefix, number);
+            if let Some(old_key) = key.replace(new_key.clone()) {
+                keys.insert(old_key, new_key);
+            }
+        };
+        let mut field_number = 0;
+        for field in form.fields.iter_mut() {
+            field_number += 1;
+            rekey(&mut field.key, "field", field_number);
+        }
+        for (index, section) in form.sections.iter_mut().enumerate() {
+            rekey(&mut section.key, "section", index + 1);
+            for field in section.fields.iter_mut() {
+                field_number += 1;
+                rekey(&mut field.key, "field", field_number);
+            }
+        }
+
+        let renamed = |key: &mut String| {
+            if let Some(new_key) = keys.get(key.as_str()) {
+                *key = new_key.clone();
+            }
+        };
+        for rule in form.rules.iter_mut() {
+            rule.target_field.iter_mut().for_each(renamed);
+            rule.target_section.iter_mut().for_each(renamed);
+            for condition in rule.conditions.iter_mut() {
+                renamed(&mut condition.field);
+            }
+        }
+
+        Self {
+            schema,
+            version: FORM_DOCUMENT_VERSION,
+            status: None,
+            form,
+        }
+    }
+
+    /// Rejects documents of a version this version of the API cannot import.
+    pub fn check_version(&self) -> Result<(), String> {
+        if self.version != FORM_DOCUMENT_VERSION {
+            return Err(format!(
+                "Version {} form documents are not supported. Use version {}.",
+                self.version, FORM_DOCUMENT_VERSION
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::services::form_template::BUILT_IN_TEMPLATES;
+
+    #[test]
+    fn schema_is_valid_json() {
+        let schema: serde_json::Value = serde_json::from_str(FORM_DOCUMENT_SCHEMA).unwrap();
+        assert_eq!(
+            serde_json::json!(FORM_DOCUMENT_VERSION),
+            schema["properties"]["version"]["const"]
+        );
+    }
+
+    #[test]
+    fn exports_key_fields_and_sections_by_their_order() {
+        let form = BUILT_IN_TEMPLATES[1].definition().unwrap();
+        let document = FormDocument::export(form, None);
+
+        assert_eq!(Some("field_1".to_string()), document.form.fields[0].key);
+        assert_eq!(Some("section_2".to_string()), document.form.sections[1].key);
+        assert_eq!(
+            Some("field_6".to_string()),
+            document.form.rules[0].target_field
+        );
+        assert_eq!("field_3", document.form.rules[0].conditions[0].field);
+        assert!(document.check_version().is_ok());
+        assert!(document.form.instantiate(Uuid::new_v4()).is_ok());
+    }
+
+    #[test]
+    fn documents_round_trip_through_json() {
+        let form = BUILT_IN_TEMPLATES[2].definition().unwrap();
+        let document = FormDocument::export(form, Some("https://example.com/schema".to_string()));
+        let json = serde_json::to_value(&document).unwrap();
+        assert_eq!(1, json["version"]);
+        assert_eq!("https://example.com/schema", json["$schema"]);
+
+        let imported: FormDocument = serde_json::from_value(json).unwrap();
+        assert_eq!(document, imported);
+    }
+
+    #[test]
+    fn other_versions_are_rejected() {
+        let form = BUILT_IN_TEMPLATES[0].definition().unwrap();
+        let mut document = FormDocument::export(form, None);
+        document.version = 2;
+        assert_eq!(
+            Err("Version 2 form documents are not supported. Use version 1.".to_string()),
+            document.check_version()
+        );
+    }
+}
@@ -0,0 +1,138 @@
+//! Contains how a form's fields are ordered, grouped into sections and split into pages.
+use uuid::Uuid;
+
+/// Where a field sits in a form: the section it is grouped in, if any.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Placement {
+    pub field_id: Uuid,
+    pub section_id: Option<Uuid>,
+}
+
+impl Placement {
+    pub fn new(field_id: Uuid, section_id: Option<Uuid>) -> Self {
+        Self {
+            field_id,
+            section_id,
+        }
+    }
+}
+
+/// Orders the fields of a form after an edit, returning them in their new order.
+///
+/// Fields outside any section come first, followed by the fields of each of `sections` in turn.
+/// Within each of these groups, the fields an edit `listed` keep the order they were listed in
+/// and are followed by the group's `existing` fields the edit left out, in their previous order.
+/// Existing fields whose section is not among `sections` are moved out of it.
+pub fn arrange(sections: &[Uuid], listed: &[Placement], existing: &[Placement]) -> Vec<Placement> {
+    let unlisted = existing
+        .iter()
+        .filter(|field| !listed.iter().any(|l| l.field_id == field.field_id))
+        .map(|field| match field.section_id {
+            Some(section_id) if sections.contains(&section_id) => *field,
+            _ => Placement::new(field.field_id, None),
+        });
+    let fields: Vec<Placement> = listed.iter().copied().chain(unlisted).collect();
+
+    let mut arranged = Vec::with_capacity(fields.len());
+    for group in std::iter::once(None).chain(sections.iter().copied().map(Some)) {
+        arranged.extend(fields.iter().filter(|field| field.section_id == group));
+    }
+    arranged
+}
+
+/// Numbers the pages a form's sections are on, starting from 1.
+///
+/// Each section that asks for a page break starts a new page, unless nothing comes before it
+/// on the current page. `leading_fields` is whether the form has fields outside any section,
+/// which come before its sections on the first page.
+pub fn paginate(leading_fields: bool, page_breaks: &[bool]) -> Vec<i32> {
+    let mut page = 1;
+    let mut page_is_empty = !leading_fields;
+    page_breaks
+        .iter()
+        .map(|&page_break| {
+            if page_break && !page_is_empty {
+                page += 1;
+            }
+            page_is_empty = false;
+            page
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::{arrange, paginate, Placement};
+
+    #[test]
+    fn listed_fields_come_first_in_their_section() {
+        let section = Uuid::new_v4();
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let existing = [
+            Placement::new(a, None),
+            Placement::new(b, Some(section)),
+            Placement::new(c, Some(section)),
+        ];
+
+        let arranged = arrange(&[section], &[Placement::new(c, Some(section))], &existing);
+        assert_eq!(
+            vec![
+                Placement::new(a, None),
+                Placement::new(c, Some(section)),
+                Placement::new(b, Some(section)),
+            ],
+            arranged
+        );
+    }
+
+    #[test]
+    fn fields_are_grouped_by_section_in_section_order() {
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let listed = [
+            Placement::new(a, Some(second)),
+            Placement::new(b, Some(first)),
+            Placement::new(c, None),
+        ];
+
+        let arranged = arrange(&[first, second], &listed, &[]);
+        assert_eq!(
+            vec![
+                Placement::new(c, None),
+                Placement::new(b, Some(first)),
+                Placement::new(a, Some(second)),
+            ],
+            arranged
+        );
+    }
+
+    #[test]
+    fn fields_of_removed_sections_are_moved_out_of_them() {
+        let removed = Uuid::new_v4();
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let existing = [Placement::new(a, None), Placement::new(b, Some(removed))];
+
+        let arranged = arrange(&[], &[], &existing);
+        assert_eq!(
+            vec![Placement::new(a, None), Placement::new(b, None)],
+            arranged
+        );
+    }
+
+    #[test]
+    fn page_breaks_start_new_pages() {
+        assert_eq!(
+            vec![1, 1, 2, 3],
+            paginate(true, &[false, false, true, true])
+        );
+        assert_eq!(vec![1, 2], paginate(true, &[true, true]));
+    }
+
+    #[test]
+    fn a_page_break_before_anything_else_does_not_leave_an_empty_page() {
+        assert_eq!(vec![1, 2], paginate(false, &[true, true]));
+        assert!(paginate(false, &[]).is_empty());
+    }
+}
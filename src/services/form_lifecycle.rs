@@ -0,0 +1,140 @@
+//! Contains when a form accepts responses, given its status, open/close window and response cap.
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+use crate::db::FormStatus;
+
+/// The settings that decide whether a form accepts responses.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct Lifecycle {
+    pub status: FormStatus,
+    pub opens_at: Option<NaiveDateTime>,
+    pub closes_at: Option<NaiveDateTime>,
+    pub max_responses: Option<i32>,
+}
+
+impl Lifecycle {
+    /// Returns why the form does not accept a response at `now` after receiving `responses`,
+    /// or `None` if it does.
+    pub fn closed_reason(&self, now: NaiveDateTime, responses: i64) -> Option<String> {
+        match self.status {
+            FormStatus::Draft => return Some("This form has not been published yet.".to_string()),
+            FormStatus::Closed | FormStatus::Archived => {
+                return Some("This form is closed.".to_string())
+            }
+            FormStatus::Published => {}
+        }
+        if let Some(opens_at) = self.opens_at.filter(|&opens_at| now < opens_at) {
+            return Some(format!(
+                "This form opens at {} UTC.",
+                opens_at.format("%Y-%m-%d %H:%M")
+            ));
+        }
+        if let Some(closes_at) = self.closes_at.filter(|&closes_at| now >= closes_at) {
+            return Some(format!(
+                "This form closed at {} UTC.",
+                closes_at.format("%Y-%m-%d %H:%M")
+            ));
+        }
+        match self.max_responses {
+            Some(max) if responses >= i64::from(max) => {
+                Some("This form is no longer accepting responses.".to_string())
+            }
+            _ => None,
+        }
+    }
+
+    /// Checks that the window opens before it closes and that any response cap is positive.
+    pub fn validate(&self) -> Result<(), String> {
+        if let (Some(opens_at), Some(closes_at)) = (self.opens_at, self.closes_at) {
+            if opens_at >= closes_at {
+                return Err("A form must open before it closes.".to_string());
+            }
+        }
+        if matches!(self.max_responses, Some(max) if max < 1) {
+            return Err("A form must accept at least one response.".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn at(hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd(2021, 12, 22).and_hms(hour, 0, 0)
+    }
+
+    fn published() -> Lifecycle {
+        Lifecycle {
+            status: FormStatus::Published,
+            opens_at: None,
+            closes_at: None,
+            max_responses: None,
+        }
+    }
+
+    #[test]
+    fn published_forms_without_a_window_are_open() {
+        assert_eq!(None, published().closed_reason(at(12), 1000));
+    }
+
+    #[test]
+    fn only_published_forms_are_open() {
+        for status in [FormStatus::Draft, FormStatus::Closed, FormStatus::Archived] {
+            let lifecycle = Lifecycle {
+                status,
+                ..published()
+            };
+            assert!(lifecycle.closed_reason(at(12), 0).is_some());
+        }
+    }
+
+    #[test]
+    fn forms_are_open_within_their_window() {
+        let lifecycle = Lifecycle {
+            opens_at: Some(at(9)),
+            closes_at: Some(at(17)),
+            ..published()
+        };
+        assert_eq!(
+            Some("This form opens at 2021-12-22 09:00 UTC.".to_string()),
+            lifecycle.closed_reason(at(8), 0)
+        );
+        assert_eq!(None, lifecycle.closed_reason(at(9), 0));
+        assert_eq!(
+            Some("This form closed at 2021-12-22 17:00 UTC.".to_string()),
+            lifecycle.closed_reason(at(17), 0)
+        );
+    }
+
+    #[test]
+    fn forms_close_once_they_reach_their_cap() {
+        let lifecycle = Lifecycle {
+            max_responses: Some(2),
+            ..published()
+        };
+        assert_eq!(None, lifecycle.closed_reason(at(12), 1));
+        assert!(lifecycle.closed_reason(at(12), 2).is_some());
+    }
+
+    #[test]
+    fn windows_must_open_before_they_close() {
+        let lifecycle = Lifecycle {
+            opens_at: Some(at(17)),
+            closes_at: Some(at(9)),
+            ..published()
+        };
+        assert!(lifecycle.validate().is_err());
+        assert!(Lifecycle {
+            max_responses: Some(0),
+            ..published()
+        }
+        .validate()
+        .is_err());
+        assert!(published().validate().is_ok());
+    }
+}
@@ -0,0 +1,353 @@
+//! Contains the rules that show, hide and skip a form's fields depending on earlier answers.
+use std::collections::{HashMap, HashSet};
+
+use uuid::Uuid;
+
+use crate::db::{Condition, ConditionOperator, RuleAction};
+use crate::services::form_layout::Placement;
+
+/// A rule of a form, as needed to check and apply it.
+#[derive(Debug, Clone)]
+pub struct RuleSchema {
+    pub action: RuleAction,
+    pub target_field_id: Option<Uuid>,
+    pub target_section_id: Option<Uuid>,
+    pub match_any: bool,
+    pub conditions: Vec<Condition>,
+}
+
+impl RuleSchema {
+    /// Whether the rule's conditions are met by the given answers.
+    fn is_met(&self, answers: &HashMap<Uuid, &str>) -> bool {
+        let mut results = self
+            .conditions
+            .iter()
+            .map(|condition| holds(condition, answers.get(&condition.field_id).copied()));
+        if self.match_any {
+            results.any(|met| met)
+        } else {
+            results.all(|met| met)
+        }
+    }
+}
+
+/// Rejects a rule that targets something outside the form, or whose conditions test answers
+/// that do not come before the fields it applies to. The form's `fields` and `sections` are
+/// given in order.
+pub fn validate_rule(
+    fields: &[Placement],
+    sections: &[Uuid],
+    rule: &RuleSchema,
+) -> Result<(), String> {
+    if rule.conditions.is_empty() {
+        return Err("Rules need at least one condition.".to_string());
+    }
+    let limit = match rule.action {
+        RuleAction::Show | RuleAction::Hide => {
+            if rule.target_section_id.is_some() {
+                return Err("Only skip rules can target a section.".to_string());
+            }
+            let target = rule
+                .target_field_id
+                .ok_or_else(|| "Show and hide rules need a target field.".to_string())?;
+            index_of(fields, target)
+                .ok_or_else(|| "The target field does not belong to the form.".to_string())?
+        }
+        RuleAction::Skip => {
+            if rule.target_field_id.is_some() {
+                return Err("Skip rules target a section, not a field.".to_string());
+            }
+            let target = rule
+                .target_section_id
+                .ok_or_else(|| "Skip rules need a target section.".to_string())?;
+            if !sections.contains(&target) {
+                return Err("The target section does not belong to the form.".to_string());
+            }
+            section_start(fields, sections, target)
+        }
+    };
+    for condition in rule.conditions.iter() {
+        let index = index_of(fields, condition.field_id)
+            .ok_or_else(|| "Conditions can only test fields of the form.".to_string())?;
+        if index >= limit {
+            return Err("Conditions can only test answers to earlier fields.".to_string());
+        }
+        match (condition.operator, &condition.value) {
+            (ConditionOperator::Answered, _) => {}
+            (_, None) => {
+                return Err("Only answered conditions can do without a value.".to_string());
+            }
+            (ConditionOperator::LessThan, Some(value)) if number(value).is_none() => {
+                return Err("less_than conditions need a number.".to_string());
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Returns the fields that a response with the given answers does not get to see, with the
+/// form's `fields` and `sections` given in order.
+///
+/// A field is hidden if it has `show` rules and none of them is met, if one of its `hide` rules
+/// is met, or if a met `skip` rule skips over it. Rules only see the answers to fields that are
+/// not hidden themselves, so hiding a field also hides whatever depends on its answer.
+pub fn hidden_fields(
+    fields: &[Placement],
+    sections: &[Uuid],
+    rules: &[RuleSchema],
+    answers: &HashMap<Uuid, &str>,
+) -> HashSet<Uuid> {
+    let mut hidden = HashSet::new();
+    let mut visible: HashMap<Uuid, &str> = HashMap::new();
+    for (index, field) in fields.iter().enumerate() {
+        let targets = |rule: &&RuleSchema| rule.target_field_id == Some(field.field_id);
+        let shows: Vec<&RuleSchema> = rules
+            .iter()
+            .filter(|rule| rule.action == RuleAction::Show)
+            .filter(targets)
+            .collect();
+        let shown = shows.is_empty() || shows.iter().any(|rule| rule.is_met(&visible));
+        let hidden_by_rule = rules
+            .iter()
+            .filter(|rule| rule.action == RuleAction::Hide)
+            .filter(targets)
+            .any(|rule| rule.is_met(&visible));
+        let skipped = rules
+            .iter()
+            .filter(|rule| rule.action == RuleAction::Skip)
+            .any(|rule| skips(fields, sections, rule, index) && rule.is_met(&visible));
+
+        if !shown || hidden_by_rule || skipped {
+            hidden.insert(field.field_id);
+        } else if let Some(answer) = answers.get(&field.field_id) {
+            visible.insert(field.field_id, answer);
+        }
+    }
+    hidden
+}
+
+/// Whether a `skip` rule skips over the field at `index`, which it does for the fields after
+/// the last one its conditions test and before its target section.
+fn skips(fields: &[Placement], sections: &[Uuid], rule: &RuleSchema, index: usize) -> bool {
+    let target = match rule.target_section_id {
+        Some(target) => target,
+        None => return false,
+    };
+    let last_tested = rule
+        .conditions
+        .iter()
+        .filter_map(|condition| index_of(fields, condition.field_id))
+        .max();
+    last_tested.map_or(false, |last_tested| {
+        last_tested < index && index < section_start(fields, sections, target)
+    })
+}
+
+/// Whether a condition holds for the answer to its field, which is `None` if the field was not
+/// answered or is hidden. Blank answers meet no condition.
+fn holds(condition: &Condition, answer: Option<&str>) -> bool {
+    let answer = match answer.map(str::trim).filter(|answer| !answer.is_empty()) {
+        Some(answer) => answer,
+        None => return false,
+    };
+    let value = condition.value.as_deref().unwrap_or_default().trim();
+    match condition.operator {
+        ConditionOperator::Answered => true,
+        ConditionOperator::Equals => match (number(answer), number(value)) {
+            (Some(answer), Some(value)) => (answer - value).abs() < f64::EPSILON,
+            _ => answer == value,
+        },
+        // Checkbox fields with options are answered with the JSON array of the ticked options
+        ConditionOperator::Contains => match serde_json::from_str::<Vec<String>>(answer) {
+            Ok(ticked) => ticked.iter().any(|option| option == value),
+            Err(_) => answer.to_lowercase().contains(&value.to_lowercase()),
+        },
+        ConditionOperator::LessThan => match (number(answer), number(value)) {
+            (Some(answer), Some(value)) => answer < value,
+            _ => false,
+        },
+    }
+}
+
+fn number(value: &str) -> Option<f64> {
+    value
+        .trim()
+        .parse()
+        .ok()
+        .filter(|value: &f64| value.is_finite())
+}
+
+fn index_of(fields: &[Placement], field_id: Uuid) -> Option<usize> {
+    fields.iter().position(|field| field.field_id == field_id)
+}
+
+/// Index of the first field in the given section or a later one, fields outside any section
+/// coming before every section.
+fn section_start(fields: &[Placement], sections: &[Uuid], section_id: Uuid) -> usize {
+    let rank = |section_id: Option<Uuid>| {
+        section_id
+            .and_then(|id| sections.iter().position(|section| *section == id))
+            .map_or(0, |index| index + 1)
+    };
+    let target = rank(Some(section_id));
+    fields
+        .iter()
+        .position(|field| rank(field.section_id) >= target)
+        .unwrap_or_else(|| fields.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use uuid::Uuid;
+
+    use super::{hidden_fields, validate_rule, RuleSchema};
+    use crate::db::{Condition, ConditionOperator, RuleAction};
+    use crate::services::form_layout::Placement;
+
+    fn condition(field_id: Uuid, operator: ConditionOperator, value: &str) -> Condition {
+        Condition {
+            field_id,
+            operator,
+            value: Some(value.to_string()),
+        }
+    }
+
+    fn rule(action: RuleAction, conditions: Vec<Condition>) -> RuleSchema {
+        RuleSchema {
+            action,
+            target_field_id: None,
+            target_section_id: None,
+            match_any: false,
+            conditions,
+        }
+    }
+
+    #[test]
+    fn show_rules_reveal_follow_up_questions() {
+        let (rating, follow_up) = (Uuid::new_v4(), Uuid::new_v4());
+        let fields = [
+            Placement::new(rating, None),
+            Placement::new(follow_up, None),
+        ];
+        let mut show = rule(
+            RuleAction::Show,
+            vec![condition(rating, ConditionOperator::LessThan, "3")],
+        );
+        show.target_field_id = Some(follow_up);
+
+        let low: HashMap<Uuid, &str> = vec![(rating, "2")].into_iter().collect();
+        assert!(hidden_fields(&fields, &[], &[show.clone()], &low).is_empty());
+        let high: HashMap<Uuid, &str> = vec![(rating, "5")].into_iter().collect();
+        assert!(hidden_fields(&fields, &[], &[show.clone()], &high).contains(&follow_up));
+        assert!(hidden_fields(&fields, &[], &[show], &HashMap::new()).contains(&follow_up));
+    }
+
+    #[test]
+    fn hidden_answers_do_not_meet_conditions() {
+        let (first, second, third) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let fields = [
+            Placement::new(first, None),
+            Placement::new(second, None),
+            Placement::new(third, None),
+        ];
+        let mut hide = rule(
+            RuleAction::Hide,
+            vec![condition(first, ConditionOperator::Equals, "no")],
+        );
+        hide.target_field_id = Some(second);
+        let mut show = rule(
+            RuleAction::Show,
+            vec![Condition {
+                field_id: second,
+                operator: ConditionOperator::Answered,
+                value: None,
+            }],
+        );
+        show.target_field_id = Some(third);
+
+        let answers: HashMap<Uuid, &str> = vec![(first, "no"), (second, "Something")]
+            .into_iter()
+            .collect();
+        let hidden = hidden_fields(&fields, &[], &[hide, show], &answers);
+        assert!(hidden.contains(&second));
+        assert!(hidden.contains(&third));
+    }
+
+    #[test]
+    fn skip_rules_skip_to_their_section() {
+        let (section, last) = (Uuid::new_v4(), Uuid::new_v4());
+        let (question, skipped, target) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let fields = [
+            Placement::new(question, None),
+            Placement::new(skipped, Some(section)),
+            Placement::new(target, Some(last)),
+        ];
+        let mut skip = rule(
+            RuleAction::Skip,
+            vec![condition(question, ConditionOperator::Contains, "Parking")],
+        );
+        skip.target_section_id = Some(last);
+
+        let answers: HashMap<Uuid, &str> = vec![(question, r#"["Parking", "Lobby"]"#)]
+            .into_iter()
+            .collect();
+        let hidden = hidden_fields(&fields, &[section, last], &[skip], &answers);
+        assert_eq!(1, hidden.len());
+        assert!(hidden.contains(&skipped));
+    }
+
+    #[test]
+    fn conditions_match_answers() {
+        let field = Uuid::new_v4();
+        let target = Uuid::new_v4();
+        let fields = [Placement::new(field, None), Placement::new(target, None)];
+        let hides = |operator: ConditionOperator, value: &str, answer: &str| {
+            let mut hide = rule(RuleAction::Hide, vec![condition(field, operator, value)]);
+            hide.target_field_id = Some(target);
+            let answers: HashMap<Uuid, &str> = vec![(field, answer)].into_iter().collect();
+            hidden_fields(&fields, &[], &[hide], &answers).contains(&target)
+        };
+        assert!(hides(ConditionOperator::Equals, "4", "4.0"));
+        assert!(!hides(ConditionOperator::Equals, "Yes", "No"));
+        assert!(hides(
+            ConditionOperator::Contains,
+            "slow",
+            "Service was SLOW"
+        ));
+        assert!(!hides(ConditionOperator::LessThan, "3", "not a number"));
+        assert!(!hides(ConditionOperator::Answered, "", "  "));
+    }
+
+    #[test]
+    fn rules_must_test_earlier_fields_of_the_form() {
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        let fields = [Placement::new(first, None), Placement::new(second, None)];
+
+        let mut show = rule(
+            RuleAction::Show,
+            vec![condition(second, ConditionOperator::Equals, "yes")],
+        );
+        show.target_field_id = Some(first);
+        assert!(validate_rule(&fields, &[], &show).is_err());
+
+        show.target_field_id = Some(second);
+        show.conditions = vec![condition(first, ConditionOperator::Equals, "yes")];
+        assert!(validate_rule(&fields, &[], &show).is_ok());
+
+        show.conditions = vec![condition(first, ConditionOperator::LessThan, "few")];
+        assert!(validate_rule(&fields, &[], &show).is_err());
+
+        show.conditions = vec![];
+        assert!(validate_rule(&fields, &[], &show).is_err());
+
+        let mut skip = rule(
+            RuleAction::Skip,
+            vec![condition(first, ConditionOperator::Equals, "yes")],
+        );
+        skip.target_section_id = Some(Uuid::new_v4());
+        assert!(validate_rule(&fields, &[], &skip).is_err());
+    }
+}
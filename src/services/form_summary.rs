@@ -0,0 +1,304 @@
+//! Contains helpers for rendering a form response into the emails and files sent to the form's owner.
+use std::io::BufWriter;
+
+use anyhow::Context;
+use chrono::NaiveDateTime;
+use printpdf::{BuiltinFont, IndirectFontRef, Mm, PdfDocument, PdfLayerReference};
+use uuid::Uuid;
+
+use crate::clients::email::Attachment;
+use crate::db::AttachmentFormat;
+use crate::services::template::escape;
+
+/// US Letter page size, in millimeters.
+const PAGE_WIDTH: f64 = 215.9;
+const PAGE_HEIGHT: f64 = 279.4;
+/// Blank space kept around the page's content, in millimeters.
+const PAGE_MARGIN: f64 = 20.0;
+const TITLE_FONT_SIZE: f64 = 16.0;
+const BODY_FONT_SIZE: f64 = 11.0;
+/// Approximate height of a body line, in millimeters.
+const LINE_HEIGHT: f64 = 6.0;
+/// Approximate width of an average Helvetica character at `BODY_FONT_SIZE`, in millimeters.
+const CHAR_WIDTH: f64 = 2.0;
+
+/// A question of a form and the answer a response gave to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Answer {
+    pub question: String,
+    pub answer: String,
+}
+
+/// A response to a form, ready to be rendered.
+#[derive(Debug, Clone)]
+pub struct ResponseSummary {
+    pub form_title: String,
+    pub response_id: Uuid,
+    pub submitted_at: NaiveDateTime,
+    pub answers: Vec<Answer>,
+}
+
+impl ResponseSummary {
+    pub fn subject(&self) -> String {
+        format!("New response: {}", self.form_title)
+    }
+
+    fn submitted_at(&self) -> String {
+        self.submitted_at.format("%Y-%m-%d %H:%M UTC").to_string()
+    }
+
+    pub fn text(&self) -> String {
+        let mut text = format!(
+            "{} received a new response on {}.\n",
+            self.form_title,
+            self.submitted_at()
+        );
+        for answer in self.answers.iter() {
+            text.push_str(&format!("\n{}\n{}\n", answer.question, answer.answer));
+        }
+        text
+    }
+
+    pub fn html(&self) -> String {
+        let rows: String = self
+            .answers
+            .iter()
+            .map(|answer| {
+                format!(
+                    "<tr><th align=\"left\" valign=\"top\">{}</th><td>{}</td></tr>",
+                    escape(&answer.question),
+                    escape(&answer.answer).replace('\n', "<br>")
+                )
+            })
+            .collect();
+        format!(
+            "<h2>{}</h2><p>New response received on {}.</p><table cellpadding=\"6\">{}</table>",
+            escape(&self.form_title),
+            self.submitted_at(),
+            rows
+        )
+    }
+
+    /// Renders the response as a CSV file with a header row of questions and a row of answers,
+    /// so that the files of several responses can be combined into one spreadsheet.
+    pub fn csv(&self) -> String {
+        let header = std::iter::once("Submitted at")
+            .chain(self.answers.iter().map(|answer| answer.question.as_str()))
+            .map(csv_cell)
+            .collect::<Vec<_>>()
+            .join(",");
+        let submitted_at = self.submitted_at();
+        let row = std::iter::once(submitted_at.as_str())
+            .chain(self.answers.iter().map(|answer| answer.answer.as_str()))
+            .map(csv_cell)
+            .collect::<Vec<_>>()
+            .join(",");
+        format!("{}\r\n{}\r\n", header, row)
+    }
+
+    /// Renders the response as a PDF document listing each question and its answer.
+    #[tracing::instrument(name = "services::form_summary::pdf", skip(self))]
+    pub fn pdf(&self) -> Result<Vec<u8>, anyhow::Error> {
+        let (document, page, layer) = PdfDocument::new(
+            &self.form_title,
+            Mm(PAGE_WIDTH),
+            Mm(PAGE_HEIGHT),
+            "Response",
+        );
+        let regular = document
+            .add_builtin_font(BuiltinFont::Helvetica)
+            .context("Failed to load PDF font.")?;
+        let bold = document
+            .add_builtin_font(BuiltinFont::HelveticaBold)
+            .context("Failed to load PDF font.")?;
+
+        let mut layer = document.get_page(page).get_layer(layer);
+        let mut top = PAGE_HEIGHT - PAGE_MARGIN;
+        let mut line = |layer: &mut PdfLayerReference,
+                        text: &str,
+                        size: f64,
+                        font: &IndirectFontRef,
+                        gap: f64| {
+            if top < PAGE_MARGIN + LINE_HEIGHT {
+                let (page, new_layer) =
+                    document.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Response");
+                *layer = document.get_page(page).get_layer(new_layer);
+                top = PAGE_HEIGHT - PAGE_MARGIN;
+            }
+            top -= gap;
+            layer.use_text(text, size, Mm(PAGE_MARGIN), Mm(top), font);
+        };
+
+        for title_line in wrap(&self.form_title, (PAGE_WIDTH - 2.0 * PAGE_MARGIN) / 3.0) {
+            line(&mut layer, &title_line, TITLE_FONT_SIZE, &bold, 8.0);
+        }
+        let submitted_at = format!("Submitted on {}", self.submitted_at());
+        line(
+            &mut layer,
+            &submitted_at,
+            BODY_FONT_SIZE,
+            &regular,
+            LINE_HEIGHT,
+        );
+        let width = (PAGE_WIDTH - 2.0 * PAGE_MARGIN) / CHAR_WIDTH;
+        for answer in self.answers.iter() {
+            let mut gap = 2.0 * LINE_HEIGHT;
+            for question_line in wrap(&answer.question, width) {
+                line(&mut layer, &question_line, BODY_FONT_SIZE, &bold, gap);
+                gap = LINE_HEIGHT;
+            }
+            for answer_line in wrap(&answer.answer, width) {
+                line(
+                    &mut layer,
+                    &answer_line,
+                    BODY_FONT_SIZE,
+                    &regular,
+                    LINE_HEIGHT,
+                );
+            }
+        }
+
+        let mut writer = BufWriter::new(Vec::new());
+        document
+            .save(&mut writer)
+            .context("Failed to write PDF document.")?;
+        writer.into_inner().context("Failed to flush PDF document.")
+    }
+
+    /// Renders the response as a file in the given format.
+    pub fn attachment(&self, format: AttachmentFormat) -> Result<Attachment, anyhow::Error> {
+        let content = match format {
+            AttachmentFormat::Csv => self.csv().into_bytes(),
+            AttachmentFormat::Pdf => self.pdf()?,
+        };
+        Ok(Attachment::new(
+            format!("response-{}.{}", self.response_id, format.as_str()),
+            format.content_type(),
+            content,
+        ))
+    }
+}
+
+/// Quotes a CSV cell when needed, and neutralises values spreadsheets would run as formulas.
+fn csv_cell(value: &str) -> String {
+    let value = if value.starts_with(|c| matches!(c, '=' | '+' | '-' | '@')) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains(|c| matches!(c, ',' | '"' | '\r' | '\n')) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+/// Splits `text` into lines of at most `width` characters, breaking between words when possible.
+fn wrap(text: &str, width: f64) -> Vec<String> {
+    let width = width.floor().max(1.0) as usize;
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut current = String::new();
+        for word in paragraph.split_whitespace() {
+            let mut word: Vec<char> = word.chars().collect();
+            // Words longer than a line are split wherever they reach its end
+            while word.len() > width {
+                if !current.is_empty() {
+                    lines.push(std::mem::take(&mut current));
+                }
+                lines.push(word.drain(..width).collect());
+            }
+            let word: String = word.into_iter().collect();
+            if current.is_empty() {
+                current = word;
+            } else if current.chars().count() + 1 + word.chars().count() <= width {
+                current.push(' ');
+                current.push_str(&word);
+            } else {
+                lines.push(std::mem::replace(&mut current, word));
+            }
+        }
+        lines.push(current);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use uuid::Uuid;
+
+    use super::{csv_cell, wrap, Answer, ResponseSummary};
+    use crate::db::AttachmentFormat;
+
+    fn summary() -> ResponseSummary {
+        ResponseSummary {
+            form_title: "Feedback <Lobby>".to_string(),
+            response_id: Uuid::nil(),
+            submitted_at: NaiveDate::from_ymd(2021, 12, 16).and_hms(9, 30, 0),
+            answers: vec![
+                Answer {
+                    question: "How was your visit?".to_string(),
+                    answer: "Great, thanks".to_string(),
+                },
+                Answer {
+                    question: "Comments".to_string(),
+                    answer: "=1+1".to_string(),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn text_and_html_list_every_answer() {
+        let summary = summary();
+        assert_eq!(
+            "Feedback <Lobby> received a new response on 2021-12-16 09:30 UTC.\n\nHow was your visit?\nGreat, thanks\n\nComments\n=1+1\n",
+            summary.text()
+        );
+        let html = summary.html();
+        assert!(html.contains("<h2>Feedback &lt;Lobby&gt;</h2>"));
+        assert!(html.contains(
+            "<th align=\"left\" valign=\"top\">How was your visit?</th><td>Great, thanks</td>"
+        ));
+    }
+
+    #[test]
+    fn csv_has_a_header_of_questions_and_a_row_of_answers() {
+        assert_eq!(
+            "Submitted at,How was your visit?,Comments\r\n2021-12-16 09:30 UTC,\"Great, thanks\",'=1+1\r\n",
+            summary().csv()
+        );
+    }
+
+    #[test]
+    fn csv_cells_are_quoted_when_needed() {
+        assert_eq!("plain", csv_cell("plain"));
+        assert_eq!("\"say \"\"hi\"\"\"", csv_cell("say \"hi\""));
+        assert_eq!("\"two\nlines\"", csv_cell("two\nlines"));
+        assert_eq!("'@cmd", csv_cell("@cmd"));
+    }
+
+    #[test]
+    fn attachments_are_named_after_the_response() {
+        let csv = summary().attachment(AttachmentFormat::Csv).unwrap();
+        assert_eq!(
+            "response-00000000-0000-0000-0000-000000000000.csv",
+            csv.name
+        );
+        assert_eq!("text/csv", csv.content_type);
+
+        let pdf = summary().attachment(AttachmentFormat::Pdf).unwrap();
+        assert_eq!("application/pdf", pdf.content_type);
+        assert!(pdf.content.starts_with(b"%PDF"));
+    }
+
+    #[test]
+    fn long_text_is_wrapped_between_words() {
+        assert_eq!(
+            vec!["one two", "three", "abcdefg", "hij"],
+            wrap("one two three abcdefghij", 7.0)
+        );
+        assert_eq!(vec!["a", "", "b"], wrap("a\n\nb", 10.0));
+    }
+}
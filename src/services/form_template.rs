@@ -0,0 +1,377 @@
+//! Contains portable definitions of forms, used to copy forms, save them as templates and create
+//! forms from templates, along with the starter templates shipped with the API.
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::db::{
+    Condition, ConditionOperator, Field, FieldConfig, FieldType, FormRule, FormSection, NewField,
+    RuleAction,
+};
+use crate::services::form_layout::Placement;
+use crate::services::form_logic::{validate_rule, RuleSchema};
+use crate::services::form_validation::validate_config;
+
+/// A field of a form definition. Rules refer to the field by its `key`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FieldDefinition {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    pub caption: String,
+    pub r#type: FieldType,
+    #[serde(default)]
+    pub config: FieldConfig,
+}
+
+/// A section of a form definition. `skip` rules refer to the section by its `key`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SectionDefinition {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub page_break: bool,
+    #[serde(default)]
+    pub fields: Vec<FieldDefinition>,
+}
+
+/// A condition of a rule of a form definition, testing the answer to the field with key `field`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ConditionDefinition {
+    pub field: String,
+    pub operator: ConditionOperator,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+}
+
+/// A rule of a form definition, targeting fields and sections by their keys.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RuleDefinition {
+    pub action: RuleAction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_field: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_section: Option<String>,
+    #[serde(default)]
+    pub match_any: bool,
+    pub conditions: Vec<ConditionDefinition>,
+}
+
+/// The title, fields, sections and rules of a form, independent of any stored form.
+/// Its `fields` come before its `sections` and are not grouped in any of them.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FormDefinition {
+    pub title: String,
+    #[serde(default)]
+    pub fields: Vec<FieldDefinition>,
+    #[serde(default)]
+    pub sections: Vec<SectionDefinition>,
+    #[serde(default)]
+    pub rules: Vec<RuleDefinition>,
+}
+
+/// The rows of a new form created from a definition, ready to be stored in this order.
+pub struct FormInstance {
+    pub sections: Vec<FormSection>,
+    pub fields: Vec<NewField>,
+    pub rules: Vec<FormRule>,
+}
+
+impl FormDefinition {
+    /// Describes a stored form, keying its fields and sections by their ids. The form's `fields`
+    /// and `sections` are given in order.
+    pub fn of(
+        title: &str,
+        fields: &[Field],
+        sections: &[FormSection],
+        rules: &[FormRule],
+    ) -> Result<Self, anyhow::Error> {
+        let field_definitions = |section_id: Option<Uuid>| {
+            fields
+                .iter()
+                .filter(|field| field.section_id == section_id)
+                .map(|field| {
+                    Ok(FieldDefinition {
+                        key: Some(field.id.to_string()),
+                        caption: field.caption.clone(),
+                        r#type: field.field_type()?,
+                        config: field.config()?,
+                    })
+                })
+                .collect::<Result<Vec<FieldDefinition>, anyhow::Error>>()
+        };
+
+        let mut section_definitions = Vec::with_capacity(sections.len());
+        for section in sections.iter() {
+            section_definitions.push(SectionDefinition {
+                key: Some(section.id.to_string()),
+                title: section.title.clone(),
+                description: section.description.clone(),
+                page_break: section.page_break,
+                fields: field_definitions(Some(section.id))?,
+            });
+        }
+        let mut rule_definitions = Vec::with_capacity(rules.len());
+        for rule in rules.iter() {
+            rule_definitions.push(RuleDefinition {
+                action: rule.rule_action()?,
+                target_field: rule.target_field_id.map(|id| id.to_string()),
+                target_section: rule.target_section_id.map(|id| id.to_string()),
+                match_any: rule.match_any,
+                conditions: rule
+                    .conditions()?
+                    .into_iter()
+                    .map(|condition| ConditionDefinition {
+                        field: condition.field_id.to_string(),
+                        operator: condition.operator,
+                        value: condition.value,
+                    })
+                    .collect(),
+            });
+        }
+
+        Ok(Self {
+            title: title.to_string(),
+            fields: field_definitions(None)?,
+            sections: section_definitions,
+            rules: rule_definitions,
+        })
+    }
+
+    /// Builds the sections, fields and rules of a new form with the given `form_id`, giving each
+    /// of them a new id. Rejects definitions with invalid fields or rules, or whose rules refer
+    /// to keys it does not have.
+    pub fn instantiate(&self, form_id: Uuid) -> Result<FormInstance, String> {
+        let mut keys = HashMap::new();
+        let mut sections = Vec::with_capacity(self.sections.len());
+        let mut fields = Vec::new();
+        let groups = std::iter::once((None, &self.fields)).chain(
+            self.sections
+                .iter()
+                .map(|section| (Some(section), &section.fields)),
+        );
+        for (section, definitions) in groups {
+            let section_id = section.map(|section| {
+                let mut new_section = FormSection::new(form_id, sections.len() as i32);
+                new_section.title = section.title.clone();
+                new_section.description = section.description.clone();
+                new_section.page_break = section.page_break;
+                if let Some(key) = &section.key {
+                    keys.insert(key.as_str(), new_section.id);
+                }
+                let id = new_section.id;
+                sections.push(new_section);
+                id
+            });
+            for definition in definitions.iter() {
+                validate_config(definition.r#type, &definition.config)
+                    .map_err(|message| format!("{}: {}", definition.caption, message))?;
+                let mut field = NewField::default();
+                field.form_id = form_id;
+                field.caption = definition.caption.clone();
+                field.field_type = definition.r#type.as_str().to_string();
+                field.config = serde_json::to_value(&definition.config)
+                    .map_err(|e| format!("{}: {}", definition.caption, e))?;
+                field.position = fields.len() as i32;
+                field.section_id = section_id;
+                if let Some(key) = &definition.key {
+                    keys.insert(key.as_str(), field.id);
+                }
+                fields.push(field);
+            }
+        }
+
+        let resolve = |key: &str| {
+            keys.get(key)
+                .copied()
+                .ok_or_else(|| format!("Nothing in the form has the key {}.", key))
+        };
+        let placements: Vec<Placement> = fields
+            .iter()
+            .map(|field| Placement::new(field.id, field.section_id))
+            .collect();
+        let section_ids: Vec<Uuid> = sections.iter().map(|section| section.id).collect();
+        let mut rules = Vec::with_capacity(self.rules.len());
+        for (index, definition) in self.rules.iter().enumerate() {
+            let invalid = |message: String| format!("Rule {}: {}", index + 1, message);
+            let mut conditions = Vec::with_capacity(definition.conditions.len());
+            for condition in definition.conditions.iter() {
+                conditions.push(Condition {
+                    field_id: resolve(&condition.field).map_err(invalid)?,
+                    operator: condition.operator,
+                    value: condition.value.clone(),
+                });
+            }
+            let schema = RuleSchema {
+                action: definition.action,
+                target_field_id: definition
+                    .target_field
+                    .as_deref()
+                    .map(resolve)
+                    .transpose()
+                    .map_err(invalid)?,
+                target_section_id: definition
+                    .target_section
+                    .as_deref()
+                    .map(resolve)
+                    .transpose()
+                    .map_err(invalid)?,
+                match_any: definition.match_any,
+                conditions,
+            };
+            validate_rule(&placements, &section_ids, &schema).map_err(invalid)?;
+
+            let mut rule = FormRule::new(form_id, index as i32, schema.action);
+            rule.target_field_id = schema.target_field_id;
+            rule.target_section_id = schema.target_section_id;
+            rule.match_any = schema.match_any;
+            rule.conditions =
+                serde_json::to_value(&schema.conditions).map_err(|e| invalid(e.to_string()))?;
+            rules.push(rule);
+        }
+
+        Ok(FormInstance {
+            sections,
+            fields,
+            rules,
+        })
+    }
+}
+
+/// A starter template shipped with the API.
+#[derive(Serialize, Debug)]
+pub struct BuiltInTemplate {
+    pub template_id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    #[serde(skip)]
+    source: &'static str,
+}
+
+impl BuiltInTemplate {
+    pub fn definition(&self) -> Result<FormDefinition, anyhow::Error> {
+        Ok(serde_json::from_str(self.source)?)
+    }
+}
+
+/// Starter templates every account can create forms from.
+pub const BUILT_IN_TEMPLATES: &[BuiltInTemplate] = &[
+    BuiltInTemplate {
+        template_id: "nps-survey",
+        name: "NPS survey",
+        description: "Asks how likely customers are to recommend you, and what to improve.",
+        source: include_str!("../../templates/forms/nps_survey.json"),
+    },
+    BuiltInTemplate {
+        template_id: "restaurant-feedback",
+        name: "Restaurant feedback",
+        description: "Rates the food, service and atmosphere of a visit.",
+        source: include_str!("../../templates/forms/restaurant_feedback.json"),
+    },
+    BuiltInTemplate {
+        template_id: "incident-report",
+        name: "Incident report",
+        description: "Records what happened, where and when, and who to contact about it.",
+        source: include_str!("../../templates/forms/incident_report.json"),
+    },
+];
+
+/// Returns the starter template with the given `template_id`.
+pub fn built_in_template(template_id: &str) -> Option<&'static BuiltInTemplate> {
+    BUILT_IN_TEMPLATES
+        .iter()
+        .find(|template| template.template_id == template_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(key: &str, caption: &str, field_type: FieldType) -> FieldDefinition {
+        FieldDefinition {
+            key: Some(key.to_string()),
+            caption: caption.to_string(),
+            r#type: field_type,
+            config: FieldConfig::default(),
+        }
+    }
+
+    #[test]
+    fn built_in_templates_are_valid() {
+        for template in BUILT_IN_TEMPLATES {
+            let definition = template.definition().unwrap();
+            assert!(
+                definition.instantiate(Uuid::new_v4()).is_ok(),
+                "{} is invalid",
+                template.template_id
+            );
+        }
+    }
+
+    #[test]
+    fn instances_get_new_ids_and_keep_their_rules() {
+        let definition = FormDefinition {
+            title: "Survey".to_string(),
+            fields: vec![field("rating", "Rating", FieldType::Rating)],
+            sections: vec![SectionDefinition {
+                key: None,
+                title: Some("Details".to_string()),
+                description: None,
+                page_break: true,
+                fields: vec![field("details", "What went wrong?", FieldType::Text)],
+            }],
+            rules: vec![RuleDefinition {
+                action: RuleAction::Show,
+                target_field: Some("details".to_string()),
+                target_section: None,
+                match_any: false,
+                conditions: vec![ConditionDefinition {
+                    field: "rating".to_string(),
+                    operator: ConditionOperator::LessThan,
+                    value: Some("3".to_string()),
+                }],
+            }],
+        };
+        let form_id = Uuid::new_v4();
+        let instance = definition.instantiate(form_id).unwrap();
+
+        assert_eq!(1, instance.sections.len());
+        assert_eq!(2, instance.fields.len());
+        assert_eq!(None, instance.fields[0].section_id);
+        assert_eq!(Some(instance.sections[0].id), instance.fields[1].section_id);
+        assert_eq!(1, instance.fields[1].position);
+        assert_eq!(
+            Some(instance.fields[1].id),
+            instance.rules[0].target_field_id
+        );
+        let conditions = instance.rules[0].conditions().unwrap();
+        assert_eq!(instance.fields[0].id, conditions[0].field_id);
+        assert!(instance.fields.iter().all(|field| field.form_id == form_id));
+    }
+
+    #[test]
+    fn rules_must_refer_to_keys_of_the_definition() {
+        let definition = FormDefinition {
+            title: "Survey".to_string(),
+            fields: vec![field("rating", "Rating", FieldType::Rating)],
+            sections: vec![],
+            rules: vec![RuleDefinition {
+                action: RuleAction::Hide,
+                target_field: Some("missing".to_string()),
+                target_section: None,
+                match_any: false,
+                conditions: vec![ConditionDefinition {
+                    field: "rating".to_string(),
+                    operator: ConditionOperator::Answered,
+                    value: None,
+                }],
+            }],
+        };
+        assert_eq!(
+            Err("Rule 1: Nothing in the form has the key missing.".to_string()),
+            definition.instantiate(Uuid::new_v4()).map(|_| ())
+        );
+    }
+}
@@ -0,0 +1,503 @@
+//! Contains the validation of form field settings and of the answers responses give to them.
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime};
+use regex::Regex;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::db::{FieldConfig, FieldType};
+
+/// Most stars a rating field can be configured with.
+const MAX_RATING: f64 = 10.0;
+/// Image types answers to image fields can be uploaded as.
+const IMAGE_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp"];
+/// Most characters an answer can have, whatever its field's config allows.
+pub const MAX_ANSWER_LENGTH: usize = 5_000;
+/// Most characters the `data:` URL of an uploaded image can have, about 3 MB once decoded.
+pub const MAX_IMAGE_ANSWER_LENGTH: usize = 4 * 1024 * 1024;
+/// Largest JSON body a response can be submitted with, enough for a few images.
+pub const MAX_RESPONSE_BODY_SIZE: usize = 16 * 1024 * 1024;
+
+/// A field of a form, as needed to validate a response to it.
+#[derive(Debug, Clone)]
+pub struct FieldSchema {
+    pub id: Uuid,
+    pub field_type: FieldType,
+    pub config: FieldConfig,
+}
+
+/// Why an answer was rejected, as a stable `code` for clients and a `message` for people.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl Violation {
+    pub fn new(code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Why the answer to a field was rejected.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FieldError {
+    pub field_id: Uuid,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl FieldError {
+    fn new(field_id: Uuid, violation: Violation) -> Self {
+        Self {
+            field_id,
+            code: violation.code,
+            message: violation.message,
+        }
+    }
+}
+
+/// Rejects settings that do not apply to the field's type or that no answer could satisfy.
+pub fn validate_config(field_type: FieldType, config: &FieldConfig) -> Result<(), String> {
+    if let (Some(min), Some(max)) = (config.min, config.max) {
+        if min > max {
+            return Err("min cannot be greater than max.".to_string());
+        }
+    }
+    if field_type.has_options() {
+        if field_type != FieldType::Checkbox && config.options.is_empty() {
+            return Err(format!("{} fields need options.", field_type.as_str()));
+        }
+        let mut seen = HashSet::new();
+        for option in config.options.iter() {
+            if option.trim().is_empty() {
+                return Err("Options cannot be blank.".to_string());
+            }
+            if !seen.insert(option) {
+                return Err(format!("The option {} is listed twice.", option));
+            }
+        }
+    } else if !config.options.is_empty() {
+        return Err("Only checkbox, radio and select fields have options.".to_string());
+    }
+    if let Some(pattern) = &config.pattern {
+        if !field_type.is_textual() {
+            return Err("Only text, email and phone fields can have a pattern.".to_string());
+        }
+        anchored(pattern).map_err(|e| format!("The pattern is invalid: {}", e))?;
+    }
+    match field_type {
+        FieldType::Slider if config.min.is_none() || config.max.is_none() => {
+            Err("Slider fields need a min and a max.".to_string())
+        }
+        FieldType::Rating if config.max.unwrap_or(5.0) > MAX_RATING => Err(format!(
+            "Ratings cannot have more than {} stars.",
+            MAX_RATING
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Validates the answers of a response against the form's fields, returning an error for
+/// every field that is missing a required answer or whose answer is invalid, then for every
+/// answer to a field the form does not have or that answers a field twice. Blank answers to
+/// optional fields are accepted.
+pub fn validate_response(fields: &[FieldSchema], answers: &[(Uuid, &str)]) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    let mut by_field: HashMap<Uuid, &str> = HashMap::new();
+    let mut extra = Vec::new();
+    for (field_id, answer) in answers.iter() {
+        if !fields.iter().any(|field| field.id == *field_id) {
+            extra.push(FieldError::new(
+                *field_id,
+                Violation::new("unknown_field", "This field does not belong to the form."),
+            ));
+        } else if by_field.contains_key(field_id) {
+            extra.push(FieldError::new(
+                *field_id,
+                Violation::new("duplicate_answer", "This field is answered more than once."),
+            ));
+        } else {
+            by_field.insert(*field_id, answer);
+        }
+    }
+    for field in fields.iter() {
+        let result = match by_field.get(&field.id) {
+            Some(answer) if !answer.trim().is_empty() => check_length(field.field_type, answer)
+                .and_then(|_| validate_answer(field.field_type, &field.config, answer)),
+            _ if field.config.required => {
+                Err(Violation::new("required", "This field is required."))
+            }
+            _ => Ok(()),
+        };
+        if let Err(violation) = result {
+            errors.push(FieldError::new(field.id, violation));
+        }
+    }
+    errors.extend(extra);
+    errors
+}
+
+/// Rejects answers longer than any field of their type should need.
+fn check_length(field_type: FieldType, answer: &str) -> Result<(), Violation> {
+    let limit = match field_type {
+        FieldType::Image => MAX_IMAGE_ANSWER_LENGTH,
+        _ => MAX_ANSWER_LENGTH,
+    };
+    if answer.chars().count() > limit {
+        Err(Violation::new(
+            "too_long",
+            format!("Answers cannot be longer than {} characters.", limit),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Validates a non-blank answer to a field of the given type and settings.
+pub fn validate_answer(
+    field_type: FieldType,
+    config: &FieldConfig,
+    answer: &str,
+) -> Result<(), Violation> {
+    let invalid = |message: &str| Err(Violation::new("invalid_format", message));
+    match field_type {
+        FieldType::Text => {
+            let length = answer.chars().count() as f64;
+            if let Some(min) = config.min.filter(|min| length < *min) {
+                return Err(Violation::new(
+                    "too_short",
+                    format!("Must be at least {} characters long.", min),
+                ));
+            }
+            if let Some(max) = config.max.filter(|max| length > *max) {
+                return Err(Violation::new(
+                    "too_long",
+                    format!("Must be at most {} characters long.", max),
+                ));
+            }
+        }
+        FieldType::Email => {
+            if !is_email(answer) {
+                return invalid("Must be a valid email address.");
+            }
+        }
+        FieldType::Phone => {
+            if !is_phone(answer) {
+                return invalid("Must be a valid phone number.");
+            }
+        }
+        FieldType::Number | FieldType::Slider => {
+            let value: f64 = answer
+                .trim()
+                .parse()
+                .ok()
+                .filter(|value: &f64| value.is_finite())
+                .ok_or_else(|| Violation::new("invalid_format", "Must be a number."))?;
+            in_range(value, config.min, config.max)?;
+        }
+        FieldType::Rating => {
+            let value: u32 = answer.trim().parse().map_err(|_| {
+                Violation::new("invalid_format", "Must be a whole number of stars.")
+            })?;
+            in_range(
+                value as f64,
+                config.min.or(Some(1.0)),
+                config.max.or(Some(5.0)),
+            )?;
+        }
+        FieldType::DateTime => {
+            if !is_date_time(answer.trim()) {
+                return invalid("Must be a date and/or time in ISO 8601 format.");
+            }
+        }
+        FieldType::Checkbox if config.options.is_empty() => {
+            if answer != "true" && answer != "false" {
+                return invalid("Must be true or false.");
+            }
+        }
+        FieldType::Checkbox => {
+            let ticked: Vec<String> = serde_json::from_str(answer).map_err(|_| {
+                Violation::new(
+                    "invalid_format",
+                    "Must be a JSON array of the ticked options.",
+                )
+            })?;
+            if let Some(unknown) = ticked.iter().find(|t| !config.options.contains(t)) {
+                return Err(Violation::new(
+                    "invalid_option",
+                    format!("{} is not one of the options.", unknown),
+                ));
+            }
+            let count = ticked.len() as f64;
+            if let Some(min) = config.min.filter(|min| count < *min) {
+                return Err(Violation::new(
+                    "out_of_range",
+                    format!("Tick at least {} options.", min),
+                ));
+            }
+            if let Some(max) = config.max.filter(|max| count > *max) {
+                return Err(Violation::new(
+                    "out_of_range",
+                    format!("Tick at most {} options.", max),
+                ));
+            }
+        }
+        FieldType::Radio | FieldType::Select => {
+            if !config.options.iter().any(|option| option == answer) {
+                return Err(Violation::new(
+                    "invalid_option",
+                    "Must be one of the options.",
+                ));
+            }
+        }
+        FieldType::Image => validate_image(answer, config.max)?,
+    }
+    if let Some(pattern) = &config.pattern {
+        // Patterns are checked when the form is saved, so an invalid one is skipped rather than failing every response
+        if let Ok(regex) = anchored(pattern) {
+            if !regex.is_match(answer) {
+                return Err(Violation::new(
+                    "pattern_mismatch",
+                    "Does not match the expected format.",
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Compiles a pattern so that it has to match answers as a whole.
+fn anchored(pattern: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&format!("^(?:{})$", pattern))
+}
+
+fn in_range(value: f64, min: Option<f64>, max: Option<f64>) -> Result<(), Violation> {
+    if let Some(min) = min.filter(|min| value < *min) {
+        return Err(Violation::new(
+            "out_of_range",
+            format!("Must be at least {}.", min),
+        ));
+    }
+    if let Some(max) = max.filter(|max| value > *max) {
+        return Err(Violation::new(
+            "out_of_range",
+            format!("Must be at most {}.", max),
+        ));
+    }
+    Ok(())
+}
+
+fn is_email(answer: &str) -> bool {
+    let mut parts = answer.split('@');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(local), Some(domain), None) => {
+            !local.is_empty()
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && answer.len() <= 254
+                && !answer.contains(char::is_whitespace)
+        }
+        _ => false,
+    }
+}
+
+/// Accepts international and national numbers of 7 to 15 digits, with common separators.
+fn is_phone(answer: &str) -> bool {
+    let number: String = answer
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '.' | '(' | ')'))
+        .collect();
+    let digits = number.strip_prefix('+').unwrap_or(&number);
+    (7..=15).contains(&digits.len()) && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+fn is_date_time(answer: &str) -> bool {
+    DateTime::parse_from_rfc3339(answer).is_ok()
+        || NaiveDateTime::parse_from_str(answer, "%Y-%m-%dT%H:%M:%S").is_ok()
+        || NaiveDateTime::parse_from_str(answer, "%Y-%m-%dT%H:%M").is_ok()
+        || NaiveDate::parse_from_str(answer, "%Y-%m-%d").is_ok()
+        || NaiveTime::parse_from_str(answer, "%H:%M").is_ok()
+}
+
+/// Accepts base64 `data:` URLs of common image types, no larger than `max_kilobytes`.
+fn validate_image(answer: &str, max_kilobytes: Option<f64>) -> Result<(), Violation> {
+    let invalid = || {
+        Violation::new(
+            "invalid_format",
+            "Must be a PNG, JPEG, GIF or WebP image as a base64 data URL.",
+        )
+    };
+    let rest = answer.strip_prefix("data:").ok_or_else(invalid)?;
+    let (content_type, data) = rest.split_once(";base64,").ok_or_else(invalid)?;
+    if !IMAGE_TYPES.contains(&content_type) {
+        return Err(invalid());
+    }
+    let image = base64::decode(data).map_err(|_| invalid())?;
+    if let Some(max) = max_kilobytes.filter(|max| image.len() as f64 > max * 1024.0) {
+        return Err(Violation::new(
+            "too_large",
+            format!("Images cannot be larger than {} KB.", max),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::{
+        validate_answer, validate_config, validate_response, FieldSchema, MAX_ANSWER_LENGTH,
+    };
+    use crate::db::{FieldConfig, FieldType};
+
+    fn config(json: serde_json::Value) -> FieldConfig {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn configs_must_suit_their_field_type() {
+        assert!(validate_config(FieldType::Text, &FieldConfig::default()).is_ok());
+        assert!(validate_config(FieldType::Radio, &FieldConfig::default()).is_err());
+        assert!(validate_config(FieldType::Checkbox, &FieldConfig::default()).is_ok());
+        assert!(
+            validate_config(FieldType::Slider, &config(serde_json::json!({ "min": 0 }))).is_err()
+        );
+        assert!(validate_config(
+            FieldType::Number,
+            &config(serde_json::json!({ "min": 5, "max": 1 }))
+        )
+        .is_err());
+        assert!(validate_config(
+            FieldType::Select,
+            &config(serde_json::json!({ "options": ["a", "a"] }))
+        )
+        .is_err());
+        assert!(validate_config(
+            FieldType::Number,
+            &config(serde_json::json!({ "options": ["a"] }))
+        )
+        .is_err());
+        assert!(validate_config(
+            FieldType::Text,
+            &config(serde_json::json!({ "pattern": "(" }))
+        )
+        .is_err());
+        assert!(
+            validate_config(FieldType::Rating, &config(serde_json::json!({ "max": 20 }))).is_err()
+        );
+    }
+
+    #[test]
+    fn answers_are_validated_by_type() {
+        let none = FieldConfig::default();
+        assert!(validate_answer(FieldType::Email, &none, "staff@hermodapp.com").is_ok());
+        assert!(validate_answer(FieldType::Email, &none, "staff@hermodapp").is_err());
+        assert!(validate_answer(FieldType::Phone, &none, "+1 (555) 555-0123").is_ok());
+        assert!(validate_answer(FieldType::Phone, &none, "call me").is_err());
+        assert!(validate_answer(FieldType::Number, &none, "3.5").is_ok());
+        assert!(validate_answer(FieldType::Number, &none, "NaN").is_err());
+        assert!(validate_answer(FieldType::Rating, &none, "5").is_ok());
+        assert!(validate_answer(FieldType::Rating, &none, "6").is_err());
+        assert!(validate_answer(FieldType::DateTime, &none, "2021-12-17T10:15:00Z").is_ok());
+        assert!(validate_answer(FieldType::DateTime, &none, "2021-12-17").is_ok());
+        assert!(validate_answer(FieldType::DateTime, &none, "tomorrow").is_err());
+        assert!(validate_answer(FieldType::Checkbox, &none, "true").is_ok());
+        assert!(validate_answer(FieldType::Checkbox, &none, "yes").is_err());
+        assert!(validate_answer(
+            FieldType::Image,
+            &none,
+            "data:image/png;base64,iVBORw0KGgo="
+        )
+        .is_ok());
+        assert!(validate_answer(FieldType::Image, &none, "data:text/plain;base64,aGk=").is_err());
+    }
+
+    #[test]
+    fn answers_respect_the_field_config() {
+        let length = config(serde_json::json!({ "min": 2, "max": 4 }));
+        assert!(validate_answer(FieldType::Text, &length, "abc").is_ok());
+        assert!(validate_answer(FieldType::Text, &length, "a").is_err());
+        assert!(validate_answer(FieldType::Text, &length, "abcde").is_err());
+        assert!(validate_answer(FieldType::Slider, &length, "5").is_err());
+
+        let pattern = config(serde_json::json!({ "pattern": "[A-Z]{3}" }));
+        assert!(validate_answer(FieldType::Text, &pattern, "ABC").is_ok());
+        assert!(validate_answer(FieldType::Text, &pattern, "ABCD").is_err());
+
+        let options = config(serde_json::json!({ "options": ["Red", "Blue"], "max": 1 }));
+        assert!(validate_answer(FieldType::Radio, &options, "Red").is_ok());
+        assert!(validate_answer(FieldType::Radio, &options, "Green").is_err());
+        assert!(validate_answer(FieldType::Checkbox, &options, r#"["Blue"]"#).is_ok());
+        assert!(validate_answer(FieldType::Checkbox, &options, r#"["Red", "Blue"]"#).is_err());
+        assert!(validate_answer(FieldType::Checkbox, &options, r#"["Green"]"#).is_err());
+
+        let size = config(serde_json::json!({ "max": 0.001 }));
+        assert!(validate_answer(
+            FieldType::Image,
+            &size,
+            "data:image/png;base64,iVBORw0KGgo="
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn responses_report_every_invalid_field() {
+        let required = FieldSchema {
+            id: Uuid::new_v4(),
+            field_type: FieldType::Text,
+            config: config(serde_json::json!({ "required": true })),
+        };
+        let optional = FieldSchema {
+            id: Uuid::new_v4(),
+            field_type: FieldType::Email,
+            config: FieldConfig::default(),
+        };
+        let fields = vec![required.clone(), optional.clone()];
+
+        assert!(validate_response(&fields, &[(required.id, "Hi"), (optional.id, "")]).is_empty());
+
+        let errors = validate_response(&fields, &[(required.id, " "), (optional.id, "nope")]);
+        assert_eq!(2, errors.len());
+        assert_eq!(required.id, errors[0].field_id);
+        assert_eq!("required", errors[0].code);
+        assert_eq!("This field is required.", errors[0].message);
+        assert_eq!(optional.id, errors[1].field_id);
+        assert_eq!("invalid_format", errors[1].code);
+    }
+
+    #[test]
+    fn responses_cannot_answer_other_fields_twice_or_at_length() {
+        let field = FieldSchema {
+            id: Uuid::new_v4(),
+            field_type: FieldType::Text,
+            config: FieldConfig::default(),
+        };
+        let stranger = Uuid::new_v4();
+        let long = "a".repeat(MAX_ANSWER_LENGTH + 1);
+
+        let errors = validate_response(
+            &[field.clone()],
+            &[
+                (field.id, long.as_str()),
+                (field.id, "Hi"),
+                (stranger, "Hi"),
+            ],
+        );
+        let codes: Vec<(Uuid, &str)> = errors.iter().map(|e| (e.field_id, e.code)).collect();
+        assert_eq!(
+            vec![
+                (field.id, "too_long"),
+                (field.id, "duplicate_answer"),
+                (stranger, "unknown_field")
+            ],
+            codes
+        );
+    }
+}
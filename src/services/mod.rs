@@ -1,6 +1,20 @@
 //! Contains services and helpers for Hermod.
+pub mod alert;
+pub mod analytics;
 pub mod auth;
 pub mod configuration;
 pub mod error;
+pub mod form_document;
+pub mod form_layout;
+pub mod form_lifecycle;
+pub mod form_logic;
+pub mod form_summary;
+pub mod form_template;
+pub mod form_validation;
 pub mod jwt;
+pub mod notification_worker;
+pub mod qr_image;
+pub mod qr_sheet;
 pub mod telemetry;
+pub mod template;
+pub mod trash;
@@ -0,0 +1,228 @@
+//! Contains the background worker that delivers notifications queued in the outbox.
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::Utc;
+use sqlx::PgPool;
+use tracing::field::{display, Empty};
+use uuid::Uuid;
+
+use crate::clients::{
+    email::{Email, EmailSender, TemplateRef, NOTIFICATION_ID_METADATA},
+    Providers,
+};
+use crate::db::{Notification, NotificationAttempt, NotificationChannel, NotificationStatus};
+use crate::services::alert::escalate_overdue_alerts;
+use crate::services::template::escape;
+
+/// Number of delivery attempts after which a notification is given up on.
+pub const MAX_ATTEMPTS: i32 = 6;
+/// Delay before the first retry. Each further retry waits twice as long.
+const BASE_BACKOFF: Duration = Duration::from_secs(30);
+/// Longest delay between two retries.
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+/// How long the worker sleeps when there is nothing to deliver or the database is unavailable.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Result of a single pass of the worker.
+#[derive(Debug, PartialEq)]
+pub enum ExecutionOutcome {
+    /// A notification was attempted, whether or not it was delivered.
+    TaskCompleted,
+    EmptyQueue,
+}
+
+/// Escalates overdue alerts and delivers queued notifications until the process exits.
+/// `base_url` is used to build the acknowledgement links of escalation notifications
+/// and the URL Twilio reports the outcome of calls to.
+pub async fn run_worker_until_stopped(pool: PgPool, providers: Providers, base_url: String) {
+    loop {
+        if let Err(e) = escalate_overdue_alerts(&pool, &base_url).await {
+            tracing::error!("Failed to escalate overdue alerts: {:?}", e);
+        }
+        match try_execute_task(&pool, &providers, &base_url).await {
+            Ok(ExecutionOutcome::TaskCompleted) => {}
+            Ok(ExecutionOutcome::EmptyQueue) => tokio::time::sleep(POLL_INTERVAL).await,
+            Err(e) => {
+                tracing::error!("Failed to process the notification outbox: {:?}", e);
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        }
+    }
+}
+
+/// Attempts to deliver the next due notification. The notification's row stays locked until its
+/// outcome is recorded, so several instances can run workers against the same outbox.
+#[tracing::instrument(
+    name = "services::notification_worker::try_execute_task",
+    skip(pool, providers, base_url),
+    fields(notification_id=Empty, channel=Empty)
+)]
+pub async fn try_execute_task(
+    pool: &PgPool,
+    providers: &Providers,
+    base_url: &str,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    let mut tx = pool.begin().await?;
+    let notification = sqlx::query_as!(
+        Notification,
+        r#"
+            SELECT * FROM notification
+            WHERE status = $1 AND next_attempt_at <= $2
+              AND qr_code_id IN (SELECT id FROM qr_code WHERE deleted_at IS NULL)
+            ORDER BY next_attempt_at
+            FOR UPDATE SKIP LOCKED
+            LIMIT 1"#,
+        NotificationStatus::Pending.as_str(),
+        Utc::now().naive_utc()
+    )
+    .fetch_optional(&mut tx)
+    .await?;
+    let notification = match notification {
+        Some(notification) => notification,
+        None => return Ok(ExecutionOutcome::EmptyQueue),
+    };
+    tracing::Span::current()
+        .record("notification_id", &display(notification.id))
+        .record("channel", &display(&notification.channel));
+
+    let (provider_sid, error) = match deliver(&notification, providers, base_url).await {
+        Ok(provider_sid) => (provider_sid, None),
+        Err(e) => (None, Some(format!("{:?}", e))),
+    };
+    if let Some(error) = &error {
+        tracing::warn!("Failed to deliver notification: {}", error);
+    }
+    NotificationAttempt::new(notification.id, error.clone())
+        .store(&mut tx)
+        .await?;
+
+    let attempts = notification.attempts + 1;
+    let now = Utc::now().naive_utc();
+    let (status, next_attempt_at, delivered_at) = match &error {
+        None => (NotificationStatus::Delivered, now, Some(now)),
+        Some(_) if attempts >= MAX_ATTEMPTS => (NotificationStatus::Dead, now, None),
+        Some(_) => (
+            NotificationStatus::Pending,
+            now + chrono::Duration::from_std(backoff(attempts))?,
+            None,
+        ),
+    };
+    sqlx::query!(
+        r#"
+            UPDATE notification
+            SET status = $2, attempts = $3, next_attempt_at = $4, last_error = $5, delivered_at = $6,
+                provider_sid = COALESCE($7, provider_sid)
+            WHERE id = $1"#,
+        notification.id,
+        status.as_str(),
+        attempts,
+        next_attempt_at,
+        error.or(notification.last_error),
+        delivered_at,
+        provider_sid
+    )
+    .execute(&mut tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(ExecutionOutcome::TaskCompleted)
+}
+
+/// Sends a notification through its channel's provider, returning the id the provider assigned to
+/// the message when it reports one at once. Twilio reports the id of calls through their status callback.
+async fn deliver(
+    notification: &Notification,
+    providers: &Providers,
+    base_url: &str,
+) -> Result<Option<String>, anyhow::Error> {
+    match NotificationChannel::from_str(&notification.channel)? {
+        NotificationChannel::Call => {
+            providers
+                .voice
+                .call(
+                    &notification.recipient,
+                    &notification.message,
+                    Some(voice_status_url(base_url, notification.id)),
+                )
+                .await?;
+            Ok(None)
+        }
+        NotificationChannel::Sms => {
+            providers
+                .sms
+                .send_sms(&notification.recipient, &notification.message)
+                .await?;
+            Ok(None)
+        }
+        NotificationChannel::Email => {
+            let email = notification_email(notification, providers.email.as_ref());
+            providers.email.send(&email).await
+        }
+    }
+}
+
+/// Builds the email of a notification, with the branded alert template when one is configured.
+/// Emails carry their notification's id so that Postmark's webhooks can be linked back to it.
+fn notification_email(notification: &Notification, mail: &dyn EmailSender) -> Email {
+    let html = notification
+        .html_message
+        .clone()
+        .unwrap_or_else(|| escape(&notification.message).replace('\n', "<br>"));
+    let email = match &mail.templates().alert {
+        Some(alias) => Email::template(
+            &notification.recipient,
+            TemplateRef::Alias(alias.clone()),
+            serde_json::json!({
+                "text_body": notification.message,
+                "html_body": html,
+            }),
+        ),
+        None if notification.html_message.is_some() => {
+            Email::html(&notification.recipient, &notification.message, html)
+        }
+        None => Email::text(&notification.recipient, &notification.message),
+    };
+    let email = match &notification.subject {
+        Some(subject) => email.subject(subject),
+        None => email,
+    };
+    email
+        .tag("alert")
+        .metadata(NOTIFICATION_ID_METADATA, notification.id.to_string())
+}
+
+/// URL Twilio reports the outcome of the call of the notification with the given id to.
+pub fn voice_status_url(base_url: &str, notification_id: Uuid) -> String {
+    format!(
+        "{}/webhooks/twilio/voice-status?notification_id={}",
+        base_url.trim_end_matches('/'),
+        notification_id
+    )
+}
+
+/// Delay before the attempt following the `attempts`-th failed one.
+fn backoff(attempts: i32) -> Duration {
+    let exponent = (attempts.max(1) - 1).min(16) as u32;
+    (BASE_BACKOFF * 2u32.pow(exponent)).min(MAX_BACKOFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::backoff;
+
+    #[test]
+    fn backoff_doubles_after_every_attempt() {
+        assert_eq!(Duration::from_secs(30), backoff(1));
+        assert_eq!(Duration::from_secs(60), backoff(2));
+        assert_eq!(Duration::from_secs(120), backoff(3));
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        assert_eq!(Duration::from_secs(60 * 60), backoff(10));
+        assert_eq!(Duration::from_secs(60 * 60), backoff(i32::MAX));
+    }
+}
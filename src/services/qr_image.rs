@@ -0,0 +1,429 @@
+//! Contains helpers for rendering QR codes as PNG and SVG images.
+use std::str::FromStr;
+
+use anyhow::Context;
+use image::{
+    codecs::png::PngEncoder, imageops::FilterType, ColorType, DynamicImage, GenericImageView,
+    ImageOutputFormat, Rgb, RgbImage,
+};
+use qrcode::{Color, EcLevel, QrCode};
+use serde::Deserialize;
+use uuid::Uuid;
+
+/// Smallest image dimension, in pixels, that may be requested.
+pub const MIN_IMAGE_SIZE: u32 = 64;
+/// Largest image dimension, in pixels, that may be requested.
+pub const MAX_IMAGE_SIZE: u32 = 2048;
+/// Image dimension, in pixels, used when none is requested.
+pub const DEFAULT_IMAGE_SIZE: u32 = 256;
+/// Largest quiet zone, in modules, that may be stored for a QR code.
+pub const MAX_QUIET_ZONE: u32 = 16;
+/// Fraction of the symbol's width covered by a logo.
+const LOGO_RATIO: f32 = 0.2;
+
+/// Image formats a QR code can be rendered to.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    Png,
+    Svg,
+}
+
+impl ImageFormat {
+    /// The MIME type to serve an image of this format with.
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Svg => "image/svg+xml",
+        }
+    }
+}
+
+impl Default for ImageFormat {
+    fn default() -> Self {
+        Self::Png
+    }
+}
+
+/// Error correction level of a QR symbol, from lowest (L, ~7%) to highest (H, ~30%).
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ErrorCorrection {
+    L,
+    M,
+    Q,
+    H,
+}
+
+impl Default for ErrorCorrection {
+    fn default() -> Self {
+        Self::M
+    }
+}
+
+impl From<ErrorCorrection> for EcLevel {
+    fn from(ecc: ErrorCorrection) -> Self {
+        match ecc {
+            ErrorCorrection::L => EcLevel::L,
+            ErrorCorrection::M => EcLevel::M,
+            ErrorCorrection::Q => EcLevel::Q,
+            ErrorCorrection::H => EcLevel::H,
+        }
+    }
+}
+
+/// Shape used to draw the dark modules of a QR symbol. Finder patterns are always square so
+/// that scanners can still locate the symbol.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ModuleShape {
+    Square,
+    Dot,
+}
+
+impl FromStr for ModuleShape {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "square" => Ok(Self::Square),
+            "dot" => Ok(Self::Dot),
+            other => Err(anyhow::anyhow!(
+                "{} is not a supported module shape. Use either `square` or `dot`.",
+                other
+            )),
+        }
+    }
+}
+
+/// Visual styling applied when rendering a QR code.
+#[derive(Debug, Clone)]
+pub struct QrStyle {
+    pub foreground: Rgb<u8>,
+    pub background: Rgb<u8>,
+    pub module_shape: ModuleShape,
+    /// Width of the blank border around the symbol, in modules.
+    pub quiet_zone: u32,
+    /// Image drawn over the center of the symbol.
+    pub logo: Option<DynamicImage>,
+}
+
+impl Default for QrStyle {
+    fn default() -> Self {
+        Self {
+            foreground: Rgb([0, 0, 0]),
+            background: Rgb([255, 255, 255]),
+            module_shape: ModuleShape::Square,
+            quiet_zone: 4,
+            logo: None,
+        }
+    }
+}
+
+/// Parses a color in `#rrggbb` notation.
+pub fn parse_hex_color(color: &str) -> Result<Rgb<u8>, anyhow::Error> {
+    let hex = color
+        .strip_prefix('#')
+        .filter(|hex| hex.len() == 6 && hex.is_ascii())
+        .ok_or_else(|| anyhow::anyhow!("{} is not a color in #rrggbb notation.", color))?;
+    let channel = |i: usize| {
+        u8::from_str_radix(&hex[i..i + 2], 16)
+            .with_context(|| format!("{} is not a color in #rrggbb notation.", color))
+    };
+    Ok(Rgb([channel(0)?, channel(2)?, channel(4)?]))
+}
+
+/// Decodes an uploaded PNG or JPEG logo.
+pub fn decode_logo(bytes: &[u8]) -> Result<DynamicImage, anyhow::Error> {
+    image::load_from_memory(bytes).context("Failed to decode logo as a PNG or JPEG image.")
+}
+
+/// Returns the URL encoded into the QR code with the given id.
+pub fn scan_url(base_url: &str, qr_code_id: Uuid) -> String {
+    format!("{}/scan?id={}", base_url.trim_end_matches('/'), qr_code_id)
+}
+
+/// Encodes `data` into a QR symbol and renders it as an image at least `size` pixels wide.
+///
+/// When the style includes a logo the symbol is always encoded with the highest error
+/// correction level, as the logo hides some of its modules.
+#[tracing::instrument(name = "services::qr_image::render", skip(data, style))]
+pub fn render(
+    data: &str,
+    format: ImageFormat,
+    size: u32,
+    ecc: ErrorCorrection,
+    style: &QrStyle,
+) -> Result<Vec<u8>, anyhow::Error> {
+    let symbol = Symbol::encode(data, ecc, style)?;
+
+    match format {
+        ImageFormat::Png => {
+            let image = rasterize(&symbol, size, style);
+            let mut bytes = Vec::new();
+            PngEncoder::new(&mut bytes)
+                .encode(&image, image.width(), image.height(), ColorType::Rgb8)
+                .context("Failed to encode QR code as PNG.")?;
+            Ok(bytes)
+        }
+        ImageFormat::Svg => render_svg(&symbol, size, style),
+    }
+}
+
+/// Encodes `data` into a QR symbol and draws it onto a bitmap at least `size` pixels wide.
+pub fn render_bitmap(
+    data: &str,
+    size: u32,
+    ecc: ErrorCorrection,
+    style: &QrStyle,
+) -> Result<RgbImage, anyhow::Error> {
+    let symbol = Symbol::encode(data, ecc, style)?;
+    Ok(rasterize(&symbol, size, style))
+}
+
+/// Module grid of an encoded QR code along with the area reserved for a logo.
+struct Symbol {
+    width: u32,
+    dark: Vec<bool>,
+    /// First and last module (inclusive) of the square left blank for a logo.
+    logo_area: Option<(u32, u32)>,
+}
+
+impl Symbol {
+    fn encode(data: &str, ecc: ErrorCorrection, style: &QrStyle) -> Result<Self, anyhow::Error> {
+        let ecc = if style.logo.is_some() {
+            ErrorCorrection::H
+        } else {
+            ecc
+        };
+        let code = QrCode::with_error_correction_level(data, ecc.into())
+            .context("Failed to encode data into a QR code.")?;
+        Ok(Self::new(&code, style))
+    }
+
+    fn new(code: &QrCode, style: &QrStyle) -> Self {
+        let width = code.width() as u32;
+        let dark = code
+            .to_colors()
+            .into_iter()
+            .map(|c| c == Color::Dark)
+            .collect();
+        let logo_area = style.logo.as_ref().map(|_| {
+            // Keep the logo area centered by giving it the same parity as the symbol
+            let mut modules = (width as f32 * LOGO_RATIO).ceil() as u32;
+            if modules % 2 != width % 2 {
+                modules += 1;
+            }
+            let start = (width - modules) / 2;
+            (start, start + modules - 1)
+        });
+        Self {
+            width,
+            dark,
+            logo_area,
+        }
+    }
+
+    fn is_dark(&self, x: u32, y: u32) -> bool {
+        let in_logo_area = self
+            .logo_area
+            .map(|(start, end)| (start..=end).contains(&x) && (start..=end).contains(&y))
+            .unwrap_or(false);
+        !in_logo_area && self.dark[(y * self.width + x) as usize]
+    }
+
+    fn is_finder_pattern(&self, x: u32, y: u32) -> bool {
+        let far = self.width - 7;
+        (x < 7 && y < 7) || (x >= far && y < 7) || (x < 7 && y >= far)
+    }
+
+    fn shape_at(&self, x: u32, y: u32, style: &QrStyle) -> ModuleShape {
+        if self.is_finder_pattern(x, y) {
+            ModuleShape::Square
+        } else {
+            style.module_shape
+        }
+    }
+}
+
+fn rasterize(symbol: &Symbol, size: u32, style: &QrStyle) -> RgbImage {
+    let total = symbol.width + 2 * style.quiet_zone;
+    let scale = ((size + total - 1) / total).max(1);
+    let dimension = total * scale;
+    let mut image = RgbImage::from_pixel(dimension, dimension, style.background);
+
+    for y in 0..symbol.width {
+        for x in 0..symbol.width {
+            if !symbol.is_dark(x, y) {
+                continue;
+            }
+            let left = (x + style.quiet_zone) * scale;
+            let top = (y + style.quiet_zone) * scale;
+            let shape = symbol.shape_at(x, y, style);
+            let radius = scale as f32 / 2.0;
+            for dy in 0..scale {
+                for dx in 0..scale {
+                    let inside = match shape {
+                        ModuleShape::Square => true,
+                        ModuleShape::Dot => {
+                            let (cx, cy) = (dx as f32 + 0.5 - radius, dy as f32 + 0.5 - radius);
+                            cx * cx + cy * cy <= radius * radius
+                        }
+                    };
+                    if inside {
+                        image.put_pixel(left + dx, top + dy, style.foreground);
+                    }
+                }
+            }
+        }
+    }
+
+    if let (Some(logo), Some((start, end))) = (&style.logo, symbol.logo_area) {
+        // Leave a one module margin between the logo and the surrounding modules
+        let area = (end - start + 1) * scale;
+        let fit = area.saturating_sub(2 * scale).max(1);
+        let logo = logo.resize(fit, fit, FilterType::Triangle).to_rgb8();
+        let origin = (start + style.quiet_zone) * scale;
+        let left = origin + (area - logo.width()) / 2;
+        let top = origin + (area - logo.height()) / 2;
+        image::imageops::overlay(&mut image, &logo, left, top);
+    }
+
+    image
+}
+
+fn render_svg(symbol: &Symbol, size: u32, style: &QrStyle) -> Result<Vec<u8>, anyhow::Error> {
+    let total = symbol.width + 2 * style.quiet_zone;
+    let scale = ((size + total - 1) / total).max(1);
+    let dimension = total * scale;
+    let qz = style.quiet_zone;
+
+    let mut squares = String::new();
+    let mut dots = String::new();
+    for y in 0..symbol.width {
+        for x in 0..symbol.width {
+            if !symbol.is_dark(x, y) {
+                continue;
+            }
+            match symbol.shape_at(x, y, style) {
+                ModuleShape::Square => {
+                    squares.push_str(&format!("M{} {}h1v1h-1z", x + qz, y + qz));
+                }
+                ModuleShape::Dot => dots.push_str(&format!(
+                    r#"<circle cx="{}.5" cy="{}.5" r="0.5"/>"#,
+                    x + qz,
+                    y + qz
+                )),
+            }
+        }
+    }
+
+    let mut svg = format!(
+        r#"<?xml version="1.0" standalone="yes"?><svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" version="1.1" width="{dim}" height="{dim}" viewBox="0 0 {total} {total}"><rect width="{total}" height="{total}" fill="{bg}"/><path fill="{fg}" shape-rendering="crispEdges" d="{squares}"/><g fill="{fg}">{dots}</g>"#,
+        dim = dimension,
+        total = total,
+        bg = hex_color(style.background),
+        fg = hex_color(style.foreground),
+        squares = squares,
+        dots = dots,
+    );
+
+    if let (Some(logo), Some((start, end))) = (&style.logo, symbol.logo_area) {
+        let area = (end - start + 1) as f32;
+        let fit = (area - 2.0).max(1.0);
+        let aspect = logo.width() as f32 / logo.height() as f32;
+        let (width, height) = if aspect >= 1.0 {
+            (fit, fit / aspect)
+        } else {
+            (fit * aspect, fit)
+        };
+        let origin = (start + qz) as f32;
+        let mut png = Vec::new();
+        logo.write_to(&mut png, ImageOutputFormat::Png)
+            .context("Failed to encode logo as PNG.")?;
+        svg.push_str(&format!(
+            r#"<image x="{}" y="{}" width="{}" height="{}" preserveAspectRatio="xMidYMid meet" xlink:href="data:image/png;base64,{}"/>"#,
+            origin + (area - width) / 2.0,
+            origin + (area - height) / 2.0,
+            width,
+            height,
+            base64::encode(&png)
+        ));
+    }
+
+    svg.push_str("</svg>");
+    Ok(svg.into_bytes())
+}
+
+fn hex_color(color: Rgb<u8>) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, Rgb};
+    use uuid::Uuid;
+
+    use super::{
+        parse_hex_color, render, scan_url, ErrorCorrection, ImageFormat, ModuleShape, QrStyle,
+    };
+
+    #[test]
+    fn scan_url_does_not_duplicate_slashes() {
+        let id = Uuid::new_v4();
+        assert_eq!(
+            format!("https://hermodapp.com/scan?id={}", id),
+            scan_url("https://hermodapp.com/", id)
+        );
+    }
+
+    #[test]
+    fn parse_hex_color_accepts_rrggbb_notation() {
+        assert_eq!(Rgb([0x12, 0xab, 0xff]), parse_hex_color("#12ABff").unwrap());
+        assert!(parse_hex_color("12abff").is_err());
+        assert!(parse_hex_color("#12abf").is_err());
+        assert!(parse_hex_color("#12abfg").is_err());
+    }
+
+    #[test]
+    fn render_png_produces_png_signature() {
+        let style = QrStyle::default();
+        let bytes = render("hello", ImageFormat::Png, 128, ErrorCorrection::M, &style).unwrap();
+        assert_eq!(&bytes[..8], b"\x89PNG\r\n\x1a\n");
+    }
+
+    #[test]
+    fn render_svg_uses_style_colors() {
+        let style = QrStyle {
+            foreground: Rgb([0x11, 0x22, 0x33]),
+            background: Rgb([0xfe, 0xdc, 0xba]),
+            module_shape: ModuleShape::Dot,
+            ..QrStyle::default()
+        };
+        let bytes = render("hello", ImageFormat::Svg, 128, ErrorCorrection::H, &style).unwrap();
+        let svg = String::from_utf8(bytes).unwrap();
+        assert!(svg.contains("<svg"));
+        assert!(svg.contains(r##"fill="#112233""##));
+        assert!(svg.contains(r##"fill="#fedcba""##));
+        assert!(svg.contains("<circle"));
+    }
+
+    #[test]
+    fn render_embeds_logo_in_svg() {
+        let style = QrStyle {
+            logo: Some(DynamicImage::new_rgb8(32, 16)),
+            ..QrStyle::default()
+        };
+        let bytes = render("hello", ImageFormat::Svg, 128, ErrorCorrection::L, &style).unwrap();
+        let svg = String::from_utf8(bytes).unwrap();
+        assert!(svg.contains("data:image/png;base64,"));
+    }
+
+    #[test]
+    fn render_draws_logo_in_png() {
+        let style = QrStyle {
+            logo: Some(DynamicImage::new_rgb8(16, 32)),
+            ..QrStyle::default()
+        };
+        let bytes = render("hello", ImageFormat::Png, 300, ErrorCorrection::L, &style).unwrap();
+        assert_eq!(&bytes[..8], b"\x89PNG\r\n\x1a\n");
+    }
+}
@@ -0,0 +1,267 @@
+//! Contains helpers for laying out QR codes on printable PDF label sheets.
+use std::io::BufWriter;
+
+use anyhow::Context;
+use image::DynamicImage;
+use printpdf::{BuiltinFont, Image, IndirectFontRef, Mm, PdfDocument, PdfLayerReference};
+use serde::Deserialize;
+
+use crate::services::qr_image::{self, ErrorCorrection, QrStyle};
+
+/// Resolution QR codes are rasterized at before being placed on a sheet.
+const DPI: f64 = 200.0;
+/// Font size of captions, in points.
+const CAPTION_FONT_SIZE: f64 = 8.0;
+/// Approximate height of a caption line, in millimeters.
+const CAPTION_HEIGHT: f64 = 4.0;
+/// Approximate width of an average Helvetica character at `CAPTION_FONT_SIZE`, in millimeters.
+const CAPTION_CHAR_WIDTH: f64 = 1.45;
+/// Blank space kept between a label's edge and its content, in millimeters.
+const LABEL_PADDING: f64 = 2.0;
+
+/// Label layouts a sheet of QR codes can be printed on.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum SheetLayout {
+    /// US Letter, 3 x 10 address labels of 2⅝" x 1".
+    #[serde(rename = "avery-5160")]
+    Avery5160,
+    /// US Letter, 4 x 6 square labels of 1½" x 1½".
+    #[serde(rename = "avery-22805")]
+    Avery22805,
+    /// A4, 3 x 7 address labels of 63.5mm x 38.1mm.
+    #[serde(rename = "avery-l7160")]
+    AveryL7160,
+    /// US Letter, plain 3 x 4 grid.
+    #[serde(rename = "letter-grid")]
+    LetterGrid,
+    /// A4, plain 3 x 4 grid.
+    #[serde(rename = "a4-grid")]
+    A4Grid,
+}
+
+impl Default for SheetLayout {
+    fn default() -> Self {
+        Self::LetterGrid
+    }
+}
+
+/// Physical dimensions of a label sheet, in millimeters.
+struct Geometry {
+    page_width: f64,
+    page_height: f64,
+    columns: u32,
+    rows: u32,
+    label_width: f64,
+    label_height: f64,
+    margin_left: f64,
+    margin_top: f64,
+    pitch_x: f64,
+    pitch_y: f64,
+}
+
+impl SheetLayout {
+    fn geometry(&self) -> Geometry {
+        const LETTER: (f64, f64) = (215.9, 279.4);
+        const A4: (f64, f64) = (210.0, 297.0);
+        let ((page_width, page_height), columns, rows, label, margin, pitch) = match self {
+            Self::Avery5160 => (LETTER, 3, 10, (66.675, 25.4), (4.7625, 12.7), (69.85, 25.4)),
+            Self::Avery22805 => (LETTER, 4, 6, (38.1, 38.1), (12.7, 9.525), (50.8, 44.45)),
+            Self::AveryL7160 => (A4, 3, 7, (63.5, 38.1), (7.25, 15.15), (66.04, 38.1)),
+            Self::LetterGrid => (LETTER, 3, 4, (63.5, 63.5), (12.7, 12.7), (63.5, 63.5)),
+            Self::A4Grid => (A4, 3, 4, (60.0, 66.75), (15.0, 15.0), (60.0, 66.75)),
+        };
+        Geometry {
+            page_width,
+            page_height,
+            columns,
+            rows,
+            label_width: label.0,
+            label_height: label.1,
+            margin_left: margin.0,
+            margin_top: margin.1,
+            pitch_x: pitch.0,
+            pitch_y: pitch.1,
+        }
+    }
+}
+
+/// A single QR code to print on a sheet.
+pub struct SheetEntry {
+    /// Data encoded into the QR code.
+    pub data: String,
+    /// Text printed next to the QR code.
+    pub caption: String,
+    pub style: QrStyle,
+}
+
+/// Renders QR codes onto as many pages of labels as needed and returns the PDF document.
+#[tracing::instrument(name = "services::qr_sheet::render_sheet", skip(entries))]
+pub fn render_sheet(entries: &[SheetEntry], layout: SheetLayout) -> Result<Vec<u8>, anyhow::Error> {
+    let geometry = layout.geometry();
+    let per_page = (geometry.columns * geometry.rows) as usize;
+    let (document, page, layer) = PdfDocument::new(
+        "Hermod QR codes",
+        Mm(geometry.page_width),
+        Mm(geometry.page_height),
+        "Labels",
+    );
+    let font = document
+        .add_builtin_font(BuiltinFont::Helvetica)
+        .context("Failed to load PDF font.")?;
+
+    let mut layer = document.get_page(page).get_layer(layer);
+    for (i, entry) in entries.iter().enumerate() {
+        if i > 0 && i % per_page == 0 {
+            let (page, new_layer) =
+                document.add_page(Mm(geometry.page_width), Mm(geometry.page_height), "Labels");
+            layer = document.get_page(page).get_layer(new_layer);
+        }
+        let slot = (i % per_page) as u32;
+        let column = slot % geometry.columns;
+        let row = slot / geometry.columns;
+        let left = geometry.margin_left + column as f64 * geometry.pitch_x;
+        let top = geometry.page_height - geometry.margin_top - row as f64 * geometry.pitch_y;
+        draw_label(&layer, &font, &geometry, left, top, entry)?;
+    }
+
+    let mut writer = BufWriter::new(Vec::new());
+    document
+        .save(&mut writer)
+        .context("Failed to write PDF document.")?;
+    writer.into_inner().context("Failed to flush PDF document.")
+}
+
+/// Draws a QR code and its caption into the label whose top-left corner is at (`left`, `top`).
+/// Wide labels place the caption to the right of the code, others place it underneath.
+fn draw_label(
+    layer: &PdfLayerReference,
+    font: &IndirectFontRef,
+    geometry: &Geometry,
+    left: f64,
+    top: f64,
+    entry: &SheetEntry,
+) -> Result<(), anyhow::Error> {
+    let inner_width = geometry.label_width - 2.0 * LABEL_PADDING;
+    let inner_height = geometry.label_height - 2.0 * LABEL_PADDING;
+    let side_by_side = geometry.label_width >= 1.5 * geometry.label_height;
+
+    let code_size = if side_by_side {
+        inner_height
+    } else {
+        inner_width.min(inner_height - CAPTION_HEIGHT)
+    };
+    let (code_left, code_bottom) = if side_by_side {
+        (left + LABEL_PADDING, top - LABEL_PADDING - code_size)
+    } else {
+        (
+            left + (geometry.label_width - code_size) / 2.0,
+            top - LABEL_PADDING - code_size,
+        )
+    };
+
+    let pixels = (code_size / 25.4 * DPI).round() as u32;
+    let bitmap = qr_image::render_bitmap(&entry.data, pixels, ErrorCorrection::M, &entry.style)?;
+    // The bitmap may be larger than requested, so scale it back to the space available
+    let dpi = bitmap.width() as f64 / (code_size / 25.4);
+    let mut image = Image::from_dynamic_image(&DynamicImage::ImageRgb8(bitmap));
+    // Smoothing would blur the edges between modules
+    image.image.interpolate = false;
+    image.add_to_layer(
+        layer.clone(),
+        Some(Mm(code_left)),
+        Some(Mm(code_bottom)),
+        None,
+        None,
+        None,
+        Some(dpi),
+    );
+
+    let caption_width = if side_by_side {
+        inner_width - code_size - LABEL_PADDING
+    } else {
+        inner_width
+    };
+    let caption = truncate_caption(&entry.caption, caption_width);
+    let text_width = caption.chars().count() as f64 * CAPTION_CHAR_WIDTH;
+    let (caption_left, caption_bottom) = if side_by_side {
+        (
+            code_left + code_size + LABEL_PADDING,
+            top - geometry.label_height / 2.0 - CAPTION_HEIGHT / 4.0,
+        )
+    } else {
+        (
+            left + (geometry.label_width - text_width).max(0.0) / 2.0,
+            code_bottom - CAPTION_HEIGHT + 1.0,
+        )
+    };
+    layer.use_text(
+        caption,
+        CAPTION_FONT_SIZE,
+        Mm(caption_left),
+        Mm(caption_bottom),
+        font,
+    );
+
+    Ok(())
+}
+
+/// Shortens `caption` with an ellipsis so that it fits in `width` millimeters.
+fn truncate_caption(caption: &str, width: f64) -> String {
+    let max_chars = (width / CAPTION_CHAR_WIDTH).floor().max(3.0) as usize;
+    if caption.chars().count() <= max_chars {
+        caption.to_string()
+    } else {
+        let mut truncated: String = caption.chars().take(max_chars - 3).collect();
+        truncated.push_str("...");
+        truncated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_sheet, truncate_caption, SheetEntry, SheetLayout};
+    use crate::services::qr_image::QrStyle;
+
+    fn entries(count: usize) -> Vec<SheetEntry> {
+        (0..count)
+            .map(|i| SheetEntry {
+                data: format!("https://hermodapp.com/scan?id={}", i),
+                caption: format!("Table {}", i),
+                style: QrStyle::default(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn truncate_caption_keeps_short_captions() {
+        assert_eq!("Table 1", truncate_caption("Table 1", 50.0));
+    }
+
+    #[test]
+    fn truncate_caption_shortens_long_captions() {
+        let caption = truncate_caption(&"x".repeat(100), 14.5);
+        assert_eq!("xxxxxxx...", caption);
+    }
+
+    #[test]
+    fn render_sheet_produces_pdf_for_every_layout() {
+        for layout in [
+            SheetLayout::Avery5160,
+            SheetLayout::Avery22805,
+            SheetLayout::AveryL7160,
+            SheetLayout::LetterGrid,
+            SheetLayout::A4Grid,
+        ] {
+            let pdf = render_sheet(&entries(2), layout).unwrap();
+            assert_eq!(b"%PDF", &pdf[..4]);
+        }
+    }
+
+    #[test]
+    fn render_sheet_adds_pages_when_a_sheet_is_full() {
+        let one_page = render_sheet(&entries(12), SheetLayout::LetterGrid).unwrap();
+        let two_pages = render_sheet(&entries(13), SheetLayout::LetterGrid).unwrap();
+        assert!(String::from_utf8_lossy(&one_page).contains("/Type/Pages/Count 1"));
+        assert!(String::from_utf8_lossy(&two_pages).contains("/Type/Pages/Count 2"));
+    }
+}
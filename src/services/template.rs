@@ -0,0 +1,370 @@
+//! Contains the template language used to write the messages sent when a QR code is scanned.
+//!
+//! Templates are plain text with `{{ variable | filter | ... }}` expressions, e.g.
+//! `Table {{qr.label}} needs help at {{scan.time | local | time}}`.
+use chrono::{Duration, NaiveDateTime};
+use uuid::Uuid;
+
+/// Template used for QR codes without a payload.
+pub const DEFAULT_TEMPLATE: &str =
+    "Hermod QR code {{scan.location}} was scanned at {{scan.time | local | time}}.";
+
+/// Values a template can refer to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Variable {
+    QrId,
+    QrLabel,
+    FormId,
+    FormTitle,
+    ScanTime,
+    ScanLocation,
+}
+
+impl Variable {
+    fn parse(name: &str) -> Result<Self, anyhow::Error> {
+        match name {
+            "qr.id" => Ok(Self::QrId),
+            "qr.label" => Ok(Self::QrLabel),
+            "form.id" => Ok(Self::FormId),
+            "form.title" => Ok(Self::FormTitle),
+            "scan.time" => Ok(Self::ScanTime),
+            "scan.location" => Ok(Self::ScanLocation),
+            other => Err(anyhow::anyhow!(
+                "Unknown template variable `{}`. Use one of qr.id, qr.label, form.id, form.title, scan.time or scan.location.",
+                other
+            )),
+        }
+    }
+
+    fn kind(&self) -> Kind {
+        match self {
+            Self::ScanTime => Kind::Time,
+            _ => Kind::Text,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Text,
+    Time,
+}
+
+/// Transformations applied to a variable with `|`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Filter {
+    /// Converts a time to the recipient's local time.
+    Local,
+    /// Formats a time as `HH:MM`.
+    Time,
+    /// Formats a time as `YYYY-MM-DD`.
+    Date,
+    Upper,
+    Lower,
+}
+
+impl Filter {
+    fn parse(name: &str) -> Result<Self, anyhow::Error> {
+        match name {
+            "local" => Ok(Self::Local),
+            "time" => Ok(Self::Time),
+            "date" => Ok(Self::Date),
+            "upper" => Ok(Self::Upper),
+            "lower" => Ok(Self::Lower),
+            other => Err(anyhow::anyhow!(
+                "Unknown template filter `{}`. Use one of local, time, date, upper or lower.",
+                other
+            )),
+        }
+    }
+
+    /// Kind of value the filter produces from a value of kind `input`, if it accepts it.
+    fn output(&self, input: Kind) -> Option<Kind> {
+        match (self, input) {
+            (Self::Local, Kind::Time) => Some(Kind::Time),
+            (Self::Time, Kind::Time) | (Self::Date, Kind::Time) => Some(Kind::Text),
+            (Self::Upper, Kind::Text) | (Self::Lower, Kind::Text) => Some(Kind::Text),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Expression {
+        variable: Variable,
+        filters: Vec<Filter>,
+    },
+}
+
+/// A parsed message template.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+/// Values available while rendering a template for a single scan.
+#[derive(Debug, Clone)]
+pub struct TemplateContext {
+    pub qr_id: Uuid,
+    pub qr_label: Option<String>,
+    pub form_id: Option<Uuid>,
+    pub form_title: Option<String>,
+    /// Time of the scan, in UTC.
+    pub scan_time: NaiveDateTime,
+    /// Offset of the recipient's local time from UTC, used by the `local` filter.
+    pub utc_offset_minutes: i32,
+}
+
+/// A message rendered for every kind of channel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedMessage {
+    /// Plain text, used for SMS messages, phone calls and the text part of emails.
+    pub text: String,
+    /// HTML part of emails.
+    pub html: String,
+}
+
+/// A value being rendered, before or after filters are applied.
+enum Value {
+    Text(String),
+    Time { time: NaiveDateTime, local: bool },
+}
+
+impl Template {
+    /// Parses a template, rejecting unknown variables and filters and filters applied to the wrong kind of value.
+    pub fn parse(source: &str) -> Result<Self, anyhow::Error> {
+        let mut parts = Vec::new();
+        let mut rest = source;
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                parts.push(Part::Text(rest[..start].to_string()));
+            }
+            let end = rest[start..].find("}}").ok_or_else(|| {
+                anyhow::anyhow!("Template expression is missing its closing `}}}}`.")
+            })?;
+            parts.push(parse_expression(&rest[start + 2..start + end])?);
+            rest = &rest[start + end + 2..];
+        }
+        if rest.contains("}}") {
+            return Err(anyhow::anyhow!(
+                "Template contains a `}}}}` without an opening `{{{{`."
+            ));
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest.to_string()));
+        }
+        Ok(Self { parts })
+    }
+
+    /// Creates a template that renders `text` verbatim, for payloads written before templates were validated.
+    pub fn literal(text: &str) -> Self {
+        Self {
+            parts: vec![Part::Text(text.to_string())],
+        }
+    }
+
+    /// Renders the template as plain text.
+    pub fn render(&self, context: &TemplateContext) -> String {
+        let mut output = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => output.push_str(text),
+                Part::Expression { variable, filters } => {
+                    let value = filters
+                        .iter()
+                        .fold(resolve(*variable, context), |value, filter| {
+                            apply(*filter, value, context)
+                        });
+                    match value {
+                        Value::Text(text) => output.push_str(&text),
+                        Value::Time { time, local: true } => {
+                            output.push_str(&time.format("%Y-%m-%d %H:%M").to_string())
+                        }
+                        Value::Time { time, local: false } => {
+                            output.push_str(&time.format("%Y-%m-%d %H:%M UTC").to_string())
+                        }
+                    }
+                }
+            }
+        }
+        output
+    }
+
+    /// Renders the template for every kind of channel.
+    pub fn render_message(&self, context: &TemplateContext) -> RenderedMessage {
+        RenderedMessage::from_text(self.render(context))
+    }
+}
+
+impl RenderedMessage {
+    /// Wraps already rendered plain text, deriving its HTML part.
+    pub fn from_text(text: String) -> Self {
+        Self {
+            html: format!(
+                "<p>{}</p>",
+                escape(&text).replace("\r\n", "\n").replace('\n', "<br>")
+            ),
+            text,
+        }
+    }
+}
+
+fn parse_expression(expression: &str) -> Result<Part, anyhow::Error> {
+    let mut segments = expression.split('|').map(str::trim);
+    let variable = Variable::parse(segments.next().unwrap_or_default())?;
+    let mut kind = variable.kind();
+    let mut filters = Vec::new();
+    for name in segments {
+        let filter = Filter::parse(name)?;
+        kind = filter.output(kind).ok_or_else(|| {
+            anyhow::anyhow!(
+                "The `{}` filter cannot be applied to {}.",
+                name,
+                match kind {
+                    Kind::Text => "text",
+                    Kind::Time => "a time",
+                }
+            )
+        })?;
+        filters.push(filter);
+    }
+    Ok(Part::Expression { variable, filters })
+}
+
+fn resolve(variable: Variable, context: &TemplateContext) -> Value {
+    let text = |value: Option<String>| Value::Text(value.unwrap_or_default());
+    match variable {
+        Variable::QrId => Value::Text(context.qr_id.to_string()),
+        Variable::QrLabel => text(context.qr_label.clone()),
+        Variable::FormId => text(context.form_id.map(|id| id.to_string())),
+        Variable::FormTitle => text(context.form_title.clone()),
+        Variable::ScanTime => Value::Time {
+            time: context.scan_time,
+            local: false,
+        },
+        Variable::ScanLocation => Value::Text(
+            context
+                .qr_label
+                .clone()
+                .unwrap_or_else(|| context.qr_id.to_string()),
+        ),
+    }
+}
+
+/// Applies a filter. Parsing guarantees that the value has the kind the filter expects.
+fn apply(filter: Filter, value: Value, context: &TemplateContext) -> Value {
+    match (filter, value) {
+        (Filter::Local, Value::Time { time, .. }) => Value::Time {
+            time: time + Duration::minutes(context.utc_offset_minutes as i64),
+            local: true,
+        },
+        (Filter::Time, Value::Time { time, .. }) => Value::Text(time.format("%H:%M").to_string()),
+        (Filter::Date, Value::Time { time, .. }) => {
+            Value::Text(time.format("%Y-%m-%d").to_string())
+        }
+        (Filter::Upper, Value::Text(text)) => Value::Text(text.to_uppercase()),
+        (Filter::Lower, Value::Text(text)) => Value::Text(text.to_lowercase()),
+        (_, value) => value,
+    }
+}
+
+/// Escapes text for use in HTML documents.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use uuid::Uuid;
+
+    use super::{Template, TemplateContext, DEFAULT_TEMPLATE};
+
+    fn context() -> TemplateContext {
+        TemplateContext {
+            qr_id: Uuid::nil(),
+            qr_label: Some("4".to_string()),
+            form_id: None,
+            form_title: Some("Feedback".to_string()),
+            scan_time: NaiveDate::from_ymd(2021, 12, 8).and_hms(18, 5, 0),
+            utc_offset_minutes: -6 * 60,
+        }
+    }
+
+    #[test]
+    fn variables_and_filters_are_rendered() {
+        let template =
+            Template::parse("Table {{qr.label}} needs help at {{ scan.time | local | time }}")
+                .unwrap();
+        assert_eq!("Table 4 needs help at 12:05", template.render(&context()));
+    }
+
+    #[test]
+    fn times_are_rendered_in_utc_unless_localized() {
+        let template = Template::parse("{{scan.time}} / {{scan.time | local}}").unwrap();
+        assert_eq!(
+            "2021-12-08 18:05 UTC / 2021-12-08 12:05",
+            template.render(&context())
+        );
+    }
+
+    #[test]
+    fn missing_values_render_as_empty_text() {
+        let template = Template::parse("[{{form.id}}] {{form.title | upper}}").unwrap();
+        assert_eq!("[] FEEDBACK", template.render(&context()));
+    }
+
+    #[test]
+    fn text_without_expressions_is_kept_verbatim() {
+        let template = Template::parse("Someone needs help!").unwrap();
+        assert_eq!("Someone needs help!", template.render(&context()));
+    }
+
+    #[test]
+    fn invalid_templates_are_rejected() {
+        for source in [
+            "{{qr.name}}",
+            "{{scan.time | shout}}",
+            "{{qr.label | local}}",
+            "{{scan.time | upper}}",
+            "{{qr.label",
+            "qr.label}}",
+        ] {
+            assert!(Template::parse(source).is_err(), "{} was accepted", source);
+        }
+    }
+
+    #[test]
+    fn messages_are_escaped_for_html() {
+        let mut context = context();
+        context.qr_label = Some("<Patio & Bar>".to_string());
+        let message = Template::parse("{{qr.label}}\nneeds help")
+            .unwrap()
+            .render_message(&context);
+        assert_eq!("<Patio & Bar>\nneeds help", message.text);
+        assert_eq!("<p>&lt;Patio &amp; Bar&gt;<br>needs help</p>", message.html);
+    }
+
+    #[test]
+    fn default_template_is_valid() {
+        assert_eq!(
+            "Hermod QR code 4 was scanned at 12:05.",
+            Template::parse(DEFAULT_TEMPLATE)
+                .unwrap()
+                .render(&context())
+        );
+    }
+}
@@ -0,0 +1,82 @@
+//! Contains the background purge of forms and QR codes that have stayed in the trash for longer
+//! than the retention period.
+use std::time::Duration;
+
+use chrono::{NaiveDateTime, Utc};
+use sqlx::PgPool;
+
+/// Number of days deleted forms and QR codes can be restored before they are purged.
+pub const TRASH_RETENTION_DAYS: i64 = 30;
+/// How long the purge sleeps between two passes.
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Time a form or QR code deleted at `deleted_at` is purged.
+pub fn purge_at(deleted_at: NaiveDateTime) -> NaiveDateTime {
+    deleted_at + chrono::Duration::days(TRASH_RETENTION_DAYS)
+}
+
+/// Purges forms and QR codes whose retention period is over until the process exits.
+pub async fn run_purge_until_stopped(pool: PgPool) {
+    loop {
+        let before = Utc::now().naive_utc() - chrono::Duration::days(TRASH_RETENTION_DAYS);
+        if let Err(e) = purge_deleted(&pool, before).await {
+            tracing::error!("Failed to purge the trash: {:?}", e);
+        }
+        tokio::time::sleep(PURGE_INTERVAL).await;
+    }
+}
+
+/// Permanently deletes the forms and QR codes moved to the trash before `before`, along with
+/// their responses and scans. Returns how many forms and QR codes were purged.
+#[tracing::instrument(name = "services::trash::purge_deleted", skip(pool))]
+pub async fn purge_deleted(
+    pool: &PgPool,
+    before: NaiveDateTime,
+) -> Result<(u64, u64), anyhow::Error> {
+    let mut tx = pool.begin().await?;
+
+    // Responses and fields do not cascade with their form, so they are removed first
+    sqlx::query!(
+        r#"DELETE FROM feedback USING response, form
+           WHERE feedback.response_id = response.id AND response.form_id = form.id
+           AND form.deleted_at < $1"#,
+        before
+    )
+    .execute(&mut tx)
+    .await?;
+    sqlx::query!(
+        "DELETE FROM response USING form WHERE response.form_id = form.id AND form.deleted_at < $1",
+        before
+    )
+    .execute(&mut tx)
+    .await?;
+    sqlx::query!(
+        "DELETE FROM form_input USING form WHERE form_input.form_id = form.id AND form.deleted_at < $1",
+        before
+    )
+    .execute(&mut tx)
+    .await?;
+    // QR codes linking to a purged form stop redirecting to it
+    sqlx::query!(
+        "UPDATE qr_code SET form_id = NULL FROM form WHERE qr_code.form_id = form.id AND form.deleted_at < $1",
+        before
+    )
+    .execute(&mut tx)
+    .await?;
+    let forms = sqlx::query!("DELETE FROM form WHERE deleted_at < $1", before)
+        .execute(&mut tx)
+        .await?
+        .rows_affected();
+
+    // Scans, alerts, notifications and recipients cascade with their QR code
+    let qr_codes = sqlx::query!("DELETE FROM qr_code WHERE deleted_at < $1", before)
+        .execute(&mut tx)
+        .await?
+        .rows_affected();
+
+    tx.commit().await?;
+    if forms > 0 || qr_codes > 0 {
+        tracing::info!("Purged {} forms and {} QR codes", forms, qr_codes);
+    }
+    Ok((forms, qr_codes))
+}
@@ -1,25 +1,60 @@
 //! Contains code neccessary to bootstrap the application and run the server.
 use actix_cors::Cors;
 
+use crate::clients::email::EmailSender;
+use crate::clients::local::LocalOutbox;
+use crate::clients::phone::{SmsSender, VoiceCaller};
 use crate::clients::postmark::PostmarkClient;
+use crate::clients::smtp::SmtpClient;
 use crate::clients::twilio::TwilioClient;
+use crate::clients::Providers;
 use crate::handlers::{
-    delete_qr_code, edit_form, edit_qr_code, forgot_password, generate_qr_code, get_form,
-    health_check, list_qr_codes, login, logout, register, reset_password, scan, store_form,
-    store_form_response, test_email, view_forms, who_am_i,
+    acknowledge_alert_link, clear_email_suppression, confirm_alert_acknowledgement,
+    create_recipient, delete_form, delete_form_template, delete_logo, delete_qr_code,
+    delete_recipient, duplicate_form, edit_form, edit_form_notifications, edit_form_rules,
+    edit_form_status, edit_qr_code, edit_recipient, export_form, forgot_password, generate_qr_code,
+    get_form, get_form_document_schema, get_form_notifications, get_form_status, get_logo,
+    get_qr_code_image, get_qr_code_notifications, get_qr_code_sheet, get_qr_code_stats,
+    health_check, import_form, list_alerts, list_email_suppressions, list_form_templates,
+    list_qr_codes, list_recipients, list_trash, login, logout, postmark_webhook, register,
+    reset_password, restore_form, restore_qr_code, save_form_template, scan, store_form,
+    store_form_response, test_email, twilio_gather, twilio_sms, twilio_voice_status, upload_logo,
+    use_form_template, view_forms, who_am_i,
 };
-use crate::services::configuration::DatabaseSettings;
 use crate::services::configuration::Settings;
+use crate::services::configuration::{DatabaseSettings, EmailBackend, PhoneBackend};
+use crate::services::form_validation::MAX_RESPONSE_BODY_SIZE;
 use crate::services::jwt::JwtClient;
+use crate::services::notification_worker::run_worker_until_stopped;
+use crate::services::trash::run_purge_until_stopped;
 use actix_web::dev::Server;
 use actix_web::web::Data;
 use actix_web::{web, App, HttpServer};
 use sqlx::postgres::PgPoolOptions;
 use sqlx::{ConnectOptions, PgPool};
-use std::net::TcpListener;
+use std::net::{IpAddr, TcpListener};
+use std::path::PathBuf;
+use std::sync::Arc;
 use tracing::log::LevelFilter;
 use tracing_actix_web::TracingLogger;
 
+/// Public base URL of the application, shared with handlers that need to build absolute links.
+pub struct ApplicationBaseUrl(pub String);
+
+/// Settings used to tell scanners apart without storing their addresses.
+pub struct ScanClientSettings {
+    /// Secret salt used to hash scanner addresses before they are stored.
+    pub hash_salt: String,
+    /// Reverse proxies whose `X-Forwarded-For` headers are trusted.
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+/// Basic Auth credentials expected on requests made by Postmark's webhooks.
+pub struct PostmarkWebhookCredentials {
+    pub username: String,
+    pub password: String,
+}
+
 /// Represents the server application.
 pub struct Application {
     port: u16,
@@ -33,11 +68,14 @@ impl Application {
             .await
             .expect("Failed to connect to Postgres.");
 
+        let providers = build_providers(&configuration)?;
+
         let jwt_client = JwtClient::new(
             configuration.application.jwt_signing_key,
             connection_pool.clone(),
         );
 
+        // Twilio's webhooks are verified with the account's auth token whichever backend sends SMS messages and calls
         let twilio_client = TwilioClient::new(
             configuration.twilio.base_url,
             std::time::Duration::from_secs(5),
@@ -46,18 +84,20 @@ impl Application {
             configuration.twilio.from,
         );
 
-        let postmark_client = PostmarkClient::new(
-            configuration.postmark.base_url,
-            std::time::Duration::from_secs(5),
-            configuration.postmark.server_auth_token,
-            configuration.postmark.from,
-        );
-
         sqlx::migrate!("./migrations")
             .run(&connection_pool)
             .await
             .expect("Failed to migrate the database");
 
+        // Deliver notifications queued by scans and escalate unacknowledged alerts in the background
+        tokio::spawn(run_worker_until_stopped(
+            connection_pool.clone(),
+            providers.clone(),
+            configuration.application.base_url.clone(),
+        ));
+        // Purge forms and QR codes that have been in the trash for longer than the retention period
+        tokio::spawn(run_purge_until_stopped(connection_pool.clone()));
+
         let address = format!(
             "{}:{}",
             configuration.application.host, configuration.application.port
@@ -70,7 +110,16 @@ impl Application {
             connection_pool,
             jwt_client,
             twilio_client,
-            postmark_client,
+            providers,
+            configuration.application.base_url,
+            ScanClientSettings {
+                hash_salt: configuration.application.scan_hash_salt,
+                trusted_proxies: configuration.application.trusted_proxies,
+            },
+            PostmarkWebhookCredentials {
+                username: configuration.postmark.webhook_username,
+                password: configuration.postmark.webhook_password,
+            },
         )?;
 
         Ok(Self { port, server })
@@ -87,6 +136,62 @@ impl Application {
     }
 }
 
+/// Builds the backends the delivery settings select for emails, SMS messages and calls.
+fn build_providers(configuration: &Settings) -> Result<Providers, std::io::Error> {
+    let local = Arc::new(LocalOutbox::new(
+        configuration
+            .delivery
+            .local_directory
+            .as_ref()
+            .map(PathBuf::from),
+    ));
+    let email: Arc<dyn EmailSender> = match configuration.delivery.email {
+        EmailBackend::Postmark => Arc::new(PostmarkClient::new(
+            configuration.postmark.base_url.clone(),
+            std::time::Duration::from_secs(5),
+            configuration.postmark.server_auth_token.clone(),
+            configuration.postmark.from.clone(),
+            configuration.postmark.templates.clone(),
+        )),
+        EmailBackend::Smtp => {
+            let smtp = configuration.smtp.as_ref().ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "The smtp email backend requires smtp settings.",
+                )
+            })?;
+            let credentials = match (&smtp.username, &smtp.password) {
+                (Some(username), Some(password)) => Some((username.clone(), password.clone())),
+                _ => None,
+            };
+            Arc::new(SmtpClient::new(
+                smtp.host.clone(),
+                smtp.port,
+                smtp.tls,
+                credentials,
+                smtp.from.clone(),
+                std::time::Duration::from_secs(10),
+            ))
+        }
+        EmailBackend::Local => local.clone(),
+    };
+    let (sms, voice): (Arc<dyn SmsSender>, Arc<dyn VoiceCaller>) =
+        match configuration.delivery.phone {
+            PhoneBackend::Twilio => {
+                let twilio = Arc::new(TwilioClient::new(
+                    configuration.twilio.base_url.clone(),
+                    std::time::Duration::from_secs(5),
+                    configuration.twilio.account_sid.clone(),
+                    configuration.twilio.auth_token.clone(),
+                    configuration.twilio.from.clone(),
+                ));
+                (twilio.clone(), twilio)
+            }
+            PhoneBackend::Local => (local.clone(), local),
+        };
+    Ok(Providers { email, sms, voice })
+}
+
 /// Given a configuration, returns a pool of Postgres database connections.
 pub async fn get_connection_pool(configuration: &DatabaseSettings) -> Result<PgPool, sqlx::Error> {
     let db_connect_options = configuration
@@ -105,12 +210,18 @@ fn run(
     db_pool: PgPool,
     jwt_client: JwtClient,
     twilio_client: TwilioClient,
-    postmark_client: PostmarkClient,
+    providers: Providers,
+    base_url: String,
+    scan_client_settings: ScanClientSettings,
+    postmark_webhook_credentials: PostmarkWebhookCredentials,
 ) -> Result<Server, std::io::Error> {
     let db_pool = Data::new(db_pool);
     let jwt_client = Data::new(jwt_client);
     let twilio_client = Data::new(twilio_client);
-    let postmark_client = Data::new(postmark_client);
+    let providers = Data::new(providers);
+    let base_url = Data::new(ApplicationBaseUrl(base_url));
+    let scan_client_settings = Data::new(scan_client_settings);
+    let postmark_webhook_credentials = Data::new(postmark_webhook_credentials);
 
     let server = HttpServer::new(move || {
         let cors = Cors::permissive();
@@ -126,21 +237,93 @@ fn run(
             .route("/password/forgot", web::post().to(forgot_password))
             .route("/password/reset", web::post().to(reset_password))
             .route("/health_check", web::get().to(health_check))
+            .route("/account/logo", web::get().to(get_logo))
+            .route("/account/logo", web::post().to(upload_logo))
+            .route("/account/logo/delete", web::get().to(delete_logo))
+            .route(
+                "/account/email_suppressions",
+                web::get().to(list_email_suppressions),
+            )
+            .route(
+                "/account/email_suppressions/delete",
+                web::get().to(clear_email_suppression),
+            )
             .route("/qr_codes", web::get().to(list_qr_codes))
+            .route("/qr_codes/sheet.pdf", web::get().to(get_qr_code_sheet))
             .route("/qr_code/generate", web::post().to(generate_qr_code))
             .route("/qr_code/edit", web::get().to(edit_qr_code))
             .route("/qr_code/delete", web::get().to(delete_qr_code))
+            .route("/qr_code/restore", web::get().to(restore_qr_code))
+            .route("/qr_code/image", web::get().to(get_qr_code_image))
+            .route("/qr_code/stats", web::get().to(get_qr_code_stats))
+            .route("/qr_code/recipients", web::get().to(list_recipients))
+            .route("/qr_code/recipients", web::post().to(create_recipient))
+            .route("/qr_code/recipients/edit", web::post().to(edit_recipient))
+            .route(
+                "/qr_code/recipients/delete",
+                web::get().to(delete_recipient),
+            )
+            .route(
+                "/qr_code/notifications",
+                web::get().to(get_qr_code_notifications),
+            )
+            .route("/qr_code/alerts", web::get().to(list_alerts))
+            .route(
+                "/alert/acknowledge",
+                web::get().to(confirm_alert_acknowledgement),
+            )
+            .route("/alert/acknowledge", web::post().to(acknowledge_alert_link))
+            .route("/webhooks/twilio/sms", web::post().to(twilio_sms))
+            .route(
+                "/webhooks/twilio/voice-status",
+                web::post().to(twilio_voice_status),
+            )
+            .route("/webhooks/twilio/gather", web::post().to(twilio_gather))
+            .route("/webhooks/postmark", web::post().to(postmark_webhook))
             .route("/form/new", web::post().to(store_form))
-            .route("/form/submit", web::get().to(get_form))
-            .route("/form/submit", web::post().to(store_form_response))
+            .service(
+                web::resource("/form/submit")
+                    // Answers to image fields are uploaded inline, so responses can be large
+                    .app_data(web::JsonConfig::default().limit(MAX_RESPONSE_BODY_SIZE))
+                    .route(web::get().to(get_form))
+                    .route(web::post().to(store_form_response)),
+            )
             .route("/form/view", web::get().to(view_forms))
             .route("/form/edit", web::get().to(get_form))
             .route("/form/edit", web::post().to(edit_form))
+            .route("/form/notifications", web::get().to(get_form_notifications))
+            .route(
+                "/form/notifications",
+                web::post().to(edit_form_notifications),
+            )
+            .route("/form/rules", web::post().to(edit_form_rules))
+            .route("/form/status", web::get().to(get_form_status))
+            .route("/form/status", web::post().to(edit_form_status))
+            .route("/form/delete", web::get().to(delete_form))
+            .route("/form/restore", web::get().to(restore_form))
+            .route("/form/duplicate", web::post().to(duplicate_form))
+            .route("/form/export", web::get().to(export_form))
+            .route(
+                "/form/export/schema",
+                web::get().to(get_form_document_schema),
+            )
+            .route("/form/import", web::post().to(import_form))
+            .route("/form/templates", web::get().to(list_form_templates))
+            .route("/form/templates/save", web::post().to(save_form_template))
+            .route("/form/templates/use", web::post().to(use_form_template))
+            .route(
+                "/form/templates/delete",
+                web::get().to(delete_form_template),
+            )
+            .route("/trash", web::get().to(list_trash))
             .route("/form/test", web::get().to(test_email))
             .app_data(db_pool.clone())
             .app_data(jwt_client.clone())
             .app_data(twilio_client.clone())
-            .app_data(postmark_client.clone())
+            .app_data(providers.clone())
+            .app_data(base_url.clone())
+            .app_data(scan_client_settings.clone())
+            .app_data(postmark_webhook_credentials.clone())
     })
     .listen(listener)?
     .run();
@@ -0,0 +1,81 @@
+use image::{DynamicImage, ImageOutputFormat};
+
+use crate::helpers::{login, spawn_app, TestApp};
+
+async fn jwt_token(app: &TestApp) -> String {
+    let response = login(
+        app,
+        app.test_user.username.to_string(),
+        app.test_user.password.to_string(),
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+    response.text().await.unwrap()
+}
+
+fn png_logo() -> Vec<u8> {
+    let mut bytes = Vec::new();
+    DynamicImage::new_rgb8(16, 16)
+        .write_to(&mut bytes, ImageOutputFormat::Png)
+        .unwrap();
+    bytes
+}
+
+#[actix_rt::test]
+async fn uploaded_logo_can_be_retrieved() {
+    let app = spawn_app().await;
+    let token = jwt_token(&app).await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("{}/account/logo", app.address))
+        .header("Authorization", token.clone())
+        .header("Content-Type", "image/png")
+        .body(png_logo())
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+
+    let response = client
+        .get(format!("{}/account/logo", app.address))
+        .header("Authorization", token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!("image/png", response.headers()["Content-Type"]);
+    assert_eq!(png_logo(), response.bytes().await.unwrap().to_vec());
+}
+
+#[actix_rt::test]
+async fn logo_upload_rejects_undecodable_images() {
+    let app = spawn_app().await;
+    let token = jwt_token(&app).await;
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/account/logo", app.address))
+        .header("Authorization", token)
+        .header("Content-Type", "image/png")
+        .body("definitely not a png")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[actix_rt::test]
+async fn logo_upload_rejects_logged_out_users() {
+    let app = spawn_app().await;
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/account/logo", app.address))
+        .header("Content-Type", "image/png")
+        .body(png_logo())
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(401, response.status().as_u16());
+}
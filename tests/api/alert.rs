@@ -0,0 +1,449 @@
+use uuid::Uuid;
+use wiremock::matchers::method;
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{
+    post_twilio_webhook, scan_from, spawn_app, wait_for_delivery_attempts, TestApp,
+};
+
+/// Inserts a QR code of the test user notifying `phone_number` through `channel` and `email`.
+async fn insert_alerting_qr_code(
+    app: &TestApp,
+    phone_number: Option<&str>,
+    channel: &str,
+    email: Option<&str>,
+) -> Uuid {
+    let id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO qr_code (id, account_id, phone_number, notification_channel, email, payload)
+         VALUES ($1, $2, $3, $4, $5, 'Table 4 needs help')",
+    )
+    .bind(id)
+    .bind(app.test_user.id)
+    .bind(phone_number)
+    .bind(channel)
+    .bind(email)
+    .execute(&app.db_pool)
+    .await
+    .expect("Failed to insert QR code");
+    id
+}
+
+async fn mock_providers(app: &TestApp, twilio_status: u16) {
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(twilio_status))
+        .mount(&app.twilio_server)
+        .await;
+}
+
+async fn notification_id(app: &TestApp, qr_code_id: Uuid, channel: &str) -> Uuid {
+    let (id,): (Uuid,) =
+        sqlx::query_as("SELECT id FROM notification WHERE qr_code_id = $1 AND channel = $2")
+            .bind(qr_code_id)
+            .bind(channel)
+            .fetch_one(&app.db_pool)
+            .await
+            .expect("Failed to fetch notification");
+    id
+}
+
+async fn alert_status(app: &TestApp, qr_code_id: Uuid) -> (String, Option<String>) {
+    sqlx::query_as("SELECT status, acknowledged_by FROM alert WHERE qr_code_id = $1")
+        .bind(qr_code_id)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch alert")
+}
+
+/// Confirms the acknowledgement on the page an email notification's link opens.
+async fn acknowledge(url: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(url)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn reply_sms(app: &TestApp, body: &str) -> reqwest::Response {
+    post_twilio_webhook(
+        app,
+        "/webhooks/twilio/sms",
+        &[("From", "+15555550123"), ("Body", body)],
+    )
+    .await
+}
+
+#[actix_rt::test]
+async fn email_link_acknowledges_the_alert_and_cancels_its_pending_notifications() {
+    let app = spawn_app().await;
+    let id = insert_alerting_qr_code(
+        &app,
+        Some("+15555550123"),
+        "sms",
+        Some("staff@hermodapp.com"),
+    )
+    .await;
+    // SMS delivery fails, leaving the notification waiting for a retry
+    mock_providers(&app, 500).await;
+
+    scan_from(&app, id, "203.0.113.7").await;
+    wait_for_delivery_attempts(&app, id).await;
+    assert_eq!(("open".to_string(), None), alert_status(&app, id).await);
+
+    let token = notification_id(&app, id, "email").await;
+    let url = format!("{}/alert/acknowledge?token={}", app.address, token);
+    // Opening the link only asks for confirmation, so that link prefetchers cannot acknowledge alerts
+    let response = reqwest::get(&url)
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    assert!(response.text().await.unwrap().contains(&format!(
+        r#"<form method="post" action="/alert/acknowledge?token={}">"#,
+        token
+    )));
+    assert_eq!(("open".to_string(), None), alert_status(&app, id).await);
+
+    let response = acknowledge(&url).await;
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(
+        "Thank you, the alert has been acknowledged.",
+        response.text().await.unwrap()
+    );
+    assert_eq!(
+        (
+            "acknowledged".to_string(),
+            Some("staff@hermodapp.com".to_string())
+        ),
+        alert_status(&app, id).await
+    );
+    let (status,): (String,) =
+        sqlx::query_as("SELECT status FROM notification WHERE qr_code_id = $1 AND channel = 'sms'")
+            .bind(id)
+            .fetch_one(&app.db_pool)
+            .await
+            .expect("Failed to fetch notification");
+    assert_eq!("cancelled", status);
+
+    let response = acknowledge(&url).await;
+    assert_eq!(
+        "This alert has already been acknowledged.",
+        response.text().await.unwrap()
+    );
+}
+
+#[actix_rt::test]
+async fn unknown_acknowledgement_links_are_not_found() {
+    let app = spawn_app().await;
+
+    let url = format!("{}/alert/acknowledge?token={}", app.address, Uuid::new_v4());
+
+    let response = reqwest::get(&url)
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(404, response.status().as_u16());
+    assert_eq!(404, acknowledge(&url).await.status().as_u16());
+}
+
+#[actix_rt::test]
+async fn sms_reply_acknowledges_the_latest_alert() {
+    let app = spawn_app().await;
+    let id = insert_alerting_qr_code(&app, Some("+15555550123"), "sms", None).await;
+    mock_providers(&app, 201).await;
+
+    scan_from(&app, id, "203.0.113.7").await;
+    wait_for_delivery_attempts(&app, id).await;
+    let (message,): (String,) =
+        sqlx::query_as("SELECT message FROM notification WHERE qr_code_id = $1")
+            .bind(id)
+            .fetch_one(&app.db_pool)
+            .await
+            .expect("Failed to fetch notification");
+    assert_eq!("Table 4 needs help\nReply ACK to acknowledge.", message);
+
+    let response = reply_sms(&app, "what?").await;
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("<Message>Reply ACK to acknowledge the latest alert.</Message>"));
+    assert_eq!(("open".to_string(), None), alert_status(&app, id).await);
+
+    let response = reply_sms(&app, " Ack ").await;
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(
+        "<Response><Message>Thank you, the alert has been acknowledged.</Message></Response>",
+        response.text().await.unwrap()
+    );
+    assert_eq!(
+        ("acknowledged".to_string(), Some("+15555550123".to_string())),
+        alert_status(&app, id).await
+    );
+
+    let response = reply_sms(&app, "ACK").await;
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("There is no alert to acknowledge."));
+}
+
+#[actix_rt::test]
+async fn pressing_1_during_the_call_acknowledges_the_alert() {
+    let app = spawn_app().await;
+    let id = insert_alerting_qr_code(&app, Some("+15555550123"), "call", None).await;
+    mock_providers(&app, 201).await;
+
+    scan_from(&app, id, "203.0.113.7").await;
+    wait_for_delivery_attempts(&app, id).await;
+    let token = notification_id(&app, id, "call").await;
+    let path = format!("/webhooks/twilio/gather?token={}", token);
+
+    let response = post_twilio_webhook(&app, &path, &[("Digits", "2")]).await;
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(("open".to_string(), None), alert_status(&app, id).await);
+
+    let response = post_twilio_webhook(&app, &path, &[("Digits", "1")]).await;
+    assert_eq!("text/xml", response.headers()["Content-Type"]);
+    assert_eq!(
+        "<Response><Say>Thank you, the alert has been acknowledged.</Say></Response>",
+        response.text().await.unwrap()
+    );
+    assert_eq!(
+        ("acknowledged".to_string(), Some("+15555550123".to_string())),
+        alert_status(&app, id).await
+    );
+}
+
+#[actix_rt::test]
+async fn unacknowledged_alerts_are_escalated() {
+    let app = spawn_app().await;
+    let id = insert_alerting_qr_code(&app, None, "none", Some("staff@hermodapp.com")).await;
+    let token = app.token().await;
+    mock_providers(&app, 201).await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/qr_code/edit", app.address))
+        .header("Authorization", token.clone())
+        .json(&serde_json::json!({
+            "id": id,
+            "email": "staff@hermodapp.com",
+            "payload": "Table 4 needs help",
+            "escalation_timeout_seconds": 1
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    let response = reqwest::Client::new()
+        .post(format!("{}/qr_code/recipients", app.address))
+        .header("Authorization", token)
+        .json(&serde_json::json!({
+            "qr_code_id": id,
+            "channel": "email",
+            "address": "manager@hermodapp.com",
+            "escalation": true
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+
+    scan_from(&app, id, "203.0.113.7").await;
+    let recipients = || async {
+        let recipients: Vec<(String,)> = sqlx::query_as(
+            "SELECT recipient FROM notification WHERE qr_code_id = $1 ORDER BY created_at",
+        )
+        .bind(id)
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to fetch notifications");
+        recipients
+    };
+    // Escalation recipients are not notified of the scan itself
+    assert_eq!(
+        vec![("staff@hermodapp.com".to_string(),)],
+        recipients().await
+    );
+
+    for _ in 0..50 {
+        if alert_status(&app, id).await.0 == "escalated" {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert_eq!(
+        ("escalated".to_string(), None),
+        alert_status(&app, id).await
+    );
+    assert_eq!(
+        vec![
+            ("staff@hermodapp.com".to_string(),),
+            ("manager@hermodapp.com".to_string(),)
+        ],
+        recipients().await
+    );
+    let (message,): (String,) = sqlx::query_as(
+        "SELECT message FROM notification WHERE recipient = 'manager@hermodapp.com'",
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to fetch notification");
+    assert!(message.starts_with("Unacknowledged alert: Table 4 needs help\n\n"));
+
+    // Escalated alerts can still be acknowledged
+    let token = notification_id(&app, id, "email").await;
+    acknowledge(&format!(
+        "{}/alert/acknowledge?token={}",
+        app.address, token
+    ))
+    .await;
+    assert_eq!("acknowledged", alert_status(&app, id).await.0);
+}
+
+#[actix_rt::test]
+async fn alerts_are_listed_by_status_for_the_owner_only() {
+    let app = spawn_app().await;
+    let id = insert_alerting_qr_code(&app, None, "none", Some("staff@hermodapp.com")).await;
+    let token = app.token().await;
+    mock_providers(&app, 201).await;
+    scan_from(&app, id, "203.0.113.7").await;
+
+    let list = |query: String| {
+        reqwest::Client::new()
+            .get(format!("{}/qr_code/alerts?{}", app.address, query))
+            .header("Authorization", token.clone())
+            .send()
+    };
+    let response = list(format!("id={}", id))
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(1, body["alerts"].as_array().unwrap().len());
+    assert_eq!("open", body["alerts"][0]["status"]);
+    assert_eq!("Table 4 needs help", body["alerts"][0]["message"]);
+
+    let response = list(format!("id={}&status=acknowledged", id))
+        .await
+        .expect("Failed to execute request.");
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert!(body["alerts"].as_array().unwrap().is_empty());
+
+    let response = list(format!("id={}&status=closed", id))
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(400, response.status().as_u16());
+
+    let other_user = hermod_api::db::NewUser::default();
+    other_user.store(&app.db_pool).await.unwrap();
+    sqlx::query("UPDATE qr_code SET account_id = $1 WHERE id = $2")
+        .bind(other_user.id)
+        .bind(id)
+        .execute(&app.db_pool)
+        .await
+        .expect("Failed to update QR code");
+    let response = list(format!("id={}", id))
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[actix_rt::test]
+async fn qr_codes_in_the_trash_stop_alerting() {
+    let app = spawn_app().await;
+    let id = insert_alerting_qr_code(
+        &app,
+        Some("+15555550123"),
+        "sms",
+        Some("staff@hermodapp.com"),
+    )
+    .await;
+    let token = app.token().await;
+    sqlx::query("UPDATE qr_code SET escalation_timeout_seconds = 1 WHERE id = $1")
+        .bind(id)
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    let response = app
+        .post_json(
+            &token,
+            "/qr_code/recipients",
+            serde_json::json!({
+                "qr_code_id": id,
+                "channel": "email",
+                "address": "manager@hermodapp.com",
+                "escalation": true
+            }),
+        )
+        .await;
+    assert_eq!(200, response.status().as_u16());
+
+    // Without mocked providers every delivery fails, so the scan's notifications stay queued
+    scan_from(&app, id, "203.0.113.7").await;
+    let response = app.get(&token, &format!("/qr_code/delete?id={}", id)).await;
+    assert_eq!(200, response.status().as_u16());
+
+    let pending: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM notification WHERE qr_code_id = $1 AND status = 'pending'",
+    )
+    .bind(id)
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(0, pending);
+
+    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+    assert_eq!(
+        ("cancelled".to_string(), None),
+        alert_status(&app, id).await
+    );
+    let escalations: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM notification WHERE recipient = 'manager@hermodapp.com'",
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(0, escalations);
+}
+
+#[actix_rt::test]
+async fn cancelled_alerts_cannot_be_acknowledged() {
+    let app = spawn_app().await;
+    let id = insert_alerting_qr_code(
+        &app,
+        Some("+15555550123"),
+        "sms",
+        Some("staff@hermodapp.com"),
+    )
+    .await;
+    let token = app.token().await;
+    scan_from(&app, id, "203.0.113.7").await;
+    let response = app.get(&token, &format!("/qr_code/delete?id={}", id)).await;
+    assert_eq!(200, response.status().as_u16());
+
+    let notification = notification_id(&app, id, "email").await;
+    let response = acknowledge(&format!(
+        "{}/alert/acknowledge?token={}",
+        app.address, notification
+    ))
+    .await;
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(
+        "This alert was cancelled because its QR code was deleted.",
+        response.text().await.unwrap()
+    );
+    let response = reply_sms(&app, "ACK").await;
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("There is no alert to acknowledge."));
+    assert_eq!(
+        ("cancelled".to_string(), None),
+        alert_status(&app, id).await
+    );
+}
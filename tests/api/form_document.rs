@@ -0,0 +1,162 @@
+use uuid::Uuid;
+
+use crate::helpers::{spawn_app, stored_form_id, TestApp};
+
+async fn export(app: &TestApp, token: &str, form_id: &str) -> reqwest::Response {
+    app.get(token, &format!("/form/export?id={}", form_id))
+        .await
+}
+
+async fn import(app: &TestApp, token: &str, document: &serde_json::Value) -> reqwest::Response {
+    app.post_json(token, "/form/import", document.clone()).await
+}
+
+/// A document of a form with a choice before its only section and a follow-up question in it,
+/// shown when the first option is picked.
+fn document() -> serde_json::Value {
+    serde_json::json!({
+        "version": 1,
+        "title": "Delivery",
+        "fields": [{
+            "key": "speed",
+            "caption": "How fast was your delivery?",
+            "type": "radio",
+            "config": { "required": true, "options": ["Late", "On time"] }
+        }],
+        "sections": [{
+            "key": "details",
+            "title": "Details",
+            "page_break": true,
+            "fields": [{ "key": "delay", "caption": "How late was it?", "type": "number" }]
+        }],
+        "rules": [{
+            "action": "show",
+            "target_field": "delay",
+            "conditions": [{ "field": "speed", "operator": "equals", "value": "Late" }]
+        }]
+    })
+}
+
+#[actix_rt::test]
+async fn imported_forms_export_to_the_same_document() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+
+    let form_id = stored_form_id(import(&app, &token, &document()).await).await;
+    let form = app.get_form(&form_id).await;
+    assert_eq!("Delivery", form["title"]);
+    assert_eq!(
+        vec!["Late", "On time"],
+        form["fields"][0]["config"]["options"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|option| option.as_str().unwrap())
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(
+        form["fields"][1]["field_id"],
+        form["rules"][0]["target_field_id"]
+    );
+
+    let response = export(&app, &token, &form_id).await;
+    assert_eq!(200, response.status().as_u16());
+    let exported: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        format!("{}/form/export/schema", app.base_url.trim_end_matches('/')),
+        exported["$schema"]
+    );
+    assert_eq!(1, exported["version"]);
+    assert_eq!("field_1", exported["fields"][0]["key"]);
+    assert_eq!("section_1", exported["sections"][0]["key"]);
+    assert_eq!("field_2", exported["rules"][0]["target_field"]);
+    assert_eq!("field_1", exported["rules"][0]["conditions"][0]["field"]);
+
+    // Keys only depend on the order of fields and sections, so the copy exports identically
+    let copy_id = stored_form_id(import(&app, &token, &exported).await).await;
+    assert_ne!(form_id, copy_id);
+    let copy: serde_json::Value = export(&app, &token, &copy_id).await.json().await.unwrap();
+    assert_eq!(exported, copy);
+}
+
+#[actix_rt::test]
+async fn form_creation_requests_can_be_imported_as_drafts() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+
+    let form_id = stored_form_id(
+        import(
+            &app,
+            &token,
+            &serde_json::json!({
+                "version": 1,
+                "title": "Draft",
+                "status": "draft",
+                "fields": [{ "caption": "Name", "type": "text" }]
+            }),
+        )
+        .await,
+    )
+    .await;
+
+    let response = app
+        .get(&token, &format!("/form/status?id={}", form_id))
+        .await;
+    let status: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("draft", status["status"]);
+}
+
+#[actix_rt::test]
+async fn invalid_documents_are_rejected() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+
+    let mut unsupported = document();
+    unsupported["version"] = serde_json::json!(2);
+    let mut unknown_key = document();
+    unknown_key["rules"][0]["target_field"] = serde_json::json!("reason");
+    let mut no_options = document();
+    no_options["fields"][0]["config"]["options"] = serde_json::json!([]);
+
+    for (invalid, message) in [
+        (
+            unsupported,
+            "Version 2 form documents are not supported. Use version 1.",
+        ),
+        (
+            unknown_key,
+            "Rule 1: Nothing in the form has the key reason.",
+        ),
+        (
+            no_options,
+            "How fast was your delivery?: radio fields need options.",
+        ),
+    ] {
+        let response = import(&app, &token, &invalid).await;
+        assert_eq!(400, response.status().as_u16());
+        assert_eq!(message, response.text().await.unwrap());
+    }
+}
+
+#[actix_rt::test]
+async fn only_owners_can_export_a_form() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+
+    let response = export(&app, &token, &Uuid::new_v4().to_string()).await;
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[actix_rt::test]
+async fn the_document_schema_is_served() {
+    let app = spawn_app().await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/form/export/schema", app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    let schema: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(1, schema["properties"]["version"]["const"]);
+}
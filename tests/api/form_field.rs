@@ -0,0 +1,123 @@
+use crate::helpers::{field_id, spawn_app};
+
+#[actix_rt::test]
+async fn fields_are_returned_with_their_type_and_config() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+    let form_id = app.create_survey(&token).await;
+
+    let form = app.get_form(&form_id).await;
+    let rating = form["fields"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|field| field["caption"] == "Rating")
+        .unwrap();
+    assert_eq!("rating", rating["type"]);
+    assert_eq!(true, rating["config"]["required"]);
+}
+
+#[actix_rt::test]
+async fn forms_with_invalid_field_config_are_rejected() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+
+    let response = app
+        .post_json(
+            &token,
+            "/form/new",
+            serde_json::json!({
+                "title": "Survey",
+                "fields": [{ "caption": "Colour", "type": "radio" }]
+            }),
+        )
+        .await;
+    assert_eq!(400, response.status().as_u16());
+    assert_eq!(
+        "Colour: radio fields need options.",
+        response.text().await.unwrap()
+    );
+
+    let response = app
+        .post_json(
+            &token,
+            "/form/new",
+            serde_json::json!({
+                "title": "Survey",
+                "fields": [{ "caption": "Colour", "type": "colour" }]
+            }),
+        )
+        .await;
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[actix_rt::test]
+async fn invalid_responses_are_rejected_with_per_field_errors() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+    let form_id = app.create_survey(&token).await;
+    let form = app.get_form(&form_id).await;
+    let rating = field_id(&form, "Rating");
+    let email = field_id(&form, "Email");
+
+    let response = app
+        .submit(
+            &form_id,
+            serde_json::json!({ "responses": [{ "field_id": email, "content": "not an email" }] }),
+        )
+        .await;
+    assert_eq!(422, response.status().as_u16());
+    assert_eq!(
+        "application/problem+json",
+        response.headers()["Content-Type"].to_str().unwrap()
+    );
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(422, body["status"]);
+    let errors = body["errors"].as_array().unwrap();
+    assert_eq!(2, errors.len());
+    assert_eq!(rating.to_string(), errors[0]["field_id"]);
+    assert_eq!("required", errors[0]["code"]);
+    assert_eq!("This field is required.", errors[0]["message"]);
+    assert_eq!(email.to_string(), errors[1]["field_id"]);
+    assert_eq!("invalid_format", errors[1]["code"]);
+
+    let (responses,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM response")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(0, responses);
+
+    let response = app
+        .submit(
+            &form_id,
+            serde_json::json!({ "responses": [{ "field_id": rating, "content": "4" }] }),
+        )
+        .await;
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[actix_rt::test]
+async fn answers_to_fields_of_other_forms_are_rejected() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+    let form_id = app.create_survey(&token).await;
+    let other_form_id = app.create_survey(&token).await;
+    let form = app.get_form(&form_id).await;
+    let other_rating = field_id(&app.get_form(&other_form_id).await, "Rating");
+
+    let response = app
+        .submit(
+            &form_id,
+            serde_json::json!({
+                "responses": [
+                    { "field_id": field_id(&form, "Rating"), "content": "5" },
+                    { "field_id": other_rating, "content": "1" }
+                ]
+            }),
+        )
+        .await;
+    assert_eq!(422, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(other_rating.to_string(), body["errors"][0]["field_id"]);
+    assert_eq!("unknown_field", body["errors"][0]["code"]);
+}
@@ -0,0 +1,218 @@
+use crate::helpers::{login, spawn_app, TestApp};
+
+async fn edit_form(
+    app: &TestApp,
+    token: &str,
+    form_id: &str,
+    body: serde_json::Value,
+) -> reqwest::Response {
+    app.post_json(token, &format!("/form/edit?id={}", form_id), body)
+        .await
+}
+
+fn captions(form: &serde_json::Value) -> Vec<&str> {
+    form["fields"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|field| field["caption"].as_str().unwrap())
+        .collect()
+}
+
+fn field<'a>(form: &'a serde_json::Value, caption: &str) -> &'a serde_json::Value {
+    form["fields"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|field| field["caption"] == caption)
+        .unwrap()
+}
+
+/// Creates a two page survey with a leading question and two sections, returning its id.
+async fn create_sectioned_survey(app: &TestApp, token: &str) -> String {
+    app.create_form(
+        token,
+        serde_json::json!({
+            "title": "Survey",
+            "fields": [{ "caption": "Name", "type": "text" }],
+            "sections": [
+                {
+                    "title": "Your visit",
+                    "description": "Tell us about today.",
+                    "fields": [
+                        { "caption": "Rating", "type": "rating" },
+                        { "caption": "Comments", "type": "text" }
+                    ]
+                },
+                {
+                    "title": "About you",
+                    "page_break": true,
+                    "fields": [{ "caption": "Email", "type": "email" }]
+                }
+            ]
+        }),
+    )
+    .await
+}
+
+#[actix_rt::test]
+async fn fields_are_returned_in_order_with_their_sections_and_pages() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+    let form_id = create_sectioned_survey(&app, &token).await;
+
+    let form = app.get_form(&form_id).await;
+    assert_eq!(vec!["Name", "Rating", "Comments", "Email"], captions(&form));
+    assert_eq!(2, form["page_count"]);
+
+    let sections = form["sections"].as_array().unwrap();
+    assert_eq!(2, sections.len());
+    assert_eq!("Your visit", sections[0]["title"]);
+    assert_eq!("Tell us about today.", sections[0]["description"]);
+    assert_eq!(1, sections[0]["page"]);
+    assert_eq!("About you", sections[1]["title"]);
+    assert_eq!(2, sections[1]["page"]);
+
+    assert_eq!(serde_json::Value::Null, field(&form, "Name")["section_id"]);
+    assert_eq!(
+        sections[0]["section_id"],
+        field(&form, "Comments")["section_id"]
+    );
+    assert_eq!(2, field(&form, "Email")["page"]);
+}
+
+#[actix_rt::test]
+async fn edits_reorder_fields_and_sections() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+    let form_id = create_sectioned_survey(&app, &token).await;
+    let form = app.get_form(&form_id).await;
+    let sections = form["sections"].as_array().unwrap();
+
+    let response = edit_form(
+        &app,
+        &token,
+        &form_id,
+        serde_json::json!({
+            "title": "Survey",
+            "sections": [
+                {
+                    "section_id": sections[1]["section_id"],
+                    "title": "About you",
+                    "fields": [{
+                        "field_id": field(&form, "Name")["field_id"],
+                        "caption": "Name",
+                        "type": "text",
+                        "delete": false
+                    }]
+                },
+                {
+                    "section_id": sections[0]["section_id"],
+                    "title": "Your visit",
+                    "page_break": true,
+                    "fields": [{
+                        "field_id": field(&form, "Comments")["field_id"],
+                        "caption": "Comments",
+                        "type": "text",
+                        "delete": false
+                    }]
+                }
+            ]
+        }),
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+
+    let form = app.get_form(&form_id).await;
+    assert_eq!(vec!["Name", "Email", "Comments", "Rating"], captions(&form));
+    let sections = form["sections"].as_array().unwrap();
+    assert_eq!("About you", sections[0]["title"]);
+    assert_eq!(1, sections[0]["page"]);
+    assert_eq!(2, sections[1]["page"]);
+    assert_eq!(
+        sections[0]["section_id"],
+        field(&form, "Name")["section_id"]
+    );
+}
+
+#[actix_rt::test]
+async fn deleting_a_section_keeps_its_fields() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+    let form_id = create_sectioned_survey(&app, &token).await;
+    let form = app.get_form(&form_id).await;
+    let sections = form["sections"].as_array().unwrap();
+
+    let response = edit_form(
+        &app,
+        &token,
+        &form_id,
+        serde_json::json!({
+            "title": "Survey",
+            "sections": [{ "section_id": sections[0]["section_id"], "delete": true }]
+        }),
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+
+    let form = app.get_form(&form_id).await;
+    assert_eq!(vec!["Name", "Rating", "Comments", "Email"], captions(&form));
+    assert_eq!(1, form["sections"].as_array().unwrap().len());
+    assert_eq!(
+        serde_json::Value::Null,
+        field(&form, "Rating")["section_id"]
+    );
+}
+
+#[actix_rt::test]
+async fn edits_cannot_move_fields_of_other_forms() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+    let form_id = create_sectioned_survey(&app, &token).await;
+    let other_id = create_sectioned_survey(&app, &token).await;
+    let other = app.get_form(&other_id).await;
+
+    let response = edit_form(
+        &app,
+        &token,
+        &form_id,
+        serde_json::json!({
+            "title": "Renamed",
+            "fields": [{
+                "field_id": field(&other, "Name")["field_id"],
+                "caption": "Name",
+                "type": "text",
+                "delete": false
+            }]
+        }),
+    )
+    .await;
+    assert_eq!(400, response.status().as_u16());
+    // Rejected edits leave the form untouched
+    assert_eq!("Survey", app.get_form(&form_id).await["title"]);
+}
+
+#[actix_rt::test]
+async fn other_accounts_cannot_edit_a_form() {
+    let app = spawn_app().await;
+    let form_id = create_sectioned_survey(&app, &app.token().await).await;
+    let other_user = hermod_api::db::NewUser::default();
+    other_user.store(&app.db_pool).await.unwrap();
+    let other_token = login(&app, other_user.username, other_user.password)
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    let response = edit_form(
+        &app,
+        &other_token,
+        &form_id,
+        serde_json::json!({ "title": "Hijacked", "sections": [] }),
+    )
+    .await;
+    assert_eq!(404, response.status().as_u16());
+    let form = app.get_form(&form_id).await;
+    assert_eq!("Survey", form["title"]);
+    assert_eq!(2, form["sections"].as_array().unwrap().len());
+}
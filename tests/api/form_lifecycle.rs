@@ -0,0 +1,224 @@
+use chrono::{Duration, Utc};
+
+use crate::helpers::{spawn_app, TestApp};
+
+/// Creates a form with one text question and the given status, returning its id.
+async fn create_form_with_status(app: &TestApp, token: &str, status: &str) -> String {
+    app.create_form(
+        token,
+        serde_json::json!({
+            "title": "Survey",
+            "status": status,
+            "fields": [{ "caption": "How was your visit?", "type": "text" }]
+        }),
+    )
+    .await
+}
+
+/// Submits an empty response to the form with the given `form_id`.
+async fn submit_empty(app: &TestApp, form_id: &str) -> reqwest::Response {
+    app.submit(form_id, serde_json::json!({ "responses": [] }))
+        .await
+}
+
+async fn edit_status(
+    app: &TestApp,
+    token: &str,
+    form_id: &str,
+    body: serde_json::Value,
+) -> reqwest::Response {
+    app.post_json(token, &format!("/form/status?id={}", form_id), body)
+        .await
+}
+
+#[actix_rt::test]
+async fn drafts_are_only_shown_to_their_owner() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+    let form_id = create_form_with_status(&app, &token, "draft").await;
+
+    let response = app.fetch_form(&form_id).await;
+    assert_eq!(403, response.status().as_u16());
+    assert_eq!(
+        "This form has not been published yet.",
+        response.text().await.unwrap()
+    );
+    assert_eq!(403, submit_empty(&app, &form_id).await.status().as_u16());
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/form/edit?id={}", app.address, form_id))
+        .header("Authorization", &token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    let form: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("draft", form["status"]);
+}
+
+#[actix_rt::test]
+async fn owners_publish_and_close_their_forms() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+    let form_id = create_form_with_status(&app, &token, "draft").await;
+
+    let response = edit_status(
+        &app,
+        &token,
+        &form_id,
+        serde_json::json!({ "status": "published" }),
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(200, app.fetch_form(&form_id).await.status().as_u16());
+    assert_eq!(200, submit_empty(&app, &form_id).await.status().as_u16());
+
+    let response = edit_status(
+        &app,
+        &token,
+        &form_id,
+        serde_json::json!({ "status": "closed" }),
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+    let status: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(1, status["responses"]);
+    assert_eq!("This form is closed.", status["closed_reason"]);
+    let response = submit_empty(&app, &form_id).await;
+    assert_eq!(403, response.status().as_u16());
+    assert_eq!("This form is closed.", response.text().await.unwrap());
+}
+
+#[actix_rt::test]
+async fn forms_only_accept_responses_within_their_window() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+    let form_id = create_form_with_status(&app, &token, "published").await;
+    let now = Utc::now().naive_utc();
+
+    let response = edit_status(
+        &app,
+        &token,
+        &form_id,
+        serde_json::json!({ "status": "published", "opens_at": now + Duration::days(1) }),
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+    let response = submit_empty(&app, &form_id).await;
+    assert_eq!(403, response.status().as_u16());
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .starts_with("This form opens at "));
+
+    let response = edit_status(
+        &app,
+        &token,
+        &form_id,
+        serde_json::json!({ "status": "published", "closes_at": now - Duration::days(1) }),
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+    let response = submit_empty(&app, &form_id).await;
+    assert_eq!(403, response.status().as_u16());
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .starts_with("This form closed at "));
+}
+
+#[actix_rt::test]
+async fn forms_stop_accepting_responses_at_their_cap() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+    let form_id = create_form_with_status(&app, &token, "published").await;
+
+    let response = edit_status(
+        &app,
+        &token,
+        &form_id,
+        serde_json::json!({ "status": "published", "max_responses": 1 }),
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(200, submit_empty(&app, &form_id).await.status().as_u16());
+
+    let response = submit_empty(&app, &form_id).await;
+    assert_eq!(403, response.status().as_u16());
+    assert_eq!(
+        "This form is no longer accepting responses.",
+        response.text().await.unwrap()
+    );
+    assert_eq!(403, app.fetch_form(&form_id).await.status().as_u16());
+}
+
+#[actix_rt::test]
+async fn windows_must_open_before_they_close() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+    let form_id = create_form_with_status(&app, &token, "published").await;
+    let now = Utc::now().naive_utc();
+
+    let response = edit_status(
+        &app,
+        &token,
+        &form_id,
+        serde_json::json!({
+            "status": "published",
+            "opens_at": now,
+            "closes_at": now - Duration::hours(1)
+        }),
+    )
+    .await;
+    assert_eq!(400, response.status().as_u16());
+    assert_eq!(
+        "A form must open before it closes.",
+        response.text().await.unwrap()
+    );
+}
+
+#[actix_rt::test]
+async fn concurrent_responses_cannot_exceed_the_cap() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+    let form_id = create_form_with_status(&app, &token, "published").await;
+    let response = edit_status(
+        &app,
+        &token,
+        &form_id,
+        serde_json::json!({ "status": "published", "max_responses": 2 }),
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+
+    let submissions: Vec<_> = (0..8)
+        .map(|_| {
+            let url = format!("{}/form/submit?id={}", app.address, form_id);
+            tokio::spawn(async move {
+                reqwest::Client::new()
+                    .post(url)
+                    .json(&serde_json::json!({ "responses": [] }))
+                    .send()
+                    .await
+                    .expect("Failed to execute request.")
+                    .status()
+                    .as_u16()
+            })
+        })
+        .collect();
+    let mut accepted = 0;
+    for submission in submissions {
+        if submission.await.unwrap() == 200 {
+            accepted += 1;
+        }
+    }
+    assert_eq!(2, accepted);
+
+    let stored: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM response")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(2, stored);
+}
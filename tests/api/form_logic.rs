@@ -0,0 +1,148 @@
+use uuid::Uuid;
+
+use crate::helpers::{field_id, spawn_app, TestApp};
+
+/// Creates a form with a required rating followed by a required question about what went
+/// wrong, returning its id.
+async fn create_rating_survey(app: &TestApp, token: &str) -> String {
+    app.create_form(
+        token,
+        serde_json::json!({
+            "title": "Survey",
+            "fields": [
+                { "caption": "Rating", "type": "rating", "config": { "required": true } },
+                { "caption": "What went wrong?", "type": "text", "config": { "required": true } }
+            ]
+        }),
+    )
+    .await
+}
+
+async fn edit_rules(
+    app: &TestApp,
+    token: &str,
+    form_id: &str,
+    body: serde_json::Value,
+) -> reqwest::Response {
+    app.post_json(token, &format!("/form/rules?id={}", form_id), body)
+        .await
+}
+
+/// Creates the survey with a rule that only asks what went wrong after a low rating.
+async fn create_survey_with_follow_up(app: &TestApp) -> (String, Uuid, Uuid) {
+    let token = app.token().await;
+    let form_id = create_rating_survey(app, &token).await;
+    let form = app.get_form(&form_id).await;
+    let rating = field_id(&form, "Rating");
+    let follow_up = field_id(&form, "What went wrong?");
+
+    let response = edit_rules(
+        app,
+        &token,
+        &form_id,
+        serde_json::json!({
+            "rules": [{
+                "action": "show",
+                "target_field_id": follow_up,
+                "conditions": [{ "field_id": rating, "operator": "less_than", "value": "3" }]
+            }]
+        }),
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+    (form_id, rating, follow_up)
+}
+
+#[actix_rt::test]
+async fn rules_are_returned_with_the_form() {
+    let app = spawn_app().await;
+    let (form_id, rating, follow_up) = create_survey_with_follow_up(&app).await;
+
+    let form = app.get_form(&form_id).await;
+    let rules = form["rules"].as_array().unwrap();
+    assert_eq!(1, rules.len());
+    assert_eq!("show", rules[0]["action"]);
+    assert_eq!(follow_up.to_string(), rules[0]["target_field_id"]);
+    assert_eq!(rating.to_string(), rules[0]["conditions"][0]["field_id"]);
+    assert_eq!("less_than", rules[0]["conditions"][0]["operator"]);
+}
+
+#[actix_rt::test]
+async fn shown_fields_are_required() {
+    let app = spawn_app().await;
+    let (form_id, rating, follow_up) = create_survey_with_follow_up(&app).await;
+
+    let response = app
+        .submit(
+            &form_id,
+            serde_json::json!({ "responses": [{ "field_id": rating, "content": "2" }] }),
+        )
+        .await;
+    assert_eq!(422, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(follow_up.to_string(), body["errors"][0]["field_id"]);
+    assert_eq!("required", body["errors"][0]["code"]);
+}
+
+#[actix_rt::test]
+async fn hidden_fields_are_not_required_and_their_answers_are_dropped() {
+    let app = spawn_app().await;
+    let (form_id, rating, follow_up) = create_survey_with_follow_up(&app).await;
+
+    let response = app
+        .submit(
+            &form_id,
+            serde_json::json!({ "responses": [{ "field_id": rating, "content": "5" }] }),
+        )
+        .await;
+    assert_eq!(200, response.status().as_u16());
+
+    let response = app
+        .submit(
+            &form_id,
+            serde_json::json!({
+                "responses": [
+                    { "field_id": rating, "content": "4" },
+                    { "field_id": follow_up, "content": "Nothing" }
+                ]
+            }),
+        )
+        .await;
+    assert_eq!(200, response.status().as_u16());
+    let stored: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM feedback WHERE form_input_id = $1")
+        .bind(follow_up)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(0, stored);
+}
+
+#[actix_rt::test]
+async fn rules_must_depend_on_earlier_fields() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+    let form_id = create_rating_survey(&app, &token).await;
+    let form = app.get_form(&form_id).await;
+
+    let response = edit_rules(
+        &app,
+        &token,
+        &form_id,
+        serde_json::json!({
+            "rules": [{
+                "action": "hide",
+                "target_field_id": field_id(&form, "Rating"),
+                "conditions": [{
+                    "field_id": field_id(&form, "What went wrong?"),
+                    "operator": "answered"
+                }]
+            }]
+        }),
+    )
+    .await;
+    assert_eq!(400, response.status().as_u16());
+    assert_eq!(
+        "Rule 1: Conditions can only test answers to earlier fields.",
+        response.text().await.unwrap()
+    );
+}
@@ -0,0 +1,178 @@
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{spawn_app, TestApp};
+
+/// Inserts a form with a single question and returns the ids of the form and its question.
+async fn insert_form(app: &TestApp) -> (Uuid, Uuid) {
+    let form_id = Uuid::new_v4();
+    let field_id = Uuid::new_v4();
+    sqlx::query("INSERT INTO form (id, account_id, title) VALUES ($1, $2, 'Lobby feedback')")
+        .bind(form_id)
+        .bind(app.test_user.id)
+        .execute(&app.db_pool)
+        .await
+        .expect("Failed to insert form");
+    sqlx::query(
+        "INSERT INTO form_input (id, form_id, type, caption) VALUES ($1, $2, 'text', 'Comments')",
+    )
+    .bind(field_id)
+    .bind(form_id)
+    .execute(&app.db_pool)
+    .await
+    .expect("Failed to insert form input");
+    (form_id, field_id)
+}
+
+async fn edit_settings(
+    app: &TestApp,
+    token: &str,
+    form_id: Uuid,
+    body: serde_json::Value,
+) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}/form/notifications?id={}", app.address, form_id))
+        .header("Authorization", token)
+        .json(&body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+/// Answers the form's question with a comment.
+async fn submit_comment(app: &TestApp, form_id: Uuid, field_id: Uuid) -> reqwest::Response {
+    app.submit(
+        &form_id.to_string(),
+        serde_json::json!({
+            "responses": [{ "field_id": field_id, "content": "Great, thanks" }]
+        }),
+    )
+    .await
+}
+
+/// Waits until the email server has received an email, which is sent after the response is stored.
+async fn received_emails(app: &TestApp) -> Vec<wiremock::Request> {
+    for _ in 0..100 {
+        let requests = app.email_server.received_requests().await.unwrap();
+        if !requests.is_empty() {
+            return requests;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    panic!("Emails were not sent in time");
+}
+
+#[actix_rt::test]
+async fn notification_settings_can_be_read_and_replaced() {
+    let app = spawn_app().await;
+    let (form_id, _) = insert_form(&app).await;
+    let token = app.token().await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/form/notifications?id={}", app.address, form_id))
+        .header("Authorization", &token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(0, body["recipients"].as_array().unwrap().len());
+    assert_eq!(false, body["include_attachment"]);
+
+    let response = edit_settings(
+        &app,
+        &token,
+        form_id,
+        serde_json::json!({
+            "recipients": ["owner@hermodapp.com"],
+            "include_attachment": true,
+            "attachment_format": "pdf"
+        }),
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("owner@hermodapp.com", body["recipients"][0]);
+    assert_eq!("pdf", body["attachment_format"]);
+
+    let response = edit_settings(
+        &app,
+        &token,
+        form_id,
+        serde_json::json!({ "recipients": ["not an address"] }),
+    )
+    .await;
+    assert_eq!(400, response.status().as_u16());
+
+    let response = edit_settings(
+        &app,
+        &token,
+        Uuid::new_v4(),
+        serde_json::json!({ "recipients": ["owner@hermodapp.com"] }),
+    )
+    .await;
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[actix_rt::test]
+async fn responses_are_emailed_with_an_attachment_when_configured() {
+    let app = spawn_app().await;
+    let (form_id, field_id) = insert_form(&app).await;
+    let token = app.token().await;
+    edit_settings(
+        &app,
+        &token,
+        form_id,
+        serde_json::json!({
+            "recipients": ["owner@hermodapp.com"],
+            "include_attachment": true,
+            "attachment_format": "csv"
+        }),
+    )
+    .await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = submit_comment(&app, form_id, field_id).await;
+    assert_eq!(200, response.status().as_u16());
+
+    let requests = received_emails(&app).await;
+    let email: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!("owner@hermodapp.com", email["To"]);
+    assert_eq!("New response: Lobby feedback", email["Subject"]);
+    assert_eq!("form-response", email["Tag"]);
+    assert!(email["TextBody"]
+        .as_str()
+        .unwrap()
+        .contains("Comments\nGreat, thanks"));
+    let attachment = &email["Attachments"][0];
+    assert_eq!("text/csv", attachment["ContentType"]);
+    let csv = base64::decode(attachment["Content"].as_str().unwrap()).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert!(csv.starts_with("Submitted at,Comments\r\n"));
+    assert!(csv.ends_with(",\"Great, thanks\"\r\n"));
+}
+
+#[actix_rt::test]
+async fn responses_are_not_emailed_without_recipients() {
+    let app = spawn_app().await;
+    let (form_id, field_id) = insert_form(&app).await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = submit_comment(&app, form_id, field_id).await;
+    assert_eq!(200, response.status().as_u16());
+
+    let response = submit_comment(&app, Uuid::new_v4(), field_id).await;
+    assert_eq!(404, response.status().as_u16());
+}
@@ -0,0 +1,197 @@
+use uuid::Uuid;
+
+use crate::helpers::{spawn_app, stored_form_id, TestApp};
+
+/// Creates a form with a rating in one section and a follow-up question shown after low ratings
+/// in another, returning its id.
+async fn create_sectioned_survey(app: &TestApp, token: &str) -> String {
+    let form_id = app
+        .create_form(
+            token,
+            serde_json::json!({
+                "title": "Survey",
+                "sections": [
+                    {
+                        "title": "Rating",
+                        "fields": [{ "caption": "Rating", "type": "rating", "config": { "required": true } }]
+                    },
+                    {
+                        "title": "Follow-up",
+                        "page_break": true,
+                        "fields": [{ "caption": "What went wrong?", "type": "text" }]
+                    }
+                ]
+            }),
+        )
+        .await;
+    let form = app.get_form(&form_id).await;
+    let response = app
+        .post_json(
+            token,
+            &format!("/form/rules?id={}", form_id),
+            serde_json::json!({
+                "rules": [{
+                    "action": "show",
+                    "target_field_id": form["fields"][1]["field_id"],
+                    "conditions": [{
+                        "field_id": form["fields"][0]["field_id"],
+                        "operator": "less_than",
+                        "value": "3"
+                    }]
+                }]
+            }),
+        )
+        .await;
+    assert_eq!(200, response.status().as_u16());
+    form_id
+}
+
+/// Checks that `copy` has the fields, sections and rules of `original` under new ids.
+fn assert_copied(original: &serde_json::Value, copy: &serde_json::Value) {
+    assert_eq!(2, copy["fields"].as_array().unwrap().len());
+    assert_eq!(2, copy["sections"].as_array().unwrap().len());
+    assert_eq!(2, copy["page_count"]);
+    assert_eq!(
+        original["fields"][1]["caption"],
+        copy["fields"][1]["caption"]
+    );
+    assert_ne!(
+        original["fields"][0]["field_id"],
+        copy["fields"][0]["field_id"]
+    );
+    assert_eq!(
+        copy["sections"][1]["section_id"],
+        copy["fields"][1]["section_id"]
+    );
+    assert_eq!(
+        copy["fields"][1]["field_id"],
+        copy["rules"][0]["target_field_id"]
+    );
+    assert_eq!(
+        copy["fields"][0]["field_id"],
+        copy["rules"][0]["conditions"][0]["field_id"]
+    );
+}
+
+#[actix_rt::test]
+async fn duplicates_copy_the_fields_sections_and_rules_of_a_form() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+    let form_id = create_sectioned_survey(&app, &token).await;
+
+    let copy_id = stored_form_id(
+        app.post_json(
+            &token,
+            &format!("/form/duplicate?id={}", form_id),
+            serde_json::json!({}),
+        )
+        .await,
+    )
+    .await;
+    assert_ne!(form_id, copy_id);
+
+    let copy = app.get_form(&copy_id).await;
+    assert_eq!("Survey (copy)", copy["title"]);
+    assert_copied(&app.get_form(&form_id).await, &copy);
+}
+
+#[actix_rt::test]
+async fn only_owners_can_duplicate_a_form() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+
+    let response = app
+        .post_json(
+            &token,
+            &format!("/form/duplicate?id={}", Uuid::new_v4()),
+            serde_json::json!({}),
+        )
+        .await;
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[actix_rt::test]
+async fn forms_can_be_saved_as_templates_and_created_from_them() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+    let form_id = create_sectioned_survey(&app, &token).await;
+
+    let response = app.post_json(
+        &token,
+        &format!("/form/templates/save?id={}", form_id),
+        serde_json::json!({ "name": "Location survey", "description": "Used at every location" }),
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+    let template: serde_json::Value = response.json().await.unwrap();
+    let template_id = template["id"].as_str().unwrap();
+
+    let response = app.get(&token, "/form/templates").await;
+    assert_eq!(200, response.status().as_u16());
+    let list: serde_json::Value = response.json().await.unwrap();
+    let saved = list["templates"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|listed| listed["template_id"] == template_id)
+        .unwrap();
+    assert_eq!("Location survey", saved["name"]);
+    assert_eq!(false, saved["built_in"]);
+
+    let new_form_id = stored_form_id(
+        app.post_json(
+            &token,
+            &format!("/form/templates/use?id={}", template_id),
+            serde_json::json!({}),
+        )
+        .await,
+    )
+    .await;
+    let new_form = app.get_form(&new_form_id).await;
+    assert_eq!("Survey", new_form["title"]);
+    assert_copied(&app.get_form(&form_id).await, &new_form);
+}
+
+#[actix_rt::test]
+async fn starter_templates_are_listed_and_can_be_used() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+
+    let response = app.get(&token, "/form/templates").await;
+    let list: serde_json::Value = response.json().await.unwrap();
+    let built_in: Vec<&str> = list["templates"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|listed| listed["built_in"] == true)
+        .map(|listed| listed["template_id"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        vec!["nps-survey", "restaurant-feedback", "incident-report"],
+        built_in
+    );
+
+    for template_id in built_in {
+        let form_id = stored_form_id(
+            app.post_json(
+                &token,
+                &format!("/form/templates/use?id={}", template_id),
+                serde_json::json!({}),
+            )
+            .await,
+        )
+        .await;
+        let form = app.get_form(&form_id).await;
+        assert!(!form["fields"].as_array().unwrap().is_empty());
+        assert_eq!(1, form["rules"].as_array().unwrap().len());
+    }
+
+    let response = app
+        .post_json(
+            &token,
+            "/form/templates/use?id=no-such-template",
+            serde_json::json!({}),
+        )
+        .await;
+    assert_eq!(404, response.status().as_u16());
+}
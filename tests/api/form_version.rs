@@ -0,0 +1,186 @@
+use crate::helpers::{field_id, login, spawn_app, TestApp};
+
+async fn view_responses(app: &TestApp, token: &str, form_id: &str) -> serde_json::Value {
+    let response = reqwest::Client::new()
+        .get(format!("{}/form/view?id={}", app.address, form_id))
+        .header("Authorization", token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    response.json().await.unwrap()
+}
+
+/// Creates a form with two text questions, returning its id.
+async fn create_text_survey(app: &TestApp, token: &str) -> String {
+    app.create_form(
+        token,
+        serde_json::json!({
+            "title": "Survey",
+            "fields": [
+                { "caption": "How was your visit?", "type": "text" },
+                { "caption": "Anything else?", "type": "text" }
+            ]
+        }),
+    )
+    .await
+}
+
+#[actix_rt::test]
+async fn edits_keep_earlier_responses_and_the_questions_they_answered() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+    let form_id = create_text_survey(&app, &token).await;
+    let form = app.get_form(&form_id).await;
+    let visit = field_id(&form, "How was your visit?");
+    let other = field_id(&form, "Anything else?");
+    let response = app
+        .submit(
+            &form_id,
+            serde_json::json!({
+                "responses": [
+                    { "field_id": visit, "content": "Great" },
+                    { "field_id": other, "content": "Keep it up" }
+                ]
+            }),
+        )
+        .await;
+    assert_eq!(200, response.status().as_u16());
+
+    let response = app
+        .post_json(
+            &token,
+            &format!("/form/edit?id={}", form_id),
+            serde_json::json!({
+                "title": "Survey",
+                "fields": [
+                    { "field_id": visit, "caption": "Rate your visit", "type": "text", "delete": false },
+                    { "field_id": other, "caption": "Anything else?", "type": "text", "delete": true }
+                ]
+            }),
+        )
+        .await;
+    assert_eq!(200, response.status().as_u16());
+    let form = app.get_form(&form_id).await;
+    assert_eq!(1, form["fields"].as_array().unwrap().len());
+    let response = app
+        .submit(
+            &form_id,
+            serde_json::json!({ "responses": [{ "field_id": visit, "content": "Fine" }] }),
+        )
+        .await;
+    assert_eq!(200, response.status().as_u16());
+
+    let view = view_responses(&app, &token, &form_id).await;
+    let versions = view["versions"].as_array().unwrap();
+    assert_eq!(2, versions.len());
+    assert_eq!("How was your visit?", versions[0]["fields"][0]["caption"]);
+    assert_eq!(2, versions[0]["fields"].as_array().unwrap().len());
+    assert_eq!("Rate your visit", versions[1]["fields"][0]["caption"]);
+    assert_eq!(1, versions[1]["fields"].as_array().unwrap().len());
+
+    let responses = view["responses"].as_array().unwrap();
+    let first = responses
+        .iter()
+        .find(|response| response["version"] == 1)
+        .unwrap();
+    assert_eq!(2, first["replies"].as_array().unwrap().len());
+    assert!(responses.iter().any(|response| response["version"] == 2));
+}
+
+#[actix_rt::test]
+async fn forms_in_the_trash_cannot_be_edited() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+    let form_id = create_text_survey(&app, &token).await;
+    let visit = field_id(&app.get_form(&form_id).await, "How was your visit?");
+    let response = reqwest::Client::new()
+        .get(format!("{}/form/delete?id={}", app.address, form_id))
+        .header("Authorization", &token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+
+    let response = app
+        .post_json(
+            &token,
+            &format!("/form/edit?id={}", form_id),
+            serde_json::json!({
+                "title": "Survey",
+                "fields": [
+                    { "field_id": visit, "caption": "How was your visit?", "type": "text", "delete": true }
+                ]
+            }),
+        )
+        .await;
+    assert_eq!(404, response.status().as_u16());
+
+    let archived: Option<chrono::NaiveDateTime> =
+        sqlx::query_scalar("SELECT archived_at FROM form_input WHERE id = $1")
+            .bind(visit)
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert!(archived.is_none());
+    let versions: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM form_version WHERE form_id = $1")
+        .bind(uuid::Uuid::parse_str(&form_id).unwrap())
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(1, versions);
+}
+
+#[actix_rt::test]
+async fn other_accounts_cannot_view_responses() {
+    let app = spawn_app().await;
+    let form_id = create_text_survey(&app, &app.token().await).await;
+    let other_user = hermod_api::db::NewUser::default();
+    other_user.store(&app.db_pool).await.unwrap();
+    let other_token = login(&app, other_user.username, other_user.password)
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    let response = app
+        .get(&other_token, &format!("/form/view?id={}", form_id))
+        .await;
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[actix_rt::test]
+async fn concurrent_edits_are_recorded_as_consecutive_versions() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+    let form_id = create_text_survey(&app, &token).await;
+
+    let edits: Vec<_> = (0..8)
+        .map(|i| {
+            let url = format!("{}/form/edit?id={}", app.address, form_id);
+            let token = token.clone();
+            tokio::spawn(async move {
+                reqwest::Client::new()
+                    .post(url)
+                    .header("Authorization", token)
+                    .json(&serde_json::json!({ "title": format!("Survey {}", i) }))
+                    .send()
+                    .await
+                    .expect("Failed to execute request.")
+                    .status()
+                    .as_u16()
+            })
+        })
+        .collect();
+    for edit in edits {
+        assert_eq!(200, edit.await.unwrap());
+    }
+
+    let versions: Vec<i32> =
+        sqlx::query_scalar("SELECT version FROM form_version WHERE form_id = $1 ORDER BY version")
+            .bind(uuid::Uuid::parse_str(&form_id).unwrap())
+            .fetch_all(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!((1..=9).collect::<Vec<i32>>(), versions);
+}
@@ -1,6 +1,7 @@
 use hermod_api::{
+    clients::twilio::signature,
     db::NewUser,
-    services::configuration::{get_configuration, DatabaseSettings},
+    services::configuration::{get_configuration, DatabaseSettings, EmailBackend, PhoneBackend},
     services::jwt::JwtClient,
     services::telemetry::{get_subscriber_test, init_subscriber},
     startup::{get_connection_pool, Application},
@@ -9,6 +10,7 @@ use once_cell::sync::Lazy;
 use reqwest::{Method, Response};
 use sqlx::{Connection, Executor, PgConnection, PgPool};
 use uuid::Uuid;
+use wiremock::MockServer;
 
 // Ensure that the `tracing` stack is only initialised once using `once_cell`
 static TRACING: Lazy<()> = Lazy::new(|| {
@@ -27,8 +29,9 @@ static TRACING: Lazy<()> = Lazy::new(|| {
 pub async fn spawn_app() -> TestApp {
     Lazy::force(&TRACING);
 
-    // Launch a mock server to stand in for Postmark's API
-    // let email_server = MockServer::start().await;
+    // Launch mock servers to stand in for Postmark's and Twilio's APIs
+    let email_server = MockServer::start().await;
+    let twilio_server = MockServer::start().await;
 
     // Randomise configuration to ensure test isolation
     let configuration = {
@@ -37,8 +40,13 @@ pub async fn spawn_app() -> TestApp {
         c.database.database_name = Uuid::new_v4().to_string();
         // Use a random OS port
         c.application.port = 0;
-        // Use the mock server as email API
-        // c.email_client.base_url = email_server.uri();
+        // Use the mock servers as email and phone APIs
+        c.delivery.email = EmailBackend::Postmark;
+        c.delivery.phone = PhoneBackend::Twilio;
+        c.postmark.base_url = format!("{}/", email_server.uri());
+        c.twilio.base_url = format!("{}/", twilio_server.uri());
+        // Trust the test client to report scanners' addresses, as a reverse proxy would
+        c.application.trusted_proxies = vec!["127.0.0.1".parse().unwrap(), "::1".parse().unwrap()];
         c
     };
 
@@ -63,6 +71,14 @@ pub async fn spawn_app() -> TestApp {
         port: application_port,
         db_pool: pool,
         jwt_client,
+        email_server,
+        twilio_server,
+        base_url: configuration.application.base_url.clone(),
+        twilio_auth_token: configuration.twilio.auth_token.clone(),
+        postmark_webhook_credentials: (
+            configuration.postmark.webhook_username.clone(),
+            configuration.postmark.webhook_password.clone(),
+        ),
         test_user: NewUser::default(),
         jwt_token: "".to_string(),
     };
@@ -95,6 +111,13 @@ pub struct TestApp {
     pub db_pool: PgPool,
     pub test_user: NewUser,
     pub jwt_client: JwtClient,
+    pub email_server: MockServer,
+    pub twilio_server: MockServer,
+    /// Public base URL of the application, which Twilio signs webhook requests for.
+    pub base_url: String,
+    pub twilio_auth_token: String,
+    /// Basic Auth username and password Postmark's webhooks authenticate with.
+    pub postmark_webhook_credentials: (String, String),
     jwt_token: String,
 }
 
@@ -110,6 +133,86 @@ impl TestApp {
         Ok(())
     }
 
+    /// Logs the test user in, returning the JWT to authorize their requests with.
+    pub async fn token(&self) -> String {
+        login(
+            self,
+            self.test_user.username.to_string(),
+            self.test_user.password.to_string(),
+        )
+        .await
+        .text()
+        .await
+        .unwrap()
+    }
+
+    /// Requests `path` on behalf of the user the `token` was issued to.
+    pub async fn get(&self, token: &str, path: &str) -> Response {
+        reqwest::Client::new()
+            .get(format!("{}{}", self.address, path))
+            .header("Authorization", token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Posts `body` as JSON to `path` on behalf of the user the `token` was issued to.
+    pub async fn post_json(&self, token: &str, path: &str, body: serde_json::Value) -> Response {
+        reqwest::Client::new()
+            .post(format!("{}{}", self.address, path))
+            .header("Authorization", token)
+            .json(&body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Creates a form from a `/form/new` request body, returning its id.
+    pub async fn create_form(&self, token: &str, body: serde_json::Value) -> String {
+        stored_form_id(self.post_json(token, "/form/new", body).await).await
+    }
+
+    /// Creates a form with a required rating and an optional email field, returning its id.
+    pub async fn create_survey(&self, token: &str) -> String {
+        self.create_form(
+            token,
+            serde_json::json!({
+                "title": "Survey",
+                "fields": [
+                    { "caption": "Rating", "type": "rating", "config": { "required": true } },
+                    { "caption": "Email", "type": "email" }
+                ]
+            }),
+        )
+        .await
+    }
+
+    /// Requests the form with the given `form_id` the way respondents do.
+    pub async fn fetch_form(&self, form_id: &str) -> Response {
+        reqwest::Client::new()
+            .get(format!("{}/form/submit?id={}", self.address, form_id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Returns the form with the given `form_id` as it is shown to respondents.
+    pub async fn get_form(&self, form_id: &str) -> serde_json::Value {
+        let response = self.fetch_form(form_id).await;
+        assert_eq!(200, response.status().as_u16());
+        response.json().await.unwrap()
+    }
+
+    /// Submits a response to the form with the given `form_id`.
+    pub async fn submit(&self, form_id: &str, body: serde_json::Value) -> Response {
+        reqwest::Client::new()
+            .post(format!("{}/form/submit?id={}", self.address, form_id))
+            .json(&body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn send_request_with_auth(
         &self,
         method: Method,
@@ -133,3 +236,101 @@ pub async fn login(app: &TestApp, username: String, password: String) -> reqwest
         .await
         .expect("Failed to execute request.")
 }
+
+/// Returns the id of the form that a response to `/form/new`, or to a route copying forms, stored.
+pub async fn stored_form_id(response: Response) -> String {
+    assert_eq!(200, response.status().as_u16());
+    response
+        .text()
+        .await
+        .unwrap()
+        .trim_start_matches("Stored new form with id ")
+        .trim_end_matches('.')
+        .to_string()
+}
+
+/// Returns the id of the field of a form returned by `TestApp::get_form` with the given `caption`.
+pub fn field_id(form: &serde_json::Value, caption: &str) -> Uuid {
+    let field = form["fields"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|field| field["caption"] == caption)
+        .unwrap();
+    field["field_id"].as_str().unwrap().parse().unwrap()
+}
+
+pub async fn insert_qr_code(app: &TestApp) -> Uuid {
+    let id = Uuid::new_v4();
+    sqlx::query("INSERT INTO qr_code (id, account_id) VALUES ($1, $2)")
+        .bind(id)
+        .bind(app.test_user.id)
+        .execute(&app.db_pool)
+        .await
+        .expect("Failed to insert QR code");
+    id
+}
+
+/// Waits until the notification worker has attempted every notification queued for a QR code.
+pub async fn wait_for_delivery_attempts(app: &TestApp, id: Uuid) {
+    for _ in 0..100 {
+        let (waiting,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM notification WHERE qr_code_id = $1 AND status = 'pending' AND next_attempt_at <= now() AT TIME ZONE 'utc'",
+        )
+        .bind(id)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to count notifications");
+        if waiting == 0 {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    panic!("Notifications were not delivered in time");
+}
+
+pub async fn scan_from(app: &TestApp, id: Uuid, forwarded_for: &str) -> String {
+    let response = reqwest::Client::new()
+        .get(format!("{}/scan?id={}", app.address, id))
+        .header("X-Forwarded-For", forwarded_for)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    response.text().await.unwrap()
+}
+
+/// Posts `params` to a Twilio webhook the way Twilio does, signed with the account's auth token.
+pub async fn post_twilio_webhook(
+    app: &TestApp,
+    path_and_query: &str,
+    params: &[(&str, &str)],
+) -> Response {
+    let params: Vec<(String, String)> = params
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+    let url = format!("{}{}", app.base_url.trim_end_matches('/'), path_and_query);
+    reqwest::Client::new()
+        .post(format!("{}{}", app.address, path_and_query))
+        .header(
+            "X-Twilio-Signature",
+            signature::sign(&app.twilio_auth_token, &url, &params),
+        )
+        .form(&params)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+/// Posts `event` to the Postmark webhook the way Postmark does, with the configured Basic Auth credentials.
+pub async fn post_postmark_webhook(app: &TestApp, event: serde_json::Value) -> Response {
+    let (username, password) = &app.postmark_webhook_credentials;
+    reqwest::Client::new()
+        .post(format!("{}/webhooks/postmark", app.address))
+        .basic_auth(username, Some(password))
+        .json(&event)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
@@ -1,4 +1,18 @@
+mod account;
+mod alert;
 mod auth;
+mod form_document;
+mod form_field;
+mod form_layout;
+mod form_lifecycle;
+mod form_logic;
+mod form_notification;
+mod form_template;
+mod form_version;
 mod health_check;
 mod helpers;
+mod postmark_webhook;
 mod qr_code;
+mod qr_code_recipient;
+mod trash;
+mod twilio_webhook;
@@ -0,0 +1,240 @@
+use uuid::Uuid;
+use wiremock::matchers::method;
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{
+    post_postmark_webhook, scan_from, spawn_app, wait_for_delivery_attempts, TestApp,
+};
+
+const MESSAGE_ID: &str = "b7bc2f4a-e38e-4336-af7d-e6c392c2f817";
+
+/// Inserts a QR code of the test user that emails `email`, and has Postmark accept every email.
+async fn insert_email_qr_code(app: &TestApp, email: &str) -> Uuid {
+    let id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO qr_code (id, account_id, notification_channel, email, payload)
+         VALUES ($1, $2, 'none', $3, 'Table 4 needs help')",
+    )
+    .bind(id)
+    .bind(app.test_user.id)
+    .bind(email)
+    .execute(&app.db_pool)
+    .await
+    .expect("Failed to insert QR code");
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "To": email,
+            "MessageID": MESSAGE_ID,
+            "ErrorCode": 0,
+            "Message": "OK"
+        })))
+        .mount(&app.email_server)
+        .await;
+    id
+}
+
+fn hard_bounce(email: &str, message_id: &str) -> serde_json::Value {
+    serde_json::json!({
+        "RecordType": "Bounce",
+        "MessageID": message_id,
+        "Type": "HardBounce",
+        "Email": email,
+        "Description": "The server was unable to deliver your message (ex: unknown user, mailbox not found)."
+    })
+}
+
+#[actix_rt::test]
+async fn webhooks_without_valid_credentials_are_rejected() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let event = serde_json::json!({ "RecordType": "Delivery", "MessageID": MESSAGE_ID });
+
+    let response = client
+        .post(format!("{}/webhooks/postmark", app.address))
+        .json(&event)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(401, response.status().as_u16());
+
+    let response = client
+        .post(format!("{}/webhooks/postmark", app.address))
+        .basic_auth(&app.postmark_webhook_credentials.0, Some("wrong password"))
+        .json(&event)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[actix_rt::test]
+async fn delivery_events_are_recorded_against_their_notification() {
+    let app = spawn_app().await;
+    let id = insert_email_qr_code(&app, "staff@hermodapp.com").await;
+    scan_from(&app, id, "203.0.113.7").await;
+    wait_for_delivery_attempts(&app, id).await;
+
+    // Notification emails carry their notification's id for the webhooks
+    let requests = app.email_server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    let (notification_id, provider_sid): (Uuid, Option<String>) =
+        sqlx::query_as("SELECT id, provider_sid FROM notification WHERE qr_code_id = $1")
+            .bind(id)
+            .fetch_one(&app.db_pool)
+            .await
+            .expect("Failed to fetch notification");
+    assert_eq!(
+        notification_id.to_string(),
+        body["Metadata"]["notification_id"]
+    );
+    assert_eq!(Some(MESSAGE_ID.to_string()), provider_sid);
+    assert_eq!(format!("Alert: {}", id), body["Subject"]);
+    assert_eq!("alert", body["Tag"]);
+    assert_eq!("outbound", body["MessageStream"]);
+
+    let response = post_postmark_webhook(
+        &app,
+        serde_json::json!({
+            "RecordType": "Delivery",
+            "MessageID": MESSAGE_ID,
+            "Recipient": "staff@hermodapp.com",
+            "DeliveredAt": "2021-12-14T16:06:43Z"
+        }),
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+    let (status,): (Option<String>,) =
+        sqlx::query_as("SELECT provider_status FROM notification WHERE id = $1")
+            .bind(notification_id)
+            .fetch_one(&app.db_pool)
+            .await
+            .expect("Failed to fetch notification");
+    assert_eq!(Some("delivered".to_string()), status);
+    let (record_type, event_notification_id): (String, Option<Uuid>) =
+        sqlx::query_as("SELECT record_type, notification_id FROM email_event")
+            .fetch_one(&app.db_pool)
+            .await
+            .expect("Failed to fetch email event");
+    assert_eq!("Delivery", record_type);
+    assert_eq!(Some(notification_id), event_notification_id);
+
+    // Record types that are not handled are acknowledged so that Postmark does not retry them
+    let response = post_postmark_webhook(
+        &app,
+        serde_json::json!({ "RecordType": "SubscriptionChange", "MessageID": MESSAGE_ID }),
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[actix_rt::test]
+async fn hard_bounces_suppress_the_address_until_the_owner_clears_it() {
+    let app = spawn_app().await;
+    let id = insert_email_qr_code(&app, "Staff@hermodapp.com").await;
+    let token = app.token().await;
+    scan_from(&app, id, "203.0.113.7").await;
+    wait_for_delivery_attempts(&app, id).await;
+
+    let response =
+        post_postmark_webhook(&app, hard_bounce("Staff@hermodapp.com", MESSAGE_ID)).await;
+    assert_eq!(200, response.status().as_u16());
+
+    let list = || {
+        reqwest::Client::new()
+            .get(format!("{}/account/email_suppressions", app.address))
+            .header("Authorization", token.clone())
+            .send()
+    };
+    let body: serde_json::Value = list()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .unwrap();
+    let suppressions = body["suppressions"].as_array().unwrap();
+    assert_eq!(1, suppressions.len());
+    assert_eq!("staff@hermodapp.com", suppressions[0]["email"]);
+    assert_eq!("HardBounce", suppressions[0]["reason"]);
+
+    // The next scan is recorded without emailing the suppressed address
+    sqlx::query(
+        "UPDATE qr_code SET code_cooldown_seconds = 0, client_cooldown_seconds = 0 WHERE id = $1",
+    )
+    .bind(id)
+    .execute(&app.db_pool)
+    .await
+    .expect("Failed to update QR code");
+    scan_from(&app, id, "203.0.113.8").await;
+    let (notifications,): (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM notification WHERE qr_code_id = $1")
+            .bind(id)
+            .fetch_one(&app.db_pool)
+            .await
+            .expect("Failed to count notifications");
+    assert_eq!(1, notifications);
+
+    let clear = |email: &str| {
+        reqwest::Client::new()
+            .get(format!(
+                "{}/account/email_suppressions/delete?email={}",
+                app.address,
+                urlencoding::encode(email)
+            ))
+            .header("Authorization", token.clone())
+            .send()
+    };
+    let response = clear("STAFF@hermodapp.com")
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    let response = clear("staff@hermodapp.com")
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(404, response.status().as_u16());
+    let body: serde_json::Value = list()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .unwrap();
+    assert!(body["suppressions"].as_array().unwrap().is_empty());
+}
+
+#[actix_rt::test]
+async fn hard_bounces_of_password_resets_block_further_resets() {
+    let app = spawn_app().await;
+    sqlx::query("UPDATE account SET email = 'owner@hermodapp.com' WHERE id = $1")
+        .bind(app.test_user.id)
+        .execute(&app.db_pool)
+        .await
+        .expect("Failed to update account");
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    let forgot_password = || {
+        reqwest::Client::new()
+            .post(format!(
+                "{}/password/forgot?username={}",
+                app.address, app.test_user.username
+            ))
+            .send()
+    };
+
+    let response = forgot_password().await.expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    let requests = app.email_server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!("Reset your Hermod password", body["Subject"]);
+    assert_eq!("password-reset", body["Tag"]);
+
+    // The reset email is not a notification, so the bounce is matched to the account by address
+    post_postmark_webhook(
+        &app,
+        hard_bounce("owner@hermodapp.com", &Uuid::new_v4().to_string()),
+    )
+    .await;
+    let response = forgot_password().await.expect("Failed to execute request.");
+    assert_eq!(400, response.status().as_u16());
+    assert_eq!(1, app.email_server.received_requests().await.unwrap().len());
+}
@@ -1,7 +1,11 @@
+use hermod_api::services::notification_worker::MAX_ATTEMPTS;
+use uuid::Uuid;
+use wiremock::matchers::{body_string_contains, method, path};
+use wiremock::{Mock, ResponseTemplate};
 
-
-
-
+use crate::helpers::{
+    insert_qr_code, login, scan_from, spawn_app, wait_for_delivery_attempts, TestApp,
+};
 
 /* TODO: Rewrite tests pending QR code generation/management refactor.
  *
@@ -96,3 +100,798 @@ async fn store_qr_code_data_accepts_valid_users() {
     assert_eq!(Some(0), response.content_length());
 }
 */
+
+#[actix_rt::test]
+async fn qr_code_image_is_rendered_as_png_by_default() {
+    let app = spawn_app().await;
+    let id = insert_qr_code(&app).await;
+
+    let response = reqwest::Client::new()
+        .get(&format!("{}/qr_code/image?id={}", app.address, id))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!("image/png", response.headers()["Content-Type"]);
+    let body = response.bytes().await.unwrap();
+    assert_eq!(&body[..8], b"\x89PNG\r\n\x1a\n");
+}
+
+#[actix_rt::test]
+async fn qr_code_image_is_rendered_as_svg_on_request() {
+    let app = spawn_app().await;
+    let id = insert_qr_code(&app).await;
+
+    let response = reqwest::Client::new()
+        .get(&format!(
+            "{}/qr_code/image?id={}&format=svg&size=512&ecc=H",
+            app.address, id
+        ))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!("image/svg+xml", response.headers()["Content-Type"]);
+}
+
+#[actix_rt::test]
+async fn qr_code_image_returns_404_for_unknown_id() {
+    let app = spawn_app().await;
+
+    let response = reqwest::Client::new()
+        .get(&format!(
+            "{}/qr_code/image?id={}",
+            app.address,
+            Uuid::new_v4()
+        ))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[actix_rt::test]
+async fn qr_code_image_rejects_out_of_range_size() {
+    let app = spawn_app().await;
+    let id = insert_qr_code(&app).await;
+
+    let response = reqwest::Client::new()
+        .get(&format!("{}/qr_code/image?id={}&size=1", app.address, id))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[actix_rt::test]
+async fn edit_qr_code_rejects_invalid_colors() {
+    let app = spawn_app().await;
+    let id = insert_qr_code(&app).await;
+    let token = login(
+        &app,
+        app.test_user.username.to_string(),
+        app.test_user.password.to_string(),
+    )
+    .await
+    .text()
+    .await
+    .unwrap();
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/qr_code/edit", app.address))
+        .header("Authorization", token)
+        .json(&serde_json::json!({ "id": id, "foreground_color": "blue" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[actix_rt::test]
+async fn qr_code_image_uses_stored_style() {
+    let app = spawn_app().await;
+    let id = insert_qr_code(&app).await;
+    sqlx::query("UPDATE qr_code SET foreground_color = '#123456' WHERE id = $1")
+        .bind(id)
+        .execute(&app.db_pool)
+        .await
+        .expect("Failed to update QR code");
+
+    let response = reqwest::Client::new()
+        .get(format!(
+            "{}/qr_code/image?id={}&format=svg",
+            app.address, id
+        ))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(200, response.status().as_u16());
+    assert!(response.text().await.unwrap().contains("#123456"));
+}
+
+#[actix_rt::test]
+async fn qr_code_sheet_is_rendered_as_pdf() {
+    let app = spawn_app().await;
+    let id = insert_qr_code(&app).await;
+    insert_qr_code(&app).await;
+    let token = login(
+        &app,
+        app.test_user.username.to_string(),
+        app.test_user.password.to_string(),
+    )
+    .await
+    .text()
+    .await
+    .unwrap();
+
+    let response = reqwest::Client::new()
+        .get(format!(
+            "{}/qr_codes/sheet.pdf?ids={}&layout=avery-5160",
+            app.address, id
+        ))
+        .header("Authorization", token.clone())
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!("application/pdf", response.headers()["Content-Type"]);
+    assert_eq!(b"%PDF", &response.bytes().await.unwrap()[..4]);
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/qr_codes/sheet.pdf", app.address))
+        .header("Authorization", token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[actix_rt::test]
+async fn qr_code_sheet_returns_404_for_unknown_ids() {
+    let app = spawn_app().await;
+    let token = login(
+        &app,
+        app.test_user.username.to_string(),
+        app.test_user.password.to_string(),
+    )
+    .await
+    .text()
+    .await
+    .unwrap();
+
+    let response = reqwest::Client::new()
+        .get(format!(
+            "{}/qr_codes/sheet.pdf?ids={}",
+            app.address,
+            Uuid::new_v4()
+        ))
+        .header("Authorization", token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[actix_rt::test]
+async fn scanning_a_qr_code_is_reported_in_its_stats() {
+    let app = spawn_app().await;
+    let id = insert_qr_code(&app).await;
+    let client = reqwest::Client::new();
+
+    for forwarded_for in ["203.0.113.7", "203.0.113.99", "198.51.100.1"] {
+        let response = client
+            .get(format!("{}/scan?id={}", app.address, id))
+            .header("User-Agent", "test-scanner")
+            .header("X-Forwarded-For", forwarded_for)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert_eq!(200, response.status().as_u16());
+    }
+
+    let (user_agent, client_hash): (Option<String>, Option<String>) = sqlx::query_as(
+        "SELECT user_agent, client_hash FROM scan_event WHERE qr_code_id = $1 LIMIT 1",
+    )
+    .bind(id)
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to fetch scan event");
+    assert_eq!(Some("test-scanner".to_string()), user_agent);
+    assert!(!client_hash.unwrap().contains("203.0.113"));
+
+    let token = login(
+        &app,
+        app.test_user.username.to_string(),
+        app.test_user.password.to_string(),
+    )
+    .await
+    .text()
+    .await
+    .unwrap();
+    let response = client
+        .get(format!("{}/qr_code/stats?id={}&days=7", app.address, id))
+        .header("Authorization", token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+
+    let stats: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(3, stats["total_scans"]);
+    // The first two scans come from the same /24 network
+    assert_eq!(2, stats["unique_scanners"]);
+    assert_eq!(7, stats["daily"].as_array().unwrap().len());
+    assert_eq!(3, stats["daily"][6]["scans"]);
+    assert_eq!(48, stats["hourly"].as_array().unwrap().len());
+    assert_eq!(3, stats["hourly"][47]["scans"]);
+}
+
+#[actix_rt::test]
+async fn qr_code_stats_are_only_visible_to_the_owner() {
+    let app = spawn_app().await;
+    let other_user = hermod_api::db::NewUser::default();
+    other_user.store(&app.db_pool).await.unwrap();
+    let id = Uuid::new_v4();
+    sqlx::query("INSERT INTO qr_code (id, account_id) VALUES ($1, $2)")
+        .bind(id)
+        .bind(other_user.id)
+        .execute(&app.db_pool)
+        .await
+        .expect("Failed to insert QR code");
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/qr_code/stats?id={}", app.address, id))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(401, response.status().as_u16());
+
+    let token = login(
+        &app,
+        app.test_user.username.to_string(),
+        app.test_user.password.to_string(),
+    )
+    .await
+    .text()
+    .await
+    .unwrap();
+    let response = reqwest::Client::new()
+        .get(format!("{}/qr_code/stats?id={}", app.address, id))
+        .header("Authorization", token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(401, response.status().as_u16());
+}
+
+async fn insert_notifying_qr_code(app: &TestApp, code_cooldown: i32, client_cooldown: i32) -> Uuid {
+    let id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO qr_code (id, account_id, email, payload, code_cooldown_seconds, client_cooldown_seconds)
+         VALUES ($1, $2, 'staff@hermodapp.com', 'Table 4 needs help', $3, $4)",
+    )
+    .bind(id)
+    .bind(app.test_user.id)
+    .bind(code_cooldown)
+    .bind(client_cooldown)
+    .execute(&app.db_pool)
+    .await
+    .expect("Failed to insert QR code");
+    id
+}
+
+#[actix_rt::test]
+async fn repeated_scans_within_the_cooldown_are_throttled() {
+    let app = spawn_app().await;
+    let id = insert_notifying_qr_code(&app, 300, 900).await;
+    Mock::given(method("POST"))
+        .and(path("/email"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let first = scan_from(&app, id, "203.0.113.7").await;
+    let second = scan_from(&app, id, "203.0.113.7").await;
+    let other_client = scan_from(&app, id, "198.51.100.1").await;
+
+    assert!(!first.contains("already been notified"));
+    assert!(second.contains("already been notified"));
+    assert!(other_client.contains("already been notified"));
+
+    let flags: Vec<(bool, bool)> = sqlx::query_as(
+        "SELECT notified, throttled FROM scan_event WHERE qr_code_id = $1 ORDER BY scanned_at",
+    )
+    .bind(id)
+    .fetch_all(&app.db_pool)
+    .await
+    .expect("Failed to fetch scan events");
+    assert_eq!(vec![(true, false), (false, true), (false, true)], flags);
+    wait_for_delivery_attempts(&app, id).await;
+}
+
+#[actix_rt::test]
+async fn other_clients_are_notified_once_the_code_cooldown_is_disabled() {
+    let app = spawn_app().await;
+    let id = insert_notifying_qr_code(&app, 0, 900).await;
+    Mock::given(method("POST"))
+        .and(path("/email"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    scan_from(&app, id, "203.0.113.7").await;
+    let same_client = scan_from(&app, id, "203.0.113.7").await;
+    let other_client = scan_from(&app, id, "198.51.100.1").await;
+
+    assert!(same_client.contains("already been notified"));
+    assert!(!other_client.contains("already been notified"));
+    wait_for_delivery_attempts(&app, id).await;
+}
+
+#[actix_rt::test]
+async fn edit_qr_code_rejects_excessive_cooldowns() {
+    let app = spawn_app().await;
+    let id = insert_qr_code(&app).await;
+    let token = login(
+        &app,
+        app.test_user.username.to_string(),
+        app.test_user.password.to_string(),
+    )
+    .await
+    .text()
+    .await
+    .unwrap();
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/qr_code/edit", app.address))
+        .header("Authorization", token)
+        .json(&serde_json::json!({ "id": id, "code_cooldown_seconds": 1_000_000 }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[actix_rt::test]
+async fn scans_succeed_while_notifications_are_retried() {
+    let app = spawn_app().await;
+    let id = insert_notifying_qr_code(&app, 300, 900).await;
+    Mock::given(method("POST"))
+        .and(path("/email"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    scan_from(&app, id, "203.0.113.7").await;
+    wait_for_delivery_attempts(&app, id).await;
+
+    let (status, attempts, last_error, retry_scheduled): (String, i32, Option<String>, bool) =
+        sqlx::query_as(
+            "SELECT status, attempts, last_error, next_attempt_at > now() AT TIME ZONE 'utc'
+             FROM notification WHERE qr_code_id = $1",
+        )
+        .bind(id)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch notification");
+    assert_eq!("pending", status);
+    assert_eq!(1, attempts);
+    assert!(last_error.is_some());
+    assert!(retry_scheduled);
+}
+
+#[actix_rt::test]
+async fn notifications_are_dead_lettered_after_the_last_attempt() {
+    let app = spawn_app().await;
+    let id = insert_notifying_qr_code(&app, 300, 900).await;
+    Mock::given(method("POST"))
+        .and(path("/email"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&app.email_server)
+        .await;
+
+    scan_from(&app, id, "203.0.113.7").await;
+    wait_for_delivery_attempts(&app, id).await;
+    // Skip ahead to the final retry
+    sqlx::query(
+        "UPDATE notification SET attempts = $2, next_attempt_at = now() AT TIME ZONE 'utc' - interval '1 second'
+         WHERE qr_code_id = $1",
+    )
+    .bind(id)
+    .bind(MAX_ATTEMPTS - 1)
+    .execute(&app.db_pool)
+    .await
+    .expect("Failed to update notification");
+    wait_for_delivery_attempts(&app, id).await;
+
+    let (status, attempts): (String, i32) =
+        sqlx::query_as("SELECT status, attempts FROM notification WHERE qr_code_id = $1")
+            .bind(id)
+            .fetch_one(&app.db_pool)
+            .await
+            .expect("Failed to fetch notification");
+    assert_eq!("dead", status);
+    assert_eq!(MAX_ATTEMPTS, attempts);
+}
+
+#[actix_rt::test]
+async fn qr_code_notifications_list_delivery_attempts() {
+    let app = spawn_app().await;
+    let id = insert_notifying_qr_code(&app, 300, 900).await;
+    sqlx::query("UPDATE qr_code SET phone_number = '+15555550123' WHERE id = $1")
+        .bind(id)
+        .execute(&app.db_pool)
+        .await
+        .expect("Failed to update QR code");
+    Mock::given(method("POST"))
+        .and(path("/email"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(1)
+        .mount(&app.twilio_server)
+        .await;
+
+    scan_from(&app, id, "203.0.113.7").await;
+    wait_for_delivery_attempts(&app, id).await;
+
+    let token = login(
+        &app,
+        app.test_user.username.to_string(),
+        app.test_user.password.to_string(),
+    )
+    .await
+    .text()
+    .await
+    .unwrap();
+    let response = reqwest::Client::new()
+        .get(format!("{}/qr_code/notifications?id={}", app.address, id))
+        .header("Authorization", token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+
+    let body: serde_json::Value = response.json().await.unwrap();
+    let notifications = body["notifications"].as_array().unwrap();
+    assert_eq!(2, notifications.len());
+    for notification in notifications {
+        assert_eq!("delivered", notification["status"]);
+        assert_eq!(
+            1,
+            notification["delivery_attempts"].as_array().unwrap().len()
+        );
+        assert_eq!(true, notification["delivery_attempts"][0]["succeeded"]);
+    }
+}
+
+#[actix_rt::test]
+async fn generated_qr_codes_can_notify_by_sms() {
+    let app = spawn_app().await;
+    let token = login(
+        &app,
+        app.test_user.username.to_string(),
+        app.test_user.password.to_string(),
+    )
+    .await
+    .text()
+    .await
+    .unwrap();
+    let response = reqwest::Client::new()
+        .post(format!("{}/qr_code/generate", app.address))
+        .header("Authorization", token)
+        .json(&serde_json::json!({
+            "phone_number": "+15555550123",
+            "payload": "Table 4 needs help",
+            "notification_channel": "sms"
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    let id = Uuid::parse_str(body["id"].as_str().unwrap()).unwrap();
+
+    Mock::given(method("POST"))
+        .and(path("/Accounts/DO_NOT_USE/Messages"))
+        .and(body_string_contains("To=%2B15555550123"))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(1)
+        .mount(&app.twilio_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/Accounts/DO_NOT_USE/Calls.json"))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(0)
+        .mount(&app.twilio_server)
+        .await;
+
+    scan_from(&app, id, "203.0.113.7").await;
+    wait_for_delivery_attempts(&app, id).await;
+}
+
+#[actix_rt::test]
+async fn phone_notifications_can_be_turned_off() {
+    let app = spawn_app().await;
+    let id = insert_notifying_qr_code(&app, 300, 900).await;
+    let token = login(
+        &app,
+        app.test_user.username.to_string(),
+        app.test_user.password.to_string(),
+    )
+    .await
+    .text()
+    .await
+    .unwrap();
+    let response = reqwest::Client::new()
+        .get(format!("{}/qr_code/edit", app.address))
+        .header("Authorization", token)
+        .json(&serde_json::json!({
+            "id": id,
+            "phone_number": "+15555550123",
+            "email": "staff@hermodapp.com",
+            "payload": "Table 4 needs help",
+            "notification_channel": "none"
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(0)
+        .mount(&app.twilio_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/email"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    scan_from(&app, id, "203.0.113.7").await;
+    wait_for_delivery_attempts(&app, id).await;
+}
+
+#[actix_rt::test]
+async fn edit_qr_code_rejects_unknown_notification_channels() {
+    let app = spawn_app().await;
+    let id = insert_qr_code(&app).await;
+    let token = login(
+        &app,
+        app.test_user.username.to_string(),
+        app.test_user.password.to_string(),
+    )
+    .await
+    .text()
+    .await
+    .unwrap();
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/qr_code/edit", app.address))
+        .header("Authorization", token)
+        .json(&serde_json::json!({ "id": id, "notification_channel": "pigeon" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[actix_rt::test]
+async fn qr_codes_with_invalid_payload_templates_are_rejected() {
+    let app = spawn_app().await;
+    let id = insert_qr_code(&app).await;
+    let token = login(
+        &app,
+        app.test_user.username.to_string(),
+        app.test_user.password.to_string(),
+    )
+    .await
+    .text()
+    .await
+    .unwrap();
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/qr_code/generate", app.address))
+        .header("Authorization", token.clone())
+        .json(&serde_json::json!({ "payload": "Table {{qr.table}} needs help" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(400, response.status().as_u16());
+    assert!(response.text().await.unwrap().contains("qr.table"));
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/qr_code/edit", app.address))
+        .header("Authorization", token)
+        .json(&serde_json::json!({ "id": id, "payload": "Help at {{scan.time | upper}}" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[actix_rt::test]
+async fn qr_codes_with_invalid_email_addresses_are_rejected() {
+    let app = spawn_app().await;
+    let id = insert_qr_code(&app).await;
+    let token = app.token().await;
+    let email = "staff@hermodapp.com>\r\nRCPT TO:<attacker@example.com";
+
+    let response = app
+        .post_json(
+            &token,
+            "/qr_code/generate",
+            serde_json::json!({ "email": email }),
+        )
+        .await;
+    assert_eq!(400, response.status().as_u16());
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/qr_code/edit", app.address))
+        .header("Authorization", token)
+        .json(&serde_json::json!({ "id": id, "email": email }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(400, response.status().as_u16());
+    let (stored,): (Option<String>,) = sqlx::query_as("SELECT email FROM qr_code WHERE id = $1")
+        .bind(id)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch QR code");
+    assert_eq!(None, stored);
+}
+
+#[actix_rt::test]
+async fn payload_templates_are_rendered_for_each_channel() {
+    let app = spawn_app().await;
+    let id = insert_notifying_qr_code(&app, 300, 900).await;
+    sqlx::query(
+        "UPDATE qr_code
+         SET label = '4 & 5', phone_number = '+15555550123', utc_offset_minutes = -360,
+             payload = 'Table {{qr.label}} needs help at {{scan.time | local | time}}'
+         WHERE id = $1",
+    )
+    .bind(id)
+    .execute(&app.db_pool)
+    .await
+    .expect("Failed to update QR code");
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(201))
+        .mount(&app.twilio_server)
+        .await;
+
+    scan_from(&app, id, "203.0.113.7").await;
+    wait_for_delivery_attempts(&app, id).await;
+
+    let (scanned_at,): (chrono::NaiveDateTime,) =
+        sqlx::query_as("SELECT scanned_at FROM scan_event WHERE qr_code_id = $1")
+            .bind(id)
+            .fetch_one(&app.db_pool)
+            .await
+            .expect("Failed to fetch scan event");
+    let local_time = (scanned_at - chrono::Duration::hours(6))
+        .format("%H:%M")
+        .to_string();
+
+    let notifications: Vec<(String, String, Option<String>)> = sqlx::query_as(
+        "SELECT channel, message, html_message FROM notification WHERE qr_code_id = $1 ORDER BY channel",
+    )
+    .bind(id)
+    .fetch_all(&app.db_pool)
+    .await
+    .expect("Failed to fetch notifications");
+    assert_eq!(2, notifications.len());
+    let (channel, twiml, _) = &notifications[0];
+    assert_eq!("call", channel);
+    assert!(twiml.starts_with(&format!(
+        "<Response><Say>Table 4 &amp; 5 needs help at {}</Say><Gather",
+        local_time
+    )));
+    let (channel, text, html) = &notifications[1];
+    assert_eq!("email", channel);
+    assert!(text.starts_with(&format!(
+        "Table 4 & 5 needs help at {}\n\nAcknowledge this alert: ",
+        local_time
+    )));
+    assert!(html.as_ref().unwrap().starts_with(&format!(
+        "<p>Table 4 &amp; 5 needs help at {}</p><p><a href=",
+        local_time
+    )));
+}
+
+#[actix_rt::test]
+async fn calls_use_the_qr_codes_voice_settings() {
+    let app = spawn_app().await;
+    let id = insert_qr_code(&app).await;
+    let token = login(
+        &app,
+        app.test_user.username.to_string(),
+        app.test_user.password.to_string(),
+    )
+    .await
+    .text()
+    .await
+    .unwrap();
+    let response = reqwest::Client::new()
+        .get(format!("{}/qr_code/edit", app.address))
+        .header("Authorization", token)
+        .json(&serde_json::json!({
+            "id": id,
+            "phone_number": "+15555550123",
+            "payload": "La mesa <4> necesita ayuda",
+            "voice": "Polly.Lupe",
+            "voice_language": "es-US",
+            "voice_loop": 2
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(1)
+        .mount(&app.twilio_server)
+        .await;
+
+    scan_from(&app, id, "203.0.113.7").await;
+    wait_for_delivery_attempts(&app, id).await;
+
+    let requests = app.twilio_server.received_requests().await.unwrap();
+    let body = String::from_utf8(requests[0].body.clone()).unwrap();
+    let say = r#"<Response><Say voice="Polly.Lupe" language="es-US" loop="2">La mesa &lt;4&gt; necesita ayuda</Say>"#;
+    assert!(body.starts_with(&format!("Twiml={}", urlencoding::encode(say))));
+    // The acknowledgement prompt is read with the same voice
+    let prompt =
+        r#"<Say voice="Polly.Lupe" language="es-US">Press 1 to acknowledge this alert.</Say>"#;
+    assert!(body.contains(&*urlencoding::encode(prompt)));
+}
+
+#[actix_rt::test]
+async fn edit_qr_code_rejects_invalid_voice_settings() {
+    let app = spawn_app().await;
+    let id = insert_qr_code(&app).await;
+    let token = login(
+        &app,
+        app.test_user.username.to_string(),
+        app.test_user.password.to_string(),
+    )
+    .await
+    .text()
+    .await
+    .unwrap();
+
+    for settings in [
+        serde_json::json!({ "id": id, "voice": "<alice>" }),
+        serde_json::json!({ "id": id, "voice_language": "Spanish" }),
+        serde_json::json!({ "id": id, "voice_loop": 0 }),
+        serde_json::json!({ "id": id, "voice_loop": 11 }),
+    ] {
+        let response = reqwest::Client::new()
+            .get(format!("{}/qr_code/edit", app.address))
+            .header("Authorization", token.clone())
+            .json(&settings)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert_eq!(400, response.status().as_u16());
+    }
+}
@@ -0,0 +1,205 @@
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{insert_qr_code, scan_from, spawn_app, wait_for_delivery_attempts, TestApp};
+
+async fn create_recipient(
+    app: &TestApp,
+    token: &str,
+    body: serde_json::Value,
+) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}/qr_code/recipients", app.address))
+        .header("Authorization", token)
+        .json(&body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn list_recipients(app: &TestApp, token: &str, qr_code_id: Uuid) -> serde_json::Value {
+    let response = reqwest::Client::new()
+        .get(format!(
+            "{}/qr_code/recipients?qr_code_id={}",
+            app.address, qr_code_id
+        ))
+        .header("Authorization", token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    response.json().await.unwrap()
+}
+
+#[actix_rt::test]
+async fn recipients_can_be_created_edited_and_deleted() {
+    let app = spawn_app().await;
+    let qr_code_id = insert_qr_code(&app).await;
+    let token = app.token().await;
+
+    let response = create_recipient(
+        &app,
+        &token,
+        serde_json::json!({
+            "qr_code_id": qr_code_id,
+            "name": "Manager",
+            "channel": "sms",
+            "address": "+15555550123",
+            "quiet_hours_start": "22:00:00",
+            "quiet_hours_end": "07:00:00",
+            "utc_offset_minutes": -360
+        }),
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+    let id = response.json::<serde_json::Value>().await.unwrap()["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let body = list_recipients(&app, &token, qr_code_id).await;
+    assert_eq!(1, body["recipients"].as_array().unwrap().len());
+    assert_eq!("sms", body["recipients"][0]["channel"]);
+    assert_eq!("22:00:00", body["recipients"][0]["quiet_hours_start"]);
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/qr_code/recipients/edit", app.address))
+        .header("Authorization", token.clone())
+        .json(&serde_json::json!({
+            "id": id,
+            "channel": "email",
+            "address": "manager@hermodapp.com",
+            "active": false
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+
+    let body = list_recipients(&app, &token, qr_code_id).await;
+    assert_eq!("email", body["recipients"][0]["channel"]);
+    assert_eq!(false, body["recipients"][0]["active"]);
+    assert!(body["recipients"][0]["quiet_hours_start"].is_null());
+
+    let response = reqwest::Client::new()
+        .get(format!(
+            "{}/qr_code/recipients/delete?id={}",
+            app.address, id
+        ))
+        .header("Authorization", token.clone())
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+
+    let body = list_recipients(&app, &token, qr_code_id).await;
+    assert!(body["recipients"].as_array().unwrap().is_empty());
+}
+
+#[actix_rt::test]
+async fn recipients_with_invalid_settings_are_rejected() {
+    let app = spawn_app().await;
+    let qr_code_id = insert_qr_code(&app).await;
+    let token = app.token().await;
+
+    let invalid_recipients = vec![
+        serde_json::json!({ "qr_code_id": qr_code_id, "channel": "call", "address": "555-0123" }),
+        serde_json::json!({ "qr_code_id": qr_code_id, "channel": "email", "address": "+15555550123" }),
+        serde_json::json!({
+            "qr_code_id": qr_code_id,
+            "channel": "sms",
+            "address": "+15555550123",
+            "quiet_hours_start": "22:00:00"
+        }),
+        serde_json::json!({ "qr_code_id": qr_code_id, "channel": "fax", "address": "+15555550123" }),
+    ];
+    for body in invalid_recipients {
+        let response = create_recipient(&app, &token, body).await;
+        assert_eq!(400, response.status().as_u16());
+    }
+}
+
+#[actix_rt::test]
+async fn recipients_of_other_accounts_qr_codes_are_not_accessible() {
+    let app = spawn_app().await;
+    let other_user = hermod_api::db::NewUser::default();
+    other_user.store(&app.db_pool).await.unwrap();
+    let qr_code_id = Uuid::new_v4();
+    sqlx::query("INSERT INTO qr_code (id, account_id) VALUES ($1, $2)")
+        .bind(qr_code_id)
+        .bind(other_user.id)
+        .execute(&app.db_pool)
+        .await
+        .expect("Failed to insert QR code");
+    let token = app.token().await;
+
+    let response = create_recipient(
+        &app,
+        &token,
+        serde_json::json!({ "qr_code_id": qr_code_id, "channel": "sms", "address": "+15555550123" }),
+    )
+    .await;
+    assert_eq!(401, response.status().as_u16());
+
+    let response = reqwest::Client::new()
+        .get(format!(
+            "{}/qr_code/recipients?qr_code_id={}",
+            app.address, qr_code_id
+        ))
+        .header("Authorization", token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[actix_rt::test]
+async fn scans_fan_out_to_active_recipients() {
+    let app = spawn_app().await;
+    let qr_code_id = insert_qr_code(&app).await;
+    let token = app.token().await;
+
+    for body in [
+        serde_json::json!({ "qr_code_id": qr_code_id, "channel": "sms", "address": "+15555550123" }),
+        serde_json::json!({ "qr_code_id": qr_code_id, "channel": "email", "address": "manager@hermodapp.com" }),
+        serde_json::json!({
+            "qr_code_id": qr_code_id,
+            "channel": "call",
+            "address": "+15555550124",
+            "active": false
+        }),
+        serde_json::json!({
+            "qr_code_id": qr_code_id,
+            "channel": "call",
+            "address": "+15555550125",
+            "quiet_hours_start": "00:00:00",
+            "quiet_hours_end": "23:59:59"
+        }),
+    ] {
+        let response = create_recipient(&app, &token, body).await;
+        assert_eq!(200, response.status().as_u16());
+    }
+
+    Mock::given(method("POST"))
+        .and(path("/Accounts/DO_NOT_USE/Messages"))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(1)
+        .mount(&app.twilio_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/Accounts/DO_NOT_USE/Calls.json"))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(0)
+        .mount(&app.twilio_server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/email"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    scan_from(&app, qr_code_id, "203.0.113.7").await;
+    wait_for_delivery_attempts(&app, qr_code_id).await;
+}
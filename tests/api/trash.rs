@@ -0,0 +1,120 @@
+use chrono::{Duration, Utc};
+use hermod_api::services::trash::purge_deleted;
+use uuid::Uuid;
+
+use crate::helpers::{field_id, insert_qr_code, scan_from, spawn_app};
+
+#[actix_rt::test]
+async fn deleted_qr_codes_are_listed_in_the_trash_until_restored() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+    let id = insert_qr_code(&app).await;
+
+    let response = app.get(&token, &format!("/qr_code/delete?id={}", id)).await;
+    assert_eq!(200, response.status().as_u16());
+
+    let qr_codes: serde_json::Value = app.get(&token, "/qr_codes").await.json().await.unwrap();
+    assert!(qr_codes["qr_codes"].as_array().unwrap().is_empty());
+    let response = reqwest::Client::new()
+        .get(format!("{}/scan?id={}", app.address, id))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(404, response.status().as_u16());
+
+    let trash: serde_json::Value = app.get(&token, "/trash").await.json().await.unwrap();
+    assert_eq!(id.to_string(), trash["qr_codes"][0]["qr_code_id"]);
+
+    let response = app
+        .get(&token, &format!("/qr_code/restore?id={}", id))
+        .await;
+    assert_eq!(200, response.status().as_u16());
+    scan_from(&app, id, "203.0.113.7").await;
+    let trash: serde_json::Value = app.get(&token, "/trash").await.json().await.unwrap();
+    assert!(trash["qr_codes"].as_array().unwrap().is_empty());
+}
+
+#[actix_rt::test]
+async fn deleted_forms_stop_accepting_responses_until_restored() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+    let form_id = app.create_survey(&token).await;
+
+    let response = app
+        .get(&token, &format!("/form/delete?id={}", form_id))
+        .await;
+    assert_eq!(200, response.status().as_u16());
+
+    let forms: serde_json::Value = app.get(&token, "/form/view").await.json().await.unwrap();
+    assert!(forms["forms"].as_array().unwrap().is_empty());
+    let response = app
+        .get(&token, &format!("/form/submit?id={}", form_id))
+        .await;
+    assert_eq!(404, response.status().as_u16());
+
+    let trash: serde_json::Value = app.get(&token, "/trash").await.json().await.unwrap();
+    assert_eq!(form_id, trash["forms"][0]["form_id"]);
+    assert_eq!("Survey", trash["forms"][0]["title"]);
+
+    let response = app
+        .get(&token, &format!("/form/restore?id={}", form_id))
+        .await;
+    assert_eq!(200, response.status().as_u16());
+    let response = app
+        .get(&token, &format!("/form/submit?id={}", form_id))
+        .await;
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[actix_rt::test]
+async fn only_items_past_the_retention_period_are_purged() {
+    let app = spawn_app().await;
+    let token = app.token().await;
+    let form_id = app.create_survey(&token).await;
+    let rating = field_id(&app.get_form(&form_id).await, "Rating");
+    let response = app
+        .submit(
+            &form_id,
+            serde_json::json!({ "responses": [{ "field_id": rating, "content": "5" }] }),
+        )
+        .await;
+    assert_eq!(200, response.status().as_u16());
+    let form_id: Uuid = form_id.parse().unwrap();
+    let old_code = insert_qr_code(&app).await;
+    let recent_code = insert_qr_code(&app).await;
+    scan_from(&app, old_code, "203.0.113.7").await;
+
+    let now = Utc::now().naive_utc();
+    sqlx::query("UPDATE form SET deleted_at = $1 WHERE id = $2")
+        .bind(now - Duration::days(40))
+        .bind(form_id)
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    sqlx::query("UPDATE qr_code SET deleted_at = $1 WHERE id = $2")
+        .bind(now - Duration::days(40))
+        .bind(old_code)
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    sqlx::query("UPDATE qr_code SET deleted_at = $1 WHERE id = $2")
+        .bind(now - Duration::days(1))
+        .bind(recent_code)
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let purged = purge_deleted(&app.db_pool, now - Duration::days(30))
+        .await
+        .unwrap();
+    assert_eq!((1, 1), purged);
+    let forms: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM form WHERE id = $1")
+        .bind(form_id)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(0, forms);
+    let trash: serde_json::Value = app.get(&token, "/trash").await.json().await.unwrap();
+    assert_eq!(1, trash["qr_codes"].as_array().unwrap().len());
+    assert_eq!(recent_code.to_string(), trash["qr_codes"][0]["qr_code_id"]);
+}
@@ -0,0 +1,169 @@
+use uuid::Uuid;
+use wiremock::matchers::method;
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{
+    login, post_twilio_webhook, scan_from, spawn_app, wait_for_delivery_attempts, TestApp,
+};
+
+/// Inserts a QR code of the test user that notifies +15555550123 through `channel`.
+async fn insert_phone_qr_code(app: &TestApp, channel: &str) -> Uuid {
+    let id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO qr_code (id, account_id, phone_number, notification_channel, payload)
+         VALUES ($1, $2, '+15555550123', $3, 'Table 4 needs help')",
+    )
+    .bind(id)
+    .bind(app.test_user.id)
+    .bind(channel)
+    .execute(&app.db_pool)
+    .await
+    .expect("Failed to insert QR code");
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(201))
+        .mount(&app.twilio_server)
+        .await;
+    id
+}
+
+#[actix_rt::test]
+async fn webhooks_without_a_valid_signature_are_rejected() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let params = [("From", "+15555550123"), ("Body", "ACK")];
+
+    let response = client
+        .post(format!("{}/webhooks/twilio/sms", app.address))
+        .form(&params)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(401, response.status().as_u16());
+
+    let response = client
+        .post(format!("{}/webhooks/twilio/sms", app.address))
+        .header("X-Twilio-Signature", "RSOYDt4T1cUTdK1PDd93/VVr8B8=")
+        .form(&params)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(401, response.status().as_u16());
+
+    let response = client
+        .post(format!(
+            "{}/webhooks/twilio/voice-status?notification_id={}",
+            app.address,
+            Uuid::new_v4()
+        ))
+        .form(&[("CallStatus", "completed")])
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[actix_rt::test]
+async fn sms_replies_are_recorded_against_the_latest_notification() {
+    let app = spawn_app().await;
+    let id = insert_phone_qr_code(&app, "sms").await;
+    scan_from(&app, id, "203.0.113.7").await;
+    wait_for_delivery_attempts(&app, id).await;
+
+    let response = post_twilio_webhook(
+        &app,
+        "/webhooks/twilio/sms",
+        &[
+            ("MessageSid", "SM0123456789abcdef"),
+            ("From", "+15555550123"),
+            ("To", "+12321231234"),
+            ("Body", "On my way"),
+        ],
+    )
+    .await;
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!("text/xml", response.headers()["Content-Type"]);
+
+    let token = login(
+        &app,
+        app.test_user.username.to_string(),
+        app.test_user.password.to_string(),
+    )
+    .await
+    .text()
+    .await
+    .unwrap();
+    let response = reqwest::Client::new()
+        .get(format!("{}/qr_code/notifications?id={}", app.address, id))
+        .header("Authorization", token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let body: serde_json::Value = response.json().await.unwrap();
+    let replies = body["notifications"][0]["replies"].as_array().unwrap();
+    assert_eq!(1, replies.len());
+    assert_eq!("+15555550123", replies[0]["sender"]);
+    assert_eq!("On my way", replies[0]["body"]);
+    assert_eq!("SM0123456789abcdef", replies[0]["provider_sid"]);
+}
+
+#[actix_rt::test]
+async fn call_status_callbacks_are_recorded_against_their_notification() {
+    let app = spawn_app().await;
+    let id = insert_phone_qr_code(&app, "call").await;
+    scan_from(&app, id, "203.0.113.7").await;
+    wait_for_delivery_attempts(&app, id).await;
+
+    let (notification_id,): (Uuid,) =
+        sqlx::query_as("SELECT id FROM notification WHERE qr_code_id = $1")
+            .bind(id)
+            .fetch_one(&app.db_pool)
+            .await
+            .expect("Failed to fetch notification");
+    let path = format!(
+        "/webhooks/twilio/voice-status?notification_id={}",
+        notification_id
+    );
+    // The call asks Twilio to report its outcome to the notification's callback
+    let requests = app.twilio_server.received_requests().await.unwrap();
+    let body = String::from_utf8(requests[0].body.clone()).unwrap();
+    let callback = format!("{}{}", app.base_url, path);
+    assert!(body.ends_with(&format!(
+        "&StatusCallback={}",
+        urlencoding::encode(&callback)
+    )));
+
+    let response = post_twilio_webhook(
+        &app,
+        &path,
+        &[
+            ("CallSid", "CA0123456789abcdef"),
+            ("CallStatus", "no-answer"),
+        ],
+    )
+    .await;
+    assert_eq!(204, response.status().as_u16());
+    let status: (Option<String>, Option<String>) =
+        sqlx::query_as("SELECT provider_sid, provider_status FROM notification WHERE id = $1")
+            .bind(notification_id)
+            .fetch_one(&app.db_pool)
+            .await
+            .expect("Failed to fetch notification");
+    assert_eq!(
+        (
+            Some("CA0123456789abcdef".to_string()),
+            Some("no-answer".to_string())
+        ),
+        status
+    );
+
+    let response = post_twilio_webhook(
+        &app,
+        &format!(
+            "/webhooks/twilio/voice-status?notification_id={}",
+            Uuid::new_v4()
+        ),
+        &[("CallStatus", "completed")],
+    )
+    .await;
+    assert_eq!(404, response.status().as_u16());
+}